@@ -4,6 +4,7 @@ use std::sync::Mutex;
 
 use crate::border::QuadElement;
 use crate::draw::pointer::PointerElement;
+use crate::draw::thumbnail::ThumbnailElement;
 use slog::Logger;
 use slog_scope::warn;
 use smithay::backend::renderer::gles2::{Gles2Renderbuffer, Gles2Renderer};
@@ -20,10 +21,13 @@ pub type GlMultiRenderer<'a> =
     MultiRenderer<'a, 'a, EglGlesBackend, EglGlesBackend, Gles2Renderbuffer>;
 pub type GlMultiFrame = MultiFrame<EglGlesBackend, EglGlesBackend>;
 
-pub const TILING_Z_INDEX: u8 = 100;
-pub const BORDER_Z_INDEX: u8 = 102;
-pub const POP_UP_Z_INDEX: u8 = 103;
-pub const FLOATING_Z_INDEX: u8 = 104;
+// smithay draws layer surfaces at fixed z-indices: background 10, bottom 20, top 40, overlay 60.
+// Windows go between the bottom and top layers so panels stay above them.
+pub const TILING_Z_INDEX: u8 = 30;
+pub const BORDER_Z_INDEX: u8 = 32;
+pub const POP_UP_Z_INDEX: u8 = 33;
+pub const FLOATING_Z_INDEX: u8 = 34;
+pub const OVERLAY_Z_INDEX: u8 = 200;
 pub const CURSOR_Z_INDEX: u8 = 255;
 
 smithay::custom_elements! {
@@ -31,6 +35,7 @@ smithay::custom_elements! {
     Quad=QuadElement,
     SurfaceTree=SurfaceTree,
     PointerElement=PointerElement,
+    Thumbnail=ThumbnailElement,
 }
 
 // From cosmic-comp https://github.com/pop-os/cosmic-comp/blob/master_jammy/src/backend/render/mod.rs#L42-L126
@@ -112,24 +112,15 @@ impl DrmDevice {
             .filter_map(|conn| drm.get_connector(*conn, false).ok())
         {
             let handle = conn.handle();
-            let curr_state = conn.state();
+            let connected = is_connected(&conn);
 
             let old = self.connectors.insert(handle, conn);
+            let was_connected = old.as_ref().map_or(false, is_connected);
 
-            if let Some(old) = old {
-                use connector::State;
-                match (old.state(), curr_state) {
-                    (State::Connected, State::Disconnected) => removed.push(handle),
-                    (State::Disconnected, State::Connected) => added.push(handle),
-                    //
-                    (State::Connected, State::Connected) => {}
-                    (State::Disconnected, State::Disconnected) => {}
-                    //
-                    (State::Unknown, _) => todo!(),
-                    (_, State::Unknown) => todo!(),
-                }
-            } else {
-                added.push(handle)
+            match (was_connected, connected) {
+                (true, false) => removed.push(handle),
+                (false, true) => added.push(handle),
+                _ => {}
             }
         }
 
@@ -138,23 +129,13 @@ impl DrmDevice {
         let connector_info = connector_handles
             .iter()
             .map(|conn| drm.get_connector(*conn, false).unwrap())
-            .filter(|conn| conn.state() == connector::State::Connected)
+            .filter(is_connected)
             .inspect(|conn| info!("Connected: {:?}", conn.interface()));
 
         for connector in connector_info {
-            let encoder_infos = connector
-                .encoders()
-                .iter()
-                .map(|e| *e)
-                .flat_map(|encoder_handle| drm.get_encoder(encoder_handle));
-
-            'outer: for encoder_info in encoder_infos {
-                for crtc in res_handles.filter_crtcs(encoder_info.possible_crtcs()) {
-                    if !connectors.values().any(|v| *v == crtc) {
-                        connectors.insert(connector.handle(), crtc);
-                        break 'outer;
-                    }
-                }
+            let used: Vec<crtc::Handle> = connectors.values().copied().collect();
+            if let Some(crtc) = self.free_crtc(&connector, &used) {
+                connectors.insert(connector.handle(), crtc);
             }
         }
 
@@ -164,6 +145,24 @@ impl DrmDevice {
             removed,
         }
     }
+
+    /// A crtc able to drive the connector and not in `used`
+    pub fn free_crtc(&self, connector: &connector::Info, used: &[crtc::Handle]) -> Option<crtc::Handle> {
+        let drm = self.drm.as_drm();
+        let res_handles = drm.resource_handles().ok()?;
+
+        connector
+            .encoders()
+            .iter()
+            .flat_map(|encoder_handle| drm.get_encoder(*encoder_handle))
+            .flat_map(|encoder_info| res_handles.filter_crtcs(encoder_info.possible_crtcs()))
+            .find(|crtc| !used.contains(crtc))
+    }
+}
+
+/// Connectors in an unknown state are handled as disconnected
+fn is_connected(connector: &connector::Info) -> bool {
+    connector.state() == connector::State::Connected
 }
 
 #[derive(Debug)]
@@ -0,0 +1,122 @@
+use smithay::reexports::drm::control::{connector, Device as ControlDevice};
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+const DESCRIPTOR_NAME: u8 = 0xfc;
+const DESCRIPTOR_SERIAL: u8 = 0xff;
+
+/// Monitor description from the EDID blob of a connector
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EdidInfo {
+    /// Three letters PNP manufacturer id, for example `DEL`
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+}
+
+/// Read the `EDID` property of a connector
+pub fn read<D: ControlDevice>(drm: &D, connector: connector::Handle) -> Option<EdidInfo> {
+    let properties = drm.get_properties(connector).ok()?;
+    let (handles, values) = properties.as_props_and_values();
+
+    handles
+        .iter()
+        .zip(values)
+        .find(|(handle, _)| {
+            drm.get_property(**handle)
+                .map(|info| info.name().to_bytes() == b"EDID")
+                .unwrap_or(false)
+        })
+        .and_then(|(_, blob)| drm.get_property_blob(*blob).ok())
+        .and_then(|blob| parse(&blob))
+}
+
+pub fn parse(edid: &[u8]) -> Option<EdidInfo> {
+    if edid.len() < 128 || edid[..8] != HEADER {
+        return None;
+    }
+
+    let manufacturer = u16::from_be_bytes([edid[8], edid[9]]);
+    let make = [10, 5, 0]
+        .iter()
+        .map(|shift| (((manufacturer >> shift) & 0x1f) as u8 + b'A' - 1) as char)
+        .collect();
+
+    let product = u16::from_le_bytes([edid[10], edid[11]]);
+    let serial_number = u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]);
+
+    let descriptor = |tag: u8| {
+        DESCRIPTORS
+            .iter()
+            .map(|offset| &edid[*offset..offset + 18])
+            .find(|descriptor| descriptor[..3] == [0, 0, 0] && descriptor[3] == tag)
+            .map(|descriptor| {
+                let text = &descriptor[5..];
+                let end = text.iter().position(|c| *c == b'\n').unwrap_or(text.len());
+                String::from_utf8_lossy(&text[..end]).trim().to_string()
+            })
+    };
+
+    let model = descriptor(DESCRIPTOR_NAME).unwrap_or_else(|| format!("0x{:04X}", product));
+    let serial = descriptor(DESCRIPTOR_SERIAL).unwrap_or_else(|| match serial_number {
+        0 => String::new(),
+        serial => serial.to_string(),
+    });
+
+    Some(EdidInfo {
+        make,
+        model,
+        serial,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::drm::edid::{parse, EdidInfo, HEADER};
+    use speculoos::prelude::*;
+
+    fn edid() -> Vec<u8> {
+        let mut edid = vec![0; 128];
+        edid[..8].copy_from_slice(&HEADER);
+        // "DEL"
+        edid[8..10].copy_from_slice(&0x10acu16.to_be_bytes());
+        edid[10..12].copy_from_slice(&0xa0b1u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&42u32.to_le_bytes());
+        edid
+    }
+
+    fn descriptor(edid: &mut [u8], offset: usize, tag: u8, text: &str) {
+        edid[offset + 3] = tag;
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(b'\n');
+        bytes.resize(13, b' ');
+        edid[offset + 5..offset + 18].copy_from_slice(&bytes);
+    }
+
+    #[test]
+    fn should_read_descriptors() {
+        let mut edid = edid();
+        descriptor(&mut edid, 72, 0xfc, "DELL U2720Q");
+        descriptor(&mut edid, 90, 0xff, "ABC123");
+
+        assert_that!(parse(&edid)).is_equal_to(Some(EdidInfo {
+            make: "DEL".to_string(),
+            model: "DELL U2720Q".to_string(),
+            serial: "ABC123".to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_fall_back_to_product_code_and_serial_number() {
+        assert_that!(parse(&edid())).is_equal_to(Some(EdidInfo {
+            make: "DEL".to_string(),
+            model: "0xA0B1".to_string(),
+            serial: "42".to_string(),
+        }));
+    }
+
+    #[test]
+    fn should_reject_invalid_header() {
+        assert_that!(parse(&[0; 128])).is_none();
+    }
+}
@@ -1,10 +1,11 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::path::Path;
 use std::rc::Rc;
 
 use super::device::{Device, DrmDevice};
-use crate::backend::drm::{DrmOutputId, DrmRenderer};
-use crate::backend::BackendHandler;
+use crate::backend::drm::{DrmOutputId, DrmRenderer, OUTPUT_ID_MAP};
+use crate::backend::{BackendHandler, NewOutputDescriptor, OutputId};
 use eyre::Result;
 use indexmap::IndexMap;
 use slog_scope::{error, info};
@@ -17,15 +18,19 @@ use smithay::backend::renderer::{Bind, Frame, ImportMem, Renderer};
 use smithay::backend::session::auto::AutoSession;
 use smithay::backend::session::Signal as SessionSignal;
 use smithay::reexports::calloop::LoopHandle;
-use smithay::reexports::drm::control::{crtc, Device as _, ModeTypeFlags};
+use smithay::backend::allocator::Format;
+use smithay::reexports::drm::control::{connector, crtc, Device as _, ModeTypeFlags};
 use smithay::reexports::gbm::Device as GbmDevice;
 use smithay::utils::signaling::{Linkable, Signaler};
-use smithay::utils::{Rectangle, Size};
-use smithay::output::Mode as WlMode;
+use smithay::utils::{Rectangle, Size, Transform};
+use smithay::output::{Mode as WlMode, PhysicalProperties, Subpixel};
 
 pub struct Gpu {
     drm: DrmDevice,
     drm_node: DrmNode,
+    gbm: Rc<RefCell<GbmDevice<Device>>>,
+    formats: HashSet<Format>,
+    session_signal: Signaler<SessionSignal>,
     pub outputs: IndexMap<crtc::Handle, GpuConnector>,
 }
 
@@ -76,63 +81,109 @@ impl Gpu {
             context.dmabuf_render_formats().clone()
         };
 
-        let mut outputs: IndexMap<crtc::Handle, GpuConnector> = IndexMap::new();
+        let mut gpu = Gpu {
+            drm,
+            drm_node,
+            gbm,
+            formats,
+            session_signal,
+            outputs: IndexMap::new(),
+        };
 
         for (conn, crtc) in res.map {
-            let drm = drm.inner();
+            gpu.setup_connector(conn, crtc)?;
+        }
 
-            let connector_info = drm.get_connector(conn, false).unwrap();
+        Ok(gpu)
+    }
 
-            let connector_name = super::format_connector_name(
-                connector_info.interface(),
-                connector_info.interface_id(),
-            );
+    /// Create the surface driving a connector with its preferred mode
+    fn setup_connector(&mut self, conn: connector::Handle, crtc: crtc::Handle) -> Result<()> {
+        let drm = self.drm.inner();
 
-            info!(
-                "Trying to setup connector {:?}-{} with crtc {:?} ({})",
-                connector_info.interface(),
-                connector_info.interface_id(),
-                crtc,
-                connector_name,
-            );
+        let connector_info = drm.get_connector(conn, false)?;
 
-            let drm_modes = connector_info.modes();
+        let connector_name = super::format_connector_name(
+            connector_info.interface(),
+            connector_info.interface_id(),
+        );
 
-            let wl_modes: Vec<WlMode> = drm_modes
-                .iter()
-                .map(|mode| WlMode {
-                    size: (mode.size().0 as i32, mode.size().1 as i32).into(),
-                    refresh: (mode.vrefresh() * 1000) as i32,
-                })
-                .collect();
+        info!(
+            "Trying to setup connector {:?}-{} with crtc {:?} ({})",
+            connector_info.interface(),
+            connector_info.interface_id(),
+            crtc,
+            connector_name,
+        );
 
-            let mode_id = drm_modes
-                .iter()
-                .position(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
-                .unwrap_or(0);
+        let drm_modes = connector_info.modes();
 
-            let drm_mode = drm_modes[mode_id];
+        let wl_modes: Vec<WlMode> = drm_modes
+            .iter()
+            .map(|mode| WlMode {
+                size: (mode.size().0 as i32, mode.size().1 as i32).into(),
+                refresh: (mode.vrefresh() * 1000) as i32,
+            })
+            .collect();
 
-            let mut drm_surface = drm.create_surface(crtc, drm_mode, &[conn])?;
-            drm_surface.link(session_signal.clone());
+        let mode_id = drm_modes
+            .iter()
+            .position(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+            .unwrap_or(0);
 
-            let gbm_surface =
-                GbmBufferedSurface::new(drm_surface, gbm.clone(), formats.clone(), None)?;
+        let drm_mode = drm_modes[mode_id];
+        let edid = super::edid::read(&*drm, conn).unwrap_or_default();
+        let (width, height) = connector_info.size().unwrap_or((0, 0));
+
+        let mut drm_surface = drm.create_surface(crtc, drm_mode, &[conn])?;
+        drm_surface.link(self.session_signal.clone());
+
+        let gbm_surface = GbmBufferedSurface::new(
+            drm_surface,
+            self.gbm.clone(),
+            self.formats.clone(),
+            None,
+        )?;
 
-            outputs.insert(
-                crtc,
-                GpuConnector {
-                    gbm_surface,
-                    drm_modes: drm_modes.to_vec(),
-                    wl_modes,
+        self.outputs.insert(
+            crtc,
+            GpuConnector {
+                gbm_surface,
+                connector: conn,
+                name: connector_name,
+                physical_properties: PhysicalProperties {
+                    size: (width as i32, height as i32).into(),
+                    subpixel: Subpixel::Unknown,
+                    make: edid.make,
+                    model: edid.model,
                 },
-            );
-        }
+                serial: edid.serial,
+                preferred_mode: wl_modes[mode_id],
+                drm_modes: drm_modes.to_vec(),
+                wl_modes,
+            },
+        );
 
-        Ok(Gpu {
-            drm,
-            drm_node,
-            outputs,
+        Ok(())
+    }
+
+    /// Describe the output driven by `crtc` for the handler
+    pub fn output_descriptor(&self, crtc: crtc::Handle) -> Option<NewOutputDescriptor> {
+        let connector = self.outputs.get(&crtc)?;
+        let id = DrmOutputId {
+            drm_node: self.drm_node,
+            crtc,
+        };
+        OUTPUT_ID_MAP.with(|map| map.borrow_mut().insert(id.output_id(), id));
+
+        Some(NewOutputDescriptor {
+            id: id.output_id(),
+            name: connector.name.clone(),
+            physical_properties: connector.physical_properties.clone(),
+            serial: connector.serial.clone(),
+            prefered_mode: connector.preferred_mode,
+            possible_modes: connector.wl_modes.clone(),
+            transform: Transform::Normal,
         })
     }
 
@@ -159,6 +210,10 @@ impl Gpu {
 
         let mut renderer = gpu_manager.renderer::<Gles2Renderbuffer>(&primary_gpu, &drm_node)?;
 
+        let output_id = &DrmOutputId { drm_node, crtc }.output_id();
+
+        handler.output_prepare(renderer.as_mut(), output_id);
+
         let age = {
             let state = handler.backend_state().drm();
 
@@ -197,8 +252,6 @@ impl Gpu {
                 })
         };
 
-        let output_id = &DrmOutputId { drm_node, crtc }.output_id();
-
         handler.output_render(
             renderer.as_mut(),
             output_id,
@@ -222,30 +275,121 @@ impl Gpu {
         Ok(())
     }
 
-    /// Udev changed event
+    /// Udev changed event, outputs are created and removed as monitors are plugged
     pub fn changed_event<D>(drm_node: DrmNode, handler: &mut D)
     where
         D: BackendHandler,
     {
-        if let Some(gpu) = handler.backend_state().drm().gpu(&drm_node) {
+        let (removed, added) = {
+            let state = handler.backend_state().drm();
+            let gpu = match state.gpu(&drm_node) {
+                Some(gpu) => gpu,
+                None => return,
+            };
+
             let scan = gpu.drm.scan_connectors();
             info!("connectors: {:#?}", &scan);
 
-            for _output in scan.removed {
-                //
+            let removed: Vec<OutputId> = scan
+                .removed
+                .iter()
+                .filter_map(|conn| gpu.remove_connector(*conn))
+                .collect();
+
+            let mut added = vec![];
+            for conn in scan.added {
+                match gpu.add_connector(conn, scan.map.get(&conn).copied()) {
+                    Ok(crtc) => added.push(crtc),
+                    Err(err) => error!("Failed to setup connector {:?}: {}", conn, err),
+                }
+            }
+
+            (removed, added)
+        };
+
+        for output_id in removed {
+            handler.output_removed(&output_id);
+        }
+
+        for crtc in added {
+            let state = handler.backend_state().drm();
+            let descriptor = state
+                .gpu(&drm_node)
+                .and_then(|gpu| gpu.output_descriptor(crtc));
+
+            // The first frame starts the vblank loop of the output
+            let gpu_manager = state.gpu_manager.clone();
+            if let Some(gpu) = state.gpu(&drm_node) {
+                let cleared = gpu_manager
+                    .borrow_mut()
+                    .renderer(&drm_node, &drm_node)
+                    .map_err(eyre::Report::from)
+                    .and_then(|mut renderer| {
+                        gpu.outputs.get_mut(&crtc).unwrap().clear(&mut renderer)
+                    });
+
+                if let Err(err) = cleared {
+                    error!("Failed to clear output: {}", err);
+                }
             }
 
-            for _output in scan.added {
-                //
+            if let Some(descriptor) = descriptor {
+                handler.output_created(descriptor);
             }
         }
     }
+
+    fn remove_connector(&mut self, conn: connector::Handle) -> Option<OutputId> {
+        let crtc = self
+            .outputs
+            .iter()
+            .find(|(_, output)| output.connector == conn)
+            .map(|(crtc, _)| *crtc)?;
+
+        info!("Connector {:?} disconnected", conn);
+        self.outputs.remove(&crtc);
+
+        let id = DrmOutputId {
+            drm_node: self.drm_node,
+            crtc,
+        }
+        .output_id();
+        OUTPUT_ID_MAP.with(|map| map.borrow_mut().remove(&id));
+
+        Some(id)
+    }
+
+    /// Setup a newly connected connector, the crtc from the scan is only used if it is free
+    fn add_connector(
+        &mut self,
+        conn: connector::Handle,
+        crtc: Option<crtc::Handle>,
+    ) -> Result<crtc::Handle> {
+        let used: Vec<crtc::Handle> = self.outputs.keys().copied().collect();
+        let crtc = match crtc.filter(|crtc| !used.contains(crtc)) {
+            Some(crtc) => crtc,
+            None => {
+                let info = self.drm.inner().get_connector(conn, false)?;
+                self.drm
+                    .free_crtc(&info, &used)
+                    .ok_or_else(|| eyre::eyre!("No free crtc for connector {:?}", conn))?
+            }
+        };
+
+        self.setup_connector(conn, crtc)?;
+        Ok(crtc)
+    }
 }
 
 pub struct GpuConnector {
     gbm_surface: GbmBufferedSurface<Rc<RefCell<GbmDevice<Device>>>, Device>,
+    connector: connector::Handle,
+    pub name: String,
+    pub physical_properties: PhysicalProperties,
+    pub serial: String,
+    pub preferred_mode: WlMode,
     drm_modes: Vec<smithay::reexports::drm::control::Mode>,
-    wl_modes: Vec<WlMode>,
+    pub wl_modes: Vec<WlMode>,
 }
 
 impl GpuConnector {
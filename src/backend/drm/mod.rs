@@ -1,4 +1,4 @@
-use crate::backend::{libinput, BackendHandler, NewOutputDescriptor, OutputId};
+use crate::backend::{libinput, BackendHandler, OutputId};
 use eyre::Result;
 use slog_scope::{error, info};
 use smithay::backend::allocator::dmabuf::Dmabuf;
@@ -16,9 +16,10 @@ use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
-use smithay::output::{Mode, PhysicalProperties};
+use smithay::output::Mode;
 
 mod device;
+mod edid;
 mod gpu;
 mod udev;
 
@@ -140,7 +141,11 @@ pub fn run_udev<D>(
         primary_gpu_node,
     )?;
 
-    let outputs: Vec<_> = gpu.outputs.iter().map(|(crtc, _)| *crtc).collect();
+    let outputs: Vec<_> = gpu
+        .outputs
+        .keys()
+        .filter_map(|crtc| gpu.output_descriptor(*crtc))
+        .collect();
 
     let mut gpus = HashMap::new();
     gpus.insert(primary_gpu_node, gpu);
@@ -198,30 +203,8 @@ pub fn run_udev<D>(
         .dmabuf_state()
         .create_global::<D::WaylandState, _>(display, dmabuf_formats, None);
 
-    for crtc in outputs {
-        let id = DrmOutputId {
-            drm_node: primary_gpu_node,
-            crtc,
-        };
-        let mode = Mode {
-            size: (1920, 1080).into(),
-            refresh: 60_000,
-        };
-
-        OUTPUT_ID_MAP.with(|map| map.borrow_mut().insert(id.output_id(), id));
-        handler.output_created(NewOutputDescriptor {
-            id: id.output_id(),
-            name: "".into(),
-            physical_properties: PhysicalProperties {
-                size: (1920, 1080).into(),
-                subpixel: smithay::output::Subpixel::Unknown,
-                make: "".into(),
-                model: "".into(),
-            },
-            prefered_mode: mode,
-            possible_modes: vec![mode],
-            transform: Transform::Normal,
-        })
+    for output in outputs {
+        handler.output_created(output);
     }
 
     #[cfg(feature = "xwayland")]
@@ -236,7 +219,6 @@ use crate::backend::drm::gpu::Gpu;
 use smithay::reexports::drm::control::{connector, crtc};
 use smithay::reexports::wayland_server::DisplayHandle;
 use smithay::utils::signaling::SignalToken;
-use smithay::utils::Transform;
 use smithay::wayland;
 use smithay::wayland::dmabuf::{DmabufGlobal, ImportError};
 use crate::backend::drawing::AsGles2Renderer;
@@ -0,0 +1,284 @@
+use crate::backend::{BackendHandler, NewOutputDescriptor, OutputId};
+use eyre::{eyre, Result};
+use slog_scope::{info, warn};
+use smithay::backend::egl::{EGLContext, EGLDevice, EGLDisplay};
+use smithay::backend::renderer::gles2::{Gles2Renderbuffer, Gles2Renderer};
+use smithay::backend::renderer::{Bind, Offscreen, Unbind};
+use smithay::output::{Mode, PhysicalProperties, Subpixel};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::calloop::EventLoop;
+use smithay::reexports::wayland_server::DisplayHandle;
+use smithay::utils::{Physical, Size, Transform};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const OUTPUT_NAME: &str = "HEADLESS";
+
+/// Size of a virtual output, parsed from `<width>x<height>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadlessOutput {
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug)]
+pub struct HeadlessOutputParseError(String);
+
+impl std::fmt::Display for HeadlessOutputParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid output size '{}', expected <width>x<height>", self.0)
+    }
+}
+
+impl std::error::Error for HeadlessOutputParseError {}
+
+impl FromStr for HeadlessOutput {
+    type Err = HeadlessOutputParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || HeadlessOutputParseError(s.to_string());
+        let (width, height) = s.split_once('x').ok_or_else(err)?;
+        let width: i32 = width.trim().parse().map_err(|_| err())?;
+        let height: i32 = height.trim().parse().map_err(|_| err())?;
+
+        if width <= 0 || height <= 0 {
+            return Err(err());
+        }
+
+        Ok(Self { width, height })
+    }
+}
+
+/// Run without any display or input device, every output renders into an offscreen buffer.
+/// Used to run the compositor in CI and drive it with real wayland clients.
+pub fn run_headless<D>(
+    event_loop: &mut EventLoop<'static, D>,
+    _display: &DisplayHandle,
+    handler: &mut D,
+    outputs: &[HeadlessOutput],
+) -> Result<()>
+where
+    D: BackendHandler + 'static,
+{
+    let mut renderer = init_renderer()?;
+    let mut buffers: Vec<(OutputId, Gles2Renderbuffer)> = Vec::with_capacity(outputs.len());
+
+    for (idx, output) in outputs.iter().enumerate() {
+        let size: Size<i32, Physical> = (output.width, output.height).into();
+        let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+        let buffer: Gles2Renderbuffer = renderer.create_buffer(buffer_size)?;
+
+        let mode = Mode {
+            size,
+            refresh: 60_000,
+        };
+
+        let output = NewOutputDescriptor {
+            id: OutputId { id: idx as u64 + 1 },
+            name: format!("{}-{}", OUTPUT_NAME, idx + 1),
+            serial: String::new(),
+            physical_properties: PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Wazemmes".into(),
+                model: "Headless".into(),
+            },
+            prefered_mode: mode,
+            possible_modes: vec![mode],
+            transform: Transform::Normal,
+        };
+
+        buffers.push((output.id, buffer));
+        handler.output_created(output);
+    }
+
+    handler.start_compositor();
+
+    info!("Initialization completed, starting the main loop.");
+
+    event_loop
+        .handle()
+        .insert_source(Timer::immediate(), move |_, _, handler| {
+            for (output_id, buffer) in &buffers {
+                handler.output_prepare(&mut renderer, output_id);
+
+                if let Err(err) = renderer.bind(buffer.clone()) {
+                    warn!("Failed to bind headless output buffer: {:?}", err);
+                    continue;
+                }
+
+                // Offscreen buffers have no age, the whole output is redrawn
+                if let Err(err) = handler.output_render(&mut renderer, output_id, 0, None) {
+                    warn!("Failed to render headless output: {:?}", err);
+                }
+
+                if let Err(err) = renderer.unbind() {
+                    warn!("Failed to unbind headless output buffer: {:?}", err);
+                }
+
+                handler.send_frames(output_id);
+            }
+
+            TimeoutAction::ToDuration(Duration::from_millis(16))
+        })
+        .map_err(|err| eyre!("Failed to insert the headless render timer: {}", err))?;
+
+    Ok(())
+}
+
+/// Create a renderer on an EGL device without any surface, software devices are preferred
+fn init_renderer() -> Result<Gles2Renderer> {
+    let log = slog_scope::logger();
+
+    let (software, hardware): (Vec<EGLDevice>, Vec<EGLDevice>) = EGLDevice::enumerate()?
+        .partition(|device| {
+            device
+                .extensions()
+                .iter()
+                .any(|extension| extension == "EGL_MESA_device_software")
+        });
+
+    let device = software
+        .into_iter()
+        .chain(hardware)
+        .next()
+        .ok_or_else(|| eyre!("No EGL device available"))?;
+
+    let display = EGLDisplay::new(&device, log.clone())?;
+    let context = EGLContext::new(&display, log.clone())?;
+    let renderer = unsafe { Gles2Renderer::new(context, log)? };
+
+    Ok(renderer)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::headless::{run_headless, HeadlessOutput};
+    use crate::backend::{
+        BackendHandler, BackendState, InputHandler, NewOutputDescriptor, OutputHandler, OutputId,
+    };
+    use crate::Wazemmes;
+    use smithay::backend::input::{InputBackend, InputEvent};
+    use smithay::backend::renderer::gles2::{Gles2Renderer, Gles2Texture};
+    use smithay::backend::session::auto::AutoSession;
+    use smithay::backend::SwapBuffersError;
+    use smithay::output::Mode;
+    use smithay::reexports::calloop::EventLoop;
+    use smithay::reexports::wayland_server::Display;
+    use smithay::utils::{Physical, Rectangle};
+    use smithay::wayland::dmabuf::DmabufState;
+    use speculoos::prelude::*;
+    use std::time::Duration;
+
+    /// Records what the backend asks for instead of running a compositor
+    struct Recorder {
+        dmabuf_state: DmabufState,
+        backend_state: BackendState,
+        outputs: Vec<String>,
+        started: bool,
+        rendered: usize,
+        frames: usize,
+    }
+
+    impl OutputHandler for Recorder {
+        fn output_created(&mut self, output: NewOutputDescriptor) {
+            self.outputs.push(output.name);
+        }
+
+        fn output_removed(&mut self, _output_id: &OutputId) {}
+
+        fn output_mode_updated(&mut self, _output_id: &OutputId, _mode: Mode) {}
+
+        fn output_prepare(&mut self, _renderer: &mut Gles2Renderer, _output: &OutputId) {}
+
+        fn output_render(
+            &mut self,
+            _renderer: &mut Gles2Renderer,
+            _output: &OutputId,
+            _age: usize,
+            _pointer_image: Option<&Gles2Texture>,
+        ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, SwapBuffersError> {
+            self.rendered += 1;
+            Ok(None)
+        }
+
+        fn send_frames(&mut self, _output_id: &OutputId) {
+            self.frames += 1;
+        }
+    }
+
+    impl InputHandler for Recorder {
+        fn process_input_event<I: InputBackend + 'static>(
+            &mut self,
+            _event: InputEvent<I>,
+            _absolute_output: Option<&OutputId>,
+            _session: Option<&mut AutoSession>,
+        ) {
+        }
+    }
+
+    impl BackendHandler for Recorder {
+        type WaylandState = Wazemmes;
+
+        fn dmabuf_state(&mut self) -> &mut DmabufState {
+            &mut self.dmabuf_state
+        }
+
+        fn backend_state(&mut self) -> &mut BackendState {
+            &mut self.backend_state
+        }
+
+        #[cfg(feature = "xwayland")]
+        fn start_xwayland(&mut self) {}
+
+        fn start_compositor(&mut self) {
+            self.started = true;
+        }
+
+        fn close_compositor(&mut self) {}
+    }
+
+    #[test]
+    fn should_parse_output_size() {
+        assert_that!("1920x1080".parse::<HeadlessOutput>().unwrap()).is_equal_to(HeadlessOutput {
+            width: 1920,
+            height: 1080,
+        });
+    }
+
+    #[test]
+    fn should_reject_invalid_output_size() {
+        assert_that!("0x10".parse::<HeadlessOutput>()).is_err();
+        assert_that!("abc".parse::<HeadlessOutput>()).is_err();
+        assert_that!("10x".parse::<HeadlessOutput>()).is_err();
+    }
+
+    #[test]
+    #[ignore = "needs an EGL device"]
+    fn should_create_outputs_and_render() {
+        let mut event_loop: EventLoop<'static, Recorder> = EventLoop::try_new().unwrap();
+        let display: Display<Wazemmes> = Display::new().unwrap();
+        let mut recorder = Recorder {
+            dmabuf_state: DmabufState::new(),
+            backend_state: BackendState::None,
+            outputs: vec![],
+            started: false,
+            rendered: 0,
+            frames: 0,
+        };
+
+        let outputs = ["1920x1080".parse().unwrap(), "1280x720".parse().unwrap()];
+        run_headless(&mut event_loop, &display.handle(), &mut recorder, &outputs).unwrap();
+
+        assert_that!(recorder.outputs)
+            .is_equal_to(vec!["HEADLESS-1".to_string(), "HEADLESS-2".to_string()]);
+        assert_that!(recorder.started).is_true();
+
+        event_loop
+            .dispatch(Some(Duration::ZERO), &mut recorder)
+            .unwrap();
+
+        assert_that!(recorder.rendered).is_equal_to(2);
+        assert_that!(recorder.frames).is_equal_to(2);
+    }
+}
@@ -1,10 +1,15 @@
 use crate::backend::InputHandler;
+use crate::config::input::{
+    AccelProfile, ClickMethod, DeviceType, InputSettings, ScrollMethod,
+};
+use slog_scope::warn;
 use smithay::backend::input::InputEvent;
 use smithay::backend::libinput::{LibinputInputBackend, LibinputSessionInterface};
 use smithay::backend::session::auto::AutoSession;
 use smithay::backend::session::{Session, Signal as SessionSignal};
 use smithay::reexports::calloop::LoopHandle;
-use smithay::reexports::input::Libinput;
+use smithay::reexports::input;
+use smithay::reexports::input::{DeviceCapability, DeviceConfigError, Libinput};
 use smithay::utils::signaling::{Linkable, Signaler};
 
 /// Initialize libinput backend
@@ -23,16 +28,102 @@ pub fn init<D>(
     libinput_backend.link(session_signal);
 
     event_loop
-        .insert_source(libinput_backend, move |mut event, _, handler| {
-            match &mut event {
-                InputEvent::DeviceAdded { device } => {
-                    device.config_tap_set_enabled(true).ok();
-                }
-                InputEvent::DeviceRemoved { .. } => {}
-                _ => {}
-            }
-
+        .insert_source(libinput_backend, move |event, _, handler| {
             handler.process_input_event(event, None, Some(&mut session));
         })
         .unwrap();
 }
+
+/// Types used to match the device against the `inputs` config
+pub fn device_types(device: &input::Device) -> Vec<DeviceType> {
+    let mut types = vec![];
+
+    if device.config_tap_finger_count() > 0 {
+        types.push(DeviceType::Touchpad);
+    }
+
+    if device.has_capability(DeviceCapability::Pointer) {
+        types.push(DeviceType::Pointer);
+    }
+
+    if device.has_capability(DeviceCapability::Keyboard) {
+        types.push(DeviceType::Keyboard);
+    }
+
+    types
+}
+
+/// Apply the settings to the device, unset options are reset to the libinput default
+/// so options removed from the config are reverted on reload.
+/// Tap to click is enabled unless disabled in the config.
+pub fn configure_device(device: &mut input::Device, settings: &InputSettings) {
+    let name = device.name().to_string();
+
+    let tap = settings.tap.unwrap_or(true);
+    check(&name, "tap", device.config_tap_set_enabled(tap));
+
+    let natural_scroll = settings
+        .natural_scroll
+        .unwrap_or_else(|| device.config_scroll_default_natural_scroll_enabled());
+    check(
+        &name,
+        "natural_scroll",
+        device.config_scroll_set_natural_scroll_enabled(natural_scroll),
+    );
+
+    let accel_profile = settings
+        .accel_profile
+        .map(|profile| match profile {
+            AccelProfile::Flat => input::AccelProfile::Flat,
+            AccelProfile::Adaptive => input::AccelProfile::Adaptive,
+        })
+        .or_else(|| device.config_accel_default_profile());
+    if let Some(profile) = accel_profile {
+        check(&name, "accel_profile", device.config_accel_set_profile(profile));
+    }
+
+    let accel_speed = settings
+        .accel_speed
+        .unwrap_or_else(|| device.config_accel_default_speed());
+    check(&name, "accel_speed", device.config_accel_set_speed(accel_speed));
+
+    let dwt = settings
+        .dwt
+        .unwrap_or_else(|| device.config_dwt_default_enabled());
+    check(&name, "dwt", device.config_dwt_set_enabled(dwt));
+
+    let left_handed = settings
+        .left_handed
+        .unwrap_or_else(|| device.config_left_handed_default());
+    check(&name, "left_handed", device.config_left_handed_set(left_handed));
+
+    let scroll_method = settings
+        .scroll_method
+        .map(|method| match method {
+            ScrollMethod::NoScroll => input::ScrollMethod::NoScroll,
+            ScrollMethod::TwoFinger => input::ScrollMethod::TwoFinger,
+            ScrollMethod::Edge => input::ScrollMethod::Edge,
+            ScrollMethod::OnButtonDown => input::ScrollMethod::OnButtonDown,
+        })
+        .unwrap_or_else(|| device.config_scroll_default_method());
+    check(&name, "scroll_method", device.config_scroll_set_method(scroll_method));
+
+    let click_method = settings
+        .click_method
+        .map(|method| match method {
+            ClickMethod::ButtonAreas => input::ClickMethod::ButtonAreas,
+            ClickMethod::Clickfinger => input::ClickMethod::Clickfinger,
+        })
+        .or_else(|| device.config_click_default_method());
+    if let Some(method) = click_method {
+        check(&name, "click_method", device.config_click_set_method(method));
+    }
+}
+
+/// Options a device does not support are silently skipped, since type matches
+/// usually cover devices with different capabilities
+fn check(device: &str, option: &str, result: Result<(), DeviceConfigError>) {
+    if let Err(DeviceConfigError::Invalid) = result {
+        warn!("Invalid value for '{}' on input device '{}'", option, device);
+    }
+}
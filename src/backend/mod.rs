@@ -18,6 +18,7 @@ use smithay::output::{Mode, PhysicalProperties};
 
 pub mod drawing;
 pub mod drm;
+pub mod headless;
 pub mod libinput;
 pub mod winit;
 #[cfg(feature = "xwayland")]
@@ -33,6 +34,8 @@ pub struct NewOutputDescriptor {
     pub id: OutputId,
     pub name: String,
     pub physical_properties: PhysicalProperties,
+    /// Monitor serial number, empty when unknown
+    pub serial: String,
 
     pub prefered_mode: Mode,
     pub possible_modes: Vec<Mode>,
@@ -101,7 +104,7 @@ pub trait BackendHandler: OutputHandler + InputHandler {
 }
 
 pub trait InputHandler {
-    fn process_input_event<I: InputBackend>(
+    fn process_input_event<I: InputBackend + 'static>(
         &mut self,
         event: InputEvent<I>,
         absolute_output: Option<&OutputId>,
@@ -112,8 +115,14 @@ pub trait InputHandler {
 pub trait OutputHandler {
     fn output_created(&mut self, output: NewOutputDescriptor);
 
+    /// Called once the output is disconnected, its id is not used anymore
+    fn output_removed(&mut self, output_id: &OutputId);
+
     fn output_mode_updated(&mut self, output_id: &OutputId, mode: Mode);
 
+    /// Called before the output buffer is bound, offscreen rendering must happen here
+    fn output_prepare(&mut self, renderer: &mut Gles2Renderer, output: &OutputId);
+
     fn output_render(
         &mut self,
         renderer: &mut Gles2Renderer,
@@ -130,6 +139,7 @@ pub enum PreferedBackend {
     X11,
     Winit,
     Udev,
+    Headless,
     Auto,
 }
 
@@ -152,6 +162,7 @@ impl FromStr for PreferedBackend {
             "x11" => Self::X11,
             "winit" => Self::Winit,
             "udev" => Self::Udev,
+            "headless" => Self::Headless,
             "auto" => Self::Auto,
             other => return Err(PreferedBackendParseError(other.to_string())),
         })
@@ -163,6 +174,7 @@ pub fn init<D>(
     display: &DisplayHandle,
     handler: &mut D,
     backend: PreferedBackend,
+    headless_outputs: &[headless::HeadlessOutput],
 ) where
     D: BackendHandler + AsMut<DmabufState> + 'static,
 {
@@ -193,5 +205,10 @@ pub fn init<D>(
             #[cfg(feature = "udev")]
             drm::run_udev(event_loop, display, handler).expect("Failed to initialize tty backend.");
         }
+        PreferedBackend::Headless => {
+            #[cfg(feature = "headless")]
+            headless::run_headless(event_loop, display, handler, headless_outputs)
+                .expect("Failed to initialize headless backend.");
+        }
     }
 }
@@ -52,6 +52,7 @@ where
         physical_properties,
         transform: Transform::Flipped180,
         name: OUTPUT_NAME.to_owned(),
+        serial: String::new(),
         prefered_mode: mode,
         possible_modes: vec![mode],
     };
@@ -86,6 +87,8 @@ where
                 Ok(()) => {
                     let mut backend = backend.borrow_mut();
 
+                    handler.output_prepare(backend.renderer(), &output_id);
+
                     if backend.bind().is_ok() {
                         let age = backend.buffer_age().unwrap_or(0);
                         let damage = handler
@@ -9,6 +9,7 @@ use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use crate::backend::xwayland::window::WinType;
 use crate::shell::windows::toplevel::WindowWrap;
 use crate::shell::windows::xpopup::X11Popup;
+use crate::ipc::protocol::WindowChange;
 use crate::{Wazemmes, WorkspaceRef};
 use smithay::desktop::{Kind, Window, X11Surface};
 use smithay::reexports::wayland_server::{Client, DisplayHandle, Resource};
@@ -54,10 +55,24 @@ impl Wazemmes {
                         Err(err) => error!("Error while handling X11 event: {}", err),
                     }
                 }
+
+                data.state.report_new_x11_windows();
             })
             .unwrap();
     }
 
+    /// Send the `new` window event of the X11 toplevels mapped since the last call
+    pub fn report_new_x11_windows(&mut self) {
+        let windows = match self.x11_state.as_mut() {
+            Some(x11) => std::mem::take(&mut x11.new_windows),
+            None => return,
+        };
+
+        for window in windows {
+            self.ipc_window_event(&window, WindowChange::New);
+        }
+    }
+
     pub fn xwayland_exited(&mut self) {
         let _ = self.x11_state.take();
         error!("Xwayland crashed");
@@ -99,6 +114,8 @@ pub struct X11State {
     id_map: HashMap<u32, u32>,
     root: x11rb::protocol::xproto::Window,
     pub needs_redraw: bool,
+    /// Toplevels mapped since they were last reported over IPC
+    pub new_windows: Vec<WindowWrap>,
 }
 
 impl X11State {
@@ -155,6 +172,7 @@ impl X11State {
             id_map: Default::default(),
             root,
             needs_redraw: false,
+            new_windows: vec![],
         };
 
         Ok((
@@ -318,7 +336,8 @@ impl X11State {
             WinType::Normal => {
                 debug!("New toplevel from XWindow {xwindow}");
                 let window = WindowWrap::from_x11_window(Window::new(Kind::X11(x11surface)));
-                container.push_xwindow(window);
+                container.push_xwindow(window.clone());
+                self.new_windows.push(window);
             }
             _ => {
                 let popup = Window::new(Kind::X11(x11surface));
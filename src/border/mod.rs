@@ -13,12 +13,14 @@ use smithay::utils::{Logical, Physical, Point, Rectangle, Scale, Transform};
 
 mod glow;
 
-use crate::shell::drawable::Borders;
+use crate::shell::drawable::{Borders, Quad};
 use glow::{Program, Shader};
 use crate::backend::drawing::BORDER_Z_INDEX;
 
 pub const BLUE: (f32, f32, f32) = (26.0 / 255.0, 95.0 / 255.0, 205.0 / 255.0);
 pub const RED: (f32, f32, f32) = (1.0, 95.0 / 255.0, 205.0 / 255.0);
+pub const GREY: (f32, f32, f32) = (0.3, 0.3, 0.3);
+pub const DARK_GREY: (f32, f32, f32) = (0.12, 0.12, 0.12);
 
 pub struct QuadPipeline {
     program: glow::Program,
@@ -127,6 +129,7 @@ pub struct QuadElement {
     pipeline: QuadPipeline,
     geometry: Rectangle<i32, Logical>,
     output_geometry: Rectangle<f64, Physical>,
+    z_index: u8,
 }
 
 impl QuadElement {
@@ -142,27 +145,46 @@ impl QuadElement {
                 pipeline: QuadPipeline::new(gl, border.color),
                 geometry: border.left,
                 output_geometry,
+                z_index: BORDER_Z_INDEX,
             },
             Self {
                 transform,
                 pipeline: QuadPipeline::new(gl, border.color),
                 geometry: border.right,
                 output_geometry,
+                z_index: BORDER_Z_INDEX,
             },
             Self {
                 transform,
                 pipeline: QuadPipeline::new(gl, border.color),
                 geometry: border.top,
                 output_geometry,
+                z_index: BORDER_Z_INDEX,
             },
             Self {
                 transform,
                 pipeline: QuadPipeline::new(gl, border.color),
                 geometry: border.bottom,
                 output_geometry,
+                z_index: BORDER_Z_INDEX,
             },
         ]
     }
+
+    pub fn quad(
+        gl: &Gles2,
+        output_geometry: Rectangle<f64, Physical>,
+        quad: &Quad,
+        transform: Transform,
+    ) -> Self {
+        Self {
+            transform,
+            pipeline: QuadPipeline::new(gl, quad.color),
+            geometry: quad.geometry,
+            output_geometry,
+            z_index: quad.z_index,
+        }
+    }
 }
 
 impl RenderElement<Gles2Renderer> for QuadElement {
@@ -228,7 +250,7 @@ impl RenderElement<Gles2Renderer> for QuadElement {
     }
 
     fn z_index(&self) -> u8 {
-        BORDER_Z_INDEX
+        self.z_index
     }
 }
 
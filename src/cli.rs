@@ -1,11 +1,48 @@
+use crate::backend::headless::HeadlessOutput;
 use crate::backend::PreferedBackend;
-use clap::Parser;
+use crate::ipc::protocol::EventType;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 /// Rust wayland compositor
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct WazemmesCli {
-    /// Selected backend: auto, x11, winit, udev
+    /// Selected backend: auto, x11, winit, udev, headless
     #[clap(short, long, default_value = "auto")]
     pub backend: PreferedBackend,
+    /// Virtual outputs created by the headless backend, can be repeated
+    #[clap(long = "headless-output", default_value = "1920x1080")]
+    pub headless_outputs: Vec<HeadlessOutput>,
+    /// Config file to use instead of `$XDG_CONFIG_HOME/wazemmes/config.ron`
+    #[clap(short, long)]
+    pub config: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check a config file and exit, the default config path is used when omitted
+    CheckConfig { path: Option<PathBuf> },
+    /// Send a message to the running compositor and print its JSON reply
+    #[clap(trailing_var_arg = true)]
+    Msg(MsgArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct MsgArgs {
+    /// IPC socket, defaults to `$WAZEMMES_SOCK`
+    #[clap(short, long)]
+    pub socket: Option<PathBuf>,
+    /// Pretty print the JSON replies
+    #[clap(short, long)]
+    pub pretty: bool,
+    /// Print the events of the given types until interrupted, for example `window,workspace`
+    #[clap(long, value_delimiter = ',')]
+    pub subscribe: Vec<EventType>,
+    /// A query (`get-tree`, `get-workspaces`, `get-outputs`, `get-inputs`, `get-config`,
+    /// `get-version`), an action followed by its argument, for example `move-to-workspace 3`,
+    /// or commands such as `'focus parent; workspace 3'`
+    pub message: Vec<String>,
 }
@@ -0,0 +1,670 @@
+use crate::config::command;
+use crate::config::keybinding::{lookup_keysym, Action, KeyStroke, KeysymLookup, Modifier};
+use crate::config::{resolve_include, ConfigFragment, WazemmesConfig, DEFAULT_MODE};
+use crate::process::split_command;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use xkbcommon::xkb;
+use xkbcommon::xkb::Keysym;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning: {}", self.message),
+            Severity::Error => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// Keybindings with the key kept as written, so every bad keysym can be reported
+/// instead of failing on the first one
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RawConfig {
+    includes: Vec<PathBuf>,
+    keybindings: Vec<RawKeyBinding>,
+    modes: BTreeMap<String, Vec<RawKeyBinding>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawKeyBinding {
+    modifiers: HashSet<Modifier>,
+    key: Option<String>,
+    keycode: Option<u32>,
+    #[serde(default)]
+    then: Vec<RawKeyStroke>,
+    action: Action,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawKeyStroke {
+    #[serde(default)]
+    modifiers: HashSet<Modifier>,
+    key: Option<String>,
+    keycode: Option<u32>,
+}
+
+/// A config file that could be parsed
+struct ConfigFile {
+    path: PathBuf,
+    raw: RawConfig,
+}
+
+/// A binding resolved to its keysyms, `location` is the file and index it was declared at
+struct Binding<'a> {
+    location: String,
+    mode: &'a str,
+    strokes: Vec<KeyStroke>,
+    action: &'a Action,
+}
+
+impl Binding<'_> {
+    fn combo(&self) -> String {
+        self.strokes
+            .iter()
+            .map(KeyStroke::to_string)
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+/// Check the config file at `path` and every file it includes
+pub fn check_file(path: &Path) -> Vec<Diagnostic> {
+    let mut files = vec![];
+    let mut diagnostics = vec![];
+    read_file(path, true, &mut files, &mut diagnostics);
+    check_bindings(&files, &mut diagnostics);
+    diagnostics
+}
+
+/// Check a main config file content, includes are not followed
+pub fn check_source(source: &str) -> Vec<Diagnostic> {
+    let mut files = vec![];
+    let mut diagnostics = vec![];
+    read_source(
+        Path::new("config.ron"),
+        source,
+        true,
+        &mut files,
+        &mut diagnostics,
+    );
+    check_bindings(&files, &mut diagnostics);
+    diagnostics
+}
+
+fn read_file(
+    path: &Path,
+    main: bool,
+    files: &mut Vec<ConfigFile>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if files.iter().any(|file| file.path == path) {
+        diagnostics.push(Diagnostic::warning(format!(
+            "{} is included more than once",
+            path.display()
+        )));
+        return;
+    }
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            diagnostics.push(Diagnostic::error(format!(
+                "failed to read {}: {}",
+                path.display(),
+                err
+            )));
+            return;
+        }
+    };
+
+    let includes = read_source(path, &source, main, files, diagnostics);
+    for include in includes {
+        read_file(&resolve_include(path, &include), false, files, diagnostics);
+    }
+}
+
+/// Parse a config file and return its includes
+fn read_source(
+    path: &Path,
+    source: &str,
+    main: bool,
+    files: &mut Vec<ConfigFile>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<PathBuf> {
+    let raw = match ron::from_str::<RawConfig>(source) {
+        Ok(raw) => raw,
+        Err(err) => {
+            diagnostics.push(parse_error(path, err));
+            return vec![];
+        }
+    };
+
+    // Unknown keysyms fail the real parse, they are reported with the bindings
+    let keys_known = raw
+        .keybindings
+        .iter()
+        .chain(raw.modes.values().flatten())
+        .flat_map(|binding| {
+            std::iter::once(&binding.key).chain(binding.then.iter().map(|stroke| &stroke.key))
+        })
+        .flatten()
+        .all(|key| lookup_keysym(key) != KeysymLookup::Unknown);
+
+    if keys_known {
+        let parsed = if main {
+            ron::from_str::<WazemmesConfig>(source).map(drop)
+        } else {
+            ron::from_str::<ConfigFragment>(source).map(drop)
+        };
+
+        if let Err(err) = parsed {
+            diagnostics.push(parse_error(path, err));
+        }
+    }
+
+    let includes = raw.includes.clone();
+    files.push(ConfigFile {
+        path: path.to_path_buf(),
+        raw,
+    });
+
+    includes
+}
+
+fn check_bindings(files: &[ConfigFile], diagnostics: &mut Vec<Diagnostic>) {
+    let mut bindings = vec![];
+
+    let modes = files.iter().flat_map(|file| {
+        let modes = file.raw.modes.iter().map(move |(mode, bindings)| {
+            let field = format!("modes[\"{}\"]", mode);
+            (file, mode.as_str(), field, bindings)
+        });
+
+        let default = (
+            file,
+            DEFAULT_MODE,
+            "keybindings".to_string(),
+            &file.raw.keybindings,
+        );
+        std::iter::once(default).chain(modes)
+    });
+
+    for (file, mode, field, mode_bindings) in modes {
+        for (index, binding) in mode_bindings.iter().enumerate() {
+            let location = format!("{}: {}[{}]", file.path.display(), field, index);
+            let first = (&binding.modifiers, &binding.key, binding.keycode);
+            let raw_strokes = std::iter::once(first).chain(
+                binding
+                    .then
+                    .iter()
+                    .map(|stroke| (&stroke.modifiers, &stroke.key, stroke.keycode)),
+            );
+
+            let mut strokes = vec![];
+            for (modifiers, key, keycode) in raw_strokes {
+                let key = match (key, keycode) {
+                    (None, None) => {
+                        diagnostics.push(Diagnostic::error(format!(
+                            "{}: a key or a keycode is required",
+                            location
+                        )));
+                        continue;
+                    }
+                    (Some(key), Some(keycode)) => {
+                        diagnostics.push(Diagnostic::warning(format!(
+                            "{}: both key '{}' and keycode {} are set, the keycode is used",
+                            location, key, keycode
+                        )));
+                        Some(xkb::KEY_NoSymbol)
+                    }
+                    (None, Some(_)) => Some(xkb::KEY_NoSymbol),
+                    (Some(key), None) => key_from_name(&location, key, diagnostics),
+                };
+
+                let key = match key {
+                    Some(key) => key,
+                    None => continue,
+                };
+
+                strokes.push(KeyStroke {
+                    modifiers: modifiers.clone(),
+                    key,
+                    keycode,
+                });
+            }
+
+            if strokes.len() != binding.then.len() + 1 {
+                continue;
+            }
+
+            bindings.push(Binding {
+                location,
+                mode,
+                strokes,
+                action: &binding.action,
+            });
+        }
+    }
+
+    for binding in &bindings {
+        if matches!(binding.action, Action::DragMove | Action::DragResize) {
+            diagnostics.push(Diagnostic::warning(format!(
+                "{}: {:?} does nothing in a key binding, use it in mouse_bindings",
+                binding.location, binding.action
+            )));
+        }
+
+        if let Action::Run { command, .. } = binding.action {
+            if let Err(err) = split_command(command) {
+                diagnostics.push(Diagnostic::error(format!("{}: {}", binding.location, err)));
+            }
+        }
+
+        if let Action::Command(text) = binding.action {
+            if let Err(err) = command::parse(text) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: invalid command {:?}, {}",
+                    binding.location, text, err
+                )));
+            }
+        }
+    }
+
+    check_conflicts(&bindings, diagnostics);
+    check_reachability(&bindings, diagnostics);
+    check_vt_switch(&bindings, diagnostics);
+    check_modes(files, &bindings, diagnostics);
+
+    if !bindings
+        .iter()
+        .any(|binding| *binding.action == Action::Quit)
+    {
+        diagnostics.push(Diagnostic::warning(
+            "no keybinding for Quit, the compositor cannot be exited from the keyboard",
+        ));
+    }
+}
+
+/// Resolve a keysym name, unknown names are reported and give `None`
+fn key_from_name(location: &str, name: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<Keysym> {
+    match lookup_keysym(name) {
+        KeysymLookup::Unknown => {
+            diagnostics.push(Diagnostic::error(format!(
+                "{}: unknown keysym '{}'",
+                location, name
+            )));
+            None
+        }
+        KeysymLookup::CaseInsensitive(key) => {
+            diagnostics.push(Diagnostic::warning(format!(
+                "{}: keysym '{}' only matched case insensitive for '{}'",
+                location,
+                name,
+                xkb::keysym_get_name(key)
+            )));
+            Some(key)
+        }
+        KeysymLookup::Exact(key) => Some(key),
+    }
+}
+
+fn parse_error(path: &Path, err: ron::error::SpannedError) -> Diagnostic {
+    Diagnostic::error(format!(
+        "{}:{}:{}: {}",
+        path.display(),
+        err.position.line,
+        err.position.col,
+        err.code
+    ))
+}
+
+/// Bindings are matched in order, so only the first binding of a key sequence is ever used,
+/// and a binding completing on a stroke hides the longer chords starting with it
+fn check_conflicts(bindings: &[Binding], diagnostics: &mut Vec<Diagnostic>) {
+    for (position, binding) in bindings.iter().enumerate() {
+        let first = bindings[..position]
+            .iter()
+            .find(|other| other.mode == binding.mode && other.strokes == binding.strokes);
+
+        if let Some(first) = first {
+            if first.action == binding.action {
+                diagnostics.push(Diagnostic::warning(format!(
+                    "{}: {} is a duplicate of {}",
+                    binding.location,
+                    binding.combo(),
+                    first.location
+                )));
+            } else {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: {} ({:?}) is shadowed by {} ({:?})",
+                    binding.location,
+                    binding.combo(),
+                    binding.action,
+                    first.location,
+                    first.action
+                )));
+            }
+
+            continue;
+        }
+
+        let prefix = bindings.iter().find(|other| {
+            other.mode == binding.mode
+                && other.strokes.len() < binding.strokes.len()
+                && binding.strokes.starts_with(&other.strokes)
+        });
+
+        if let Some(prefix) = prefix {
+            diagnostics.push(Diagnostic::error(format!(
+                "{}: chord {} is unreachable, {} triggers {:?} first",
+                binding.location,
+                binding.combo(),
+                prefix.location,
+                prefix.action
+            )));
+        }
+    }
+}
+
+/// Bindings are matched against the shifted keysym, `Shift+a` or `A` without `Shift` never match
+fn check_reachability(bindings: &[Binding], diagnostics: &mut Vec<Diagnostic>) {
+    for binding in bindings {
+        for stroke in &binding.strokes {
+            if stroke.modifiers.contains(&Modifier::CapsLock) {
+                continue;
+            }
+
+            let character = match char::from_u32(xkb::keysym_to_utf32(stroke.key)) {
+                Some(character) => character,
+                None => continue,
+            };
+
+            let shift = stroke.modifiers.contains(&Modifier::Shift);
+            let unreachable =
+                (shift && character.is_lowercase()) || (!shift && character.is_uppercase());
+
+            if unreachable {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: {} can never be triggered, {:?} is unreachable",
+                    binding.location, stroke, binding.action
+                )));
+            }
+        }
+    }
+}
+
+/// Keybindings take precedence over the built-in VT switching
+fn check_vt_switch(bindings: &[Binding], diagnostics: &mut Vec<Diagnostic>) {
+    for binding in bindings {
+        for stroke in &binding.strokes {
+            let vt_key =
+                (xkb::KEY_XF86Switch_VT_1..=xkb::KEY_XF86Switch_VT_12).contains(&stroke.key);
+            let vt_combo = stroke.modifiers.contains(&Modifier::Ctrl)
+                && stroke.modifiers.contains(&Modifier::Alt)
+                && (xkb::KEY_F1..=xkb::KEY_F12).contains(&stroke.key);
+
+            if vt_key || vt_combo {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: {} clashes with the built-in VT switching",
+                    binding.location, stroke
+                )));
+            }
+        }
+    }
+}
+
+/// `SetMode` must name a declared mode and every mode needs a way back out
+fn check_modes(files: &[ConfigFile], bindings: &[Binding], diagnostics: &mut Vec<Diagnostic>) {
+    let modes: HashSet<&str> = files
+        .iter()
+        .flat_map(|file| file.raw.modes.keys())
+        .map(String::as_str)
+        .chain(std::iter::once(DEFAULT_MODE))
+        .collect();
+
+    for binding in bindings {
+        if let Action::SetMode(mode) = binding.action {
+            if !modes.contains(mode.as_str()) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: {} switches to unknown mode '{}'",
+                    binding.location,
+                    binding.combo(),
+                    mode
+                )));
+            }
+        }
+    }
+
+    let mut modes: Vec<&str> = modes.into_iter().collect();
+    modes.sort_unstable();
+
+    for mode in modes.into_iter().filter(|mode| *mode != DEFAULT_MODE) {
+        let can_leave = bindings.iter().any(|binding| {
+            binding.mode == mode
+                && matches!(binding.action, Action::SetMode(target) if target != mode)
+        });
+
+        if !can_leave {
+            diagnostics.push(Diagnostic::warning(format!(
+                "mode '{}' has no binding to leave it",
+                mode
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::check::{check_source, Severity};
+    use indoc::indoc;
+    use speculoos::prelude::*;
+
+    fn config(bindings: &str) -> String {
+        format!(
+            "(gaps: 14, keybindings: [{} (modifiers: [Ctrl, Shift], key: \"Q\", action: Quit)])",
+            bindings
+        )
+    }
+
+    fn errors(source: &str) -> Vec<String> {
+        check_source(source)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn should_accept_valid_config() {
+        let diagnostics = check_source(&config(
+            r#"(modifiers: [Alt], key: "k", action: MoveFocusUp),"#,
+        ));
+
+        assert_that!(diagnostics).is_empty();
+    }
+
+    #[test]
+    fn example_config_should_have_no_errors() {
+        let source = include_str!("../../config-example.ron");
+
+        assert_that!(errors(source)).is_empty();
+    }
+
+    #[test]
+    fn should_report_parse_error_position() {
+        let source = indoc! {r#"
+            (
+                gaps: "wide",
+                keybindings: [],
+            )
+        "#};
+
+        let errors = errors(source);
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).starts_with("config.ron:2:");
+    }
+
+    #[test]
+    fn should_report_every_unknown_keysym() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], key: "nope", action: Close),
+               (modifiers: [Alt], key: "nada", action: Close),"#,
+        ));
+
+        assert_that!(errors).has_length(2);
+        assert_that!(errors[0]).contains("unknown keysym 'nope'");
+        assert_that!(errors[1]).contains("unknown keysym 'nada'");
+    }
+
+    #[test]
+    fn should_report_invalid_command_position() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], key: "p", action: Command("focus parent; workspace x")),"#,
+        ));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("column 25: expected a workspace number, found 'x'");
+    }
+
+    #[test]
+    fn should_report_shadowed_binding() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], key: "k", action: MoveFocusUp),
+               (modifiers: [Alt], key: "k", action: Close),"#,
+        ));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("shadowed by config.ron: keybindings[0]");
+    }
+
+    #[test]
+    fn should_warn_on_duplicate_binding() {
+        let source = config(
+            r#"(modifiers: [Alt], key: "k", action: MoveFocusUp),
+               (modifiers: [Alt], key: "k", action: MoveFocusUp),"#,
+        );
+
+        assert_that!(errors(&source)).is_empty();
+        assert_that!(check_source(&source)).has_length(1);
+    }
+
+    #[test]
+    fn should_report_unreachable_binding() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt, Shift], key: "k", action: MoveWindowUp),
+               (modifiers: [Alt], key: "K", action: MoveWindowDown),"#,
+        ));
+
+        assert_that!(errors).has_length(2);
+    }
+
+    #[test]
+    fn should_check_modes() {
+        let source = indoc! {r#"
+            (
+                gaps: 14,
+                keybindings: [
+                    (modifiers: [Ctrl, Shift], key: "Q", action: Quit),
+                    (modifiers: [Alt], key: "r", action: SetMode("resize")),
+                    (modifiers: [Alt], key: "p", action: SetMode("passthrough")),
+                    (modifiers: [Alt], key: "x", action: SetMode("missing")),
+                ],
+                modes: {
+                    "resize": [
+                        (modifiers: [Alt], key: "r", action: Close),
+                        (modifiers: [], key: "Escape", action: SetMode("default")),
+                    ],
+                    "passthrough": [],
+                },
+            )
+        "#};
+
+        let diagnostics = check_source(source);
+
+        assert_that!(diagnostics).has_length(2);
+        assert_that!(diagnostics[0].message).contains("unknown mode 'missing'");
+        assert_that!(diagnostics[1].message).contains("mode 'passthrough' has no binding");
+    }
+
+    #[test]
+    fn should_report_unterminated_quote_in_command() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], key: "t", action: Run(env: [], command: "alacritty -e 'htop")),"#,
+        ));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("Unterminated ' quote");
+    }
+
+    #[test]
+    fn should_report_chord_hidden_by_shorter_binding() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], key: "w", action: Close),
+               (modifiers: [Alt], key: "w", then: [(key: "1")], action: MoveToWorkspace(1)),"#,
+        ));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("chord Alt+w 1 is unreachable");
+    }
+
+    #[test]
+    fn should_accept_keycode_and_release_bindings() {
+        let errors = errors(&config(
+            r#"(modifiers: [Alt], keycode: 16, action: Close),
+               (modifiers: [Logo], key: "Super_L", on_release: true, action: Overview),"#,
+        ));
+
+        assert_that!(errors).is_empty();
+    }
+
+    #[test]
+    fn should_require_key_or_keycode() {
+        let errors = errors(&config(r#"(modifiers: [Alt], action: Close),"#));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("a key or a keycode is required");
+    }
+
+    #[test]
+    fn should_report_vt_switch_clash() {
+        let errors = errors(&config(
+            r#"(modifiers: [Ctrl, Alt], key: "F2", action: Close),"#,
+        ));
+
+        assert_that!(errors).has_length(1);
+        assert_that!(errors[0]).contains("VT switching");
+    }
+}
@@ -0,0 +1,427 @@
+//! A textual syntax for actions, `focus parent; workspace 3; exec alacritty`.
+//!
+//! Commands are separated by `;` or `,`, each one runs one or more [`Action`]s.
+//! Words can be quoted with `'` or `"`, `exec` takes the rest of its command unparsed.
+//! The i3 spellings of the supported commands, such as `splith`, `workspace number 2`
+//! or `exec --no-startup-id`, are accepted so i3 `RUN_COMMAND` messages use this syntax too.
+
+use crate::config::keybinding::Action;
+use crate::config::keyboard::LayoutSwitch;
+
+/// A syntax error, `column` is the 1-based character position of the mistake
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("column {column}: {message}")]
+pub struct CommandError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl CommandError {
+    /// The command with a caret under the mistake, for terminal output
+    pub fn show(&self, input: &str) -> String {
+        format!("{}\n{}^ {}", input, " ".repeat(self.column - 1), self.message)
+    }
+}
+
+/// A word of a command and the byte offset it starts at
+#[derive(Debug)]
+struct Word {
+    text: String,
+    offset: usize,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+}
+
+/// Parse a `;` separated list of commands into the actions they run, in order
+pub fn parse(input: &str) -> Result<Vec<Action>, CommandError> {
+    Ok(parse_list(input)?.into_iter().flatten().collect())
+}
+
+/// Parse a list of commands, keeping the actions of each command apart
+pub fn parse_list(input: &str) -> Result<Vec<Vec<Action>>, CommandError> {
+    let parser = Parser { input };
+    parser
+        .split()?
+        .into_iter()
+        .map(|(offset, command)| parser.command(offset, command))
+        .collect()
+}
+
+impl Parser<'_> {
+    fn error(&self, offset: usize, message: impl Into<String>) -> CommandError {
+        CommandError {
+            column: self.input[..offset].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Commands with the offset they start at, empty ones are skipped
+    fn split(&self) -> Result<Vec<(usize, &str)>, CommandError> {
+        let mut commands = vec![];
+        let mut quote = None;
+        let mut escaped = false;
+        let mut start = 0;
+
+        for (index, c) in self.input.char_indices() {
+            match (c, quote) {
+                _ if escaped => escaped = false,
+                ('\\', None) => escaped = true,
+                ('"' | '\'', None) => quote = Some((c, index)),
+                (c, Some((open, _))) if c == open => quote = None,
+                (';' | ',', None) => {
+                    commands.push((start, &self.input[start..index]));
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((_, offset)) = quote {
+            return Err(self.error(offset, "unterminated quote"));
+        }
+
+        commands.push((start, &self.input[start..]));
+        Ok(commands
+            .into_iter()
+            .filter(|(_, command)| !command.trim().is_empty())
+            .collect())
+    }
+
+    fn words(&self, offset: usize, command: &str) -> Result<Vec<Word>, CommandError> {
+        let mut words = vec![];
+        let mut current: Option<Word> = None;
+        let mut chars = command.char_indices();
+
+        while let Some((index, c)) = chars.next() {
+            if c.is_whitespace() {
+                words.extend(current.take());
+                continue;
+            }
+
+            let word = current.get_or_insert_with(|| Word {
+                text: String::new(),
+                offset: offset + index,
+            });
+
+            match c {
+                '"' | '\'' => {
+                    for (_, next) in chars.by_ref() {
+                        if next == c {
+                            break;
+                        }
+                        word.text.push(next);
+                    }
+                }
+                '\\' => match chars.next() {
+                    Some((_, next)) => word.text.push(next),
+                    None => return Err(self.error(offset + index, "trailing backslash")),
+                },
+                c => word.text.push(c),
+            }
+        }
+
+        words.extend(current);
+        Ok(words)
+    }
+
+    fn command(&self, offset: usize, command: &str) -> Result<Vec<Action>, CommandError> {
+        let trimmed = command.trim_start();
+        let offset = offset + command.len() - trimmed.len();
+        let end = offset + trimmed.trim_end().len();
+
+        // The command line is handed to `sh -c` as written
+        if let Some(exec) = trimmed.strip_prefix("exec") {
+            if exec.is_empty() || exec.starts_with(char::is_whitespace) {
+                let exec = exec.trim_start();
+                let exec = exec.strip_prefix("--no-startup-id").unwrap_or(exec).trim();
+                if exec.is_empty() {
+                    return Err(self.error(end, "expected a command to run"));
+                }
+
+                return Ok(vec![Action::Run {
+                    env: vec![],
+                    command: exec.to_string(),
+                    shell: true,
+                }]);
+            }
+        }
+
+        if trimmed.starts_with('[') {
+            return Err(self.error(offset, "criteria are not supported"));
+        }
+
+        let words = self.words(offset, trimmed)?;
+        let name = &words[0];
+        let args = &words[1..];
+        let texts: Vec<&str> = args.iter().map(|word| word.text.as_str()).collect();
+
+        // Points at the first argument not understood, or past the end if one is missing
+        let unexpected = |index: usize, expected: &str| match args.get(index) {
+            Some(word) => self.error(
+                word.offset,
+                format!("expected {}, found '{}'", expected, word.text),
+            ),
+            None => self.error(end, format!("expected {}", expected)),
+        };
+
+        let actions = match (name.text.as_str(), texts.as_slice()) {
+            ("nop", _) => vec![],
+            ("workspace", ["number", _]) => {
+                vec![Action::MoveToWorkspace(self.workspace(&args[1])?)]
+            }
+            (
+                "focus" | "workspace" | "layout" | "split" | "floating" | "fullscreen" | "mode"
+                | "keyboard-layout",
+                [_, _, ..],
+            ) => return Err(unexpected(1, "the end of the command")),
+            ("focus", ["left"]) => vec![Action::MoveFocusLeft],
+            ("focus", ["right"]) => vec![Action::MoveFocusRight],
+            ("focus", ["up"]) => vec![Action::MoveFocusUp],
+            ("focus", ["down"]) => vec![Action::MoveFocusDown],
+            ("focus", ["parent"]) => vec![Action::FocusParent],
+            ("focus", _) => return Err(unexpected(0, "left, right, up, down or parent")),
+            ("move", _) => self.move_command(args, &unexpected)?,
+            ("workspace", ["next" | "next_on_output"]) => vec![Action::WorkspaceNext],
+            ("workspace", ["prev" | "prev_on_output"]) => vec![Action::WorkspacePrevious],
+            ("workspace", [_]) => vec![Action::MoveToWorkspace(self.workspace(&args[0])?)],
+            ("workspace", _) => return Err(unexpected(0, "a workspace number, next or prev")),
+            ("layout", ["vertical" | "splitv"])
+            | ("split", ["v" | "vertical"])
+            | ("splitv", []) => vec![Action::LayoutVertical],
+            ("layout", ["horizontal" | "splith"])
+            | ("split", ["h" | "horizontal"])
+            | ("splith", []) => vec![Action::LayoutHorizontal],
+            ("layout", _) => return Err(unexpected(0, "vertical or horizontal")),
+            ("split", _) => return Err(unexpected(0, "h, v, horizontal or vertical")),
+            ("floating", ["toggle"]) => vec![Action::ToggleFloating],
+            ("floating", _) => return Err(unexpected(0, "toggle")),
+            ("fullscreen", [] | ["window" | "toggle"]) => vec![Action::ToggleFullScreenWindow],
+            ("fullscreen", ["container"]) => vec![Action::ToggleFullScreenContainer],
+            ("fullscreen", _) => return Err(unexpected(0, "window or container")),
+            ("close" | "kill", []) => vec![Action::Close],
+            ("switcher", []) => vec![Action::CycleWindows],
+            ("overview", []) => vec![Action::Overview],
+            ("palette", []) => vec![Action::CommandPalette],
+            ("mode", [mode]) => vec![Action::SetMode(mode.to_string())],
+            ("mode", _) => return Err(unexpected(0, "a mode name")),
+            ("keyboard-layout", ["next"]) => vec![Action::SwitchLayout(LayoutSwitch::Next)],
+            ("keyboard-layout", ["prev"]) => vec![Action::SwitchLayout(LayoutSwitch::Prev)],
+            ("keyboard-layout", [index]) => match index.parse() {
+                Ok(index) => vec![Action::SwitchLayout(LayoutSwitch::Index(index))],
+                Err(_) => return Err(unexpected(0, "next, prev or a layout index")),
+            },
+            ("keyboard-layout", _) => return Err(unexpected(0, "next, prev or a layout index")),
+            ("reload" | "restart", []) => vec![Action::Reload],
+            ("quit" | "exit", []) => vec![Action::Quit],
+            (
+                "close" | "kill" | "switcher" | "overview" | "palette" | "reload" | "restart"
+                | "quit" | "exit" | "splith" | "splitv",
+                _,
+            ) => {
+                let message = format!("'{}' takes no argument", name.text);
+                return Err(self.error(args[0].offset, message));
+            }
+            (unknown, _) => {
+                return Err(self.error(name.offset, format!("unknown command '{}'", unknown)))
+            }
+        };
+
+        Ok(actions)
+    }
+
+    /// `move [window|container] <direction>` and
+    /// `move [window|container] to workspace [number] <n> [follow]`
+    fn move_command(
+        &self,
+        args: &[Word],
+        unexpected: &dyn Fn(usize, &str) -> CommandError,
+    ) -> Result<Vec<Action>, CommandError> {
+        let texts: Vec<&str> = args.iter().map(|word| word.text.as_str()).collect();
+
+        let actions = match texts.as_slice() {
+            ["left"] | ["window", "left"] => vec![Action::MoveWindowLeft],
+            ["right"] | ["window", "right"] => vec![Action::MoveWindowRight],
+            ["up"] | ["window", "up"] => vec![Action::MoveWindowUp],
+            ["down"] | ["window", "down"] => vec![Action::MoveWindowDown],
+            // Moving containers is not implemented yet
+            ["container", "left" | "right" | "up" | "down"] => {
+                return Err(self.error(args[0].offset, "moving a container is not supported"))
+            }
+            ["container", "to", ..] => return Err(unexpected(2, "workspace")),
+            ["container", ..] => return Err(unexpected(1, "left, right, up, down or to")),
+            ["to", "workspace", ..] | ["window" | "container", "to", "workspace", ..] => {
+                let mut index = texts.iter().position(|text| *text == "workspace").unwrap() + 1;
+                if texts.get(index) == Some(&"number") {
+                    index += 1;
+                }
+
+                let num = match args.get(index) {
+                    Some(word) => self.workspace(word)?,
+                    None => return Err(unexpected(index, "a workspace number")),
+                };
+
+                match &texts[index + 1..] {
+                    [] => vec![Action::MoveWindowToWorkspace(num)],
+                    ["follow"] => vec![
+                        Action::MoveWindowToWorkspace(num),
+                        Action::MoveToWorkspace(num),
+                    ],
+                    _ => return Err(unexpected(index + 1, "follow or the end of the command")),
+                }
+            }
+            ["window", "to", ..] => return Err(unexpected(2, "workspace")),
+            ["window", ..] => return Err(unexpected(1, "left, right, up, down or to")),
+            ["to", ..] => return Err(unexpected(1, "workspace")),
+            [direction, _, ..] if ["left", "right", "up", "down"].contains(direction) => {
+                return Err(unexpected(1, "the end of the command"))
+            }
+            _ => {
+                return Err(unexpected(
+                    0,
+                    "left, right, up, down, window, container or to",
+                ))
+            }
+        };
+
+        Ok(actions)
+    }
+
+    /// Workspaces are numbered, i3 names starting with a number such as `2:web` are accepted
+    fn workspace(&self, word: &Word) -> Result<u8, CommandError> {
+        let digits: String = word.text.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().map_err(|_| {
+            self.error(
+                word.offset,
+                format!("expected a workspace number, found '{}'", word.text),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::command::{parse, parse_list, CommandError};
+    use crate::config::keybinding::Action;
+    use crate::config::keyboard::LayoutSwitch;
+    use speculoos::prelude::*;
+
+    fn error(input: &str) -> CommandError {
+        parse(input).unwrap_err()
+    }
+
+    #[test]
+    fn should_parse_chained_commands() {
+        let actions = parse("move window left; focus parent; workspace 3; exec alacritty");
+
+        assert_that!(actions.unwrap()).is_equal_to(vec![
+            Action::MoveWindowLeft,
+            Action::FocusParent,
+            Action::MoveToWorkspace(3),
+            Action::Run {
+                env: vec![],
+                command: "alacritty".to_string(),
+                shell: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn should_expand_command_to_several_actions() {
+        assert_that!(parse("move to workspace 2 follow").unwrap()).is_equal_to(vec![
+            Action::MoveWindowToWorkspace(2),
+            Action::MoveToWorkspace(2),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_exec_command_line_and_quoted_separators() {
+        assert_that!(parse("exec notify-send 'a; b'; kill").unwrap()).is_equal_to(vec![
+            Action::Run {
+                env: vec![],
+                command: "notify-send 'a; b'".to_string(),
+                shell: true,
+            },
+            Action::Close,
+        ]);
+    }
+
+    #[test]
+    fn should_parse_quoted_arguments() {
+        assert_that!(parse("mode \"resize mode\"; keyboard-layout 1").unwrap()).is_equal_to(vec![
+            Action::SetMode("resize mode".to_string()),
+            Action::SwitchLayout(LayoutSwitch::Index(1)),
+        ]);
+    }
+
+    #[test]
+    fn should_skip_empty_commands() {
+        assert_that!(parse(" ; nop;;").unwrap()).is_empty();
+    }
+
+    #[test]
+    fn should_report_unknown_command_position() {
+        assert_that!(error("focus left; dance")).is_equal_to(CommandError {
+            column: 13,
+            message: "unknown command 'dance'".to_string(),
+        });
+    }
+
+    #[test]
+    fn should_report_bad_argument_position() {
+        let err = error("workspace 3; move window to workspace three");
+
+        assert_that!(err.column).is_equal_to(39);
+        assert_that!(err.show("workspace 3; move window to workspace three"))
+            .ends_with("^ expected a workspace number, found 'three'");
+    }
+
+    #[test]
+    fn should_report_missing_argument_at_end_of_command() {
+        assert_that!(error("focus; kill").column).is_equal_to(6);
+        assert_that!(error("exec ").column).is_equal_to(5);
+    }
+
+    #[test]
+    fn should_accept_i3_spellings() {
+        let actions = parse_list(
+            "workspace number 2:web, move container to workspace number 3; splith; \
+             layout splitv; fullscreen toggle; exec --no-startup-id foot",
+        );
+
+        assert_that!(actions.unwrap()).is_equal_to(vec![
+            vec![Action::MoveToWorkspace(2)],
+            vec![Action::MoveWindowToWorkspace(3)],
+            vec![Action::LayoutHorizontal],
+            vec![Action::LayoutVertical],
+            vec![Action::ToggleFullScreenWindow],
+            vec![Action::Run {
+                env: vec![],
+                command: "foot".to_string(),
+                shell: true,
+            }],
+        ]);
+    }
+
+    #[test]
+    fn should_reject_criteria() {
+        assert_that!(error("kill; [class=\"foot\"] kill")).is_equal_to(CommandError {
+            column: 7,
+            message: "criteria are not supported".to_string(),
+        });
+    }
+
+    #[test]
+    fn should_reject_move_container() {
+        assert_that!(error("focus parent; move container left")).is_equal_to(CommandError {
+            column: 20,
+            message: "moving a container is not supported".to_string(),
+        });
+    }
+
+    #[test]
+    fn should_report_unterminated_quote() {
+        assert_that!(error("mode 'resize").column).is_equal_to(6);
+    }
+}
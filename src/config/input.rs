@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings applied to the input devices selected by `matches`,
+/// unset options keep the libinput default
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InputConfig {
+    pub matches: DeviceMatch,
+    #[serde(default)]
+    pub tap: Option<bool>,
+    #[serde(default)]
+    pub natural_scroll: Option<bool>,
+    #[serde(default)]
+    pub accel_profile: Option<AccelProfile>,
+    /// Pointer acceleration between -1.0 and 1.0
+    #[serde(default)]
+    pub accel_speed: Option<f64>,
+    /// Disable the touchpad while typing
+    #[serde(default)]
+    pub dwt: Option<bool>,
+    #[serde(default)]
+    pub left_handed: Option<bool>,
+    #[serde(default)]
+    pub scroll_method: Option<ScrollMethod>,
+    #[serde(default)]
+    pub click_method: Option<ClickMethod>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeviceMatch {
+    /// Device name as reported by libinput, see `libinput list-devices`
+    Name(String),
+    Type(DeviceType),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DeviceType {
+    Touchpad,
+    Pointer,
+    Keyboard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AccelProfile {
+    Flat,
+    Adaptive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScrollMethod {
+    NoScroll,
+    TwoFinger,
+    Edge,
+    OnButtonDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ClickMethod {
+    ButtonAreas,
+    Clickfinger,
+}
+
+/// Options resolved for a single device
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputSettings {
+    pub tap: Option<bool>,
+    pub natural_scroll: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    pub accel_speed: Option<f64>,
+    pub dwt: Option<bool>,
+    pub left_handed: Option<bool>,
+    pub scroll_method: Option<ScrollMethod>,
+    pub click_method: Option<ClickMethod>,
+}
+
+impl DeviceMatch {
+    pub fn matches(&self, name: &str, types: &[DeviceType]) -> bool {
+        match self {
+            DeviceMatch::Name(expected) => expected == name,
+            DeviceMatch::Type(device_type) => types.contains(device_type),
+        }
+    }
+}
+
+/// Merge every config matching the device, type matches are applied first
+/// so a name match always takes precedence
+pub fn settings_for(inputs: &[InputConfig], name: &str, types: &[DeviceType]) -> InputSettings {
+    let by_type = inputs
+        .iter()
+        .filter(|input| matches!(input.matches, DeviceMatch::Type(_)));
+    let by_name = inputs
+        .iter()
+        .filter(|input| matches!(input.matches, DeviceMatch::Name(_)));
+
+    by_type
+        .chain(by_name)
+        .filter(|input| input.matches.matches(name, types))
+        .fold(InputSettings::default(), |settings, input| InputSettings {
+            tap: input.tap.or(settings.tap),
+            natural_scroll: input.natural_scroll.or(settings.natural_scroll),
+            accel_profile: input.accel_profile.or(settings.accel_profile),
+            accel_speed: input.accel_speed.or(settings.accel_speed),
+            dwt: input.dwt.or(settings.dwt),
+            left_handed: input.left_handed.or(settings.left_handed),
+            scroll_method: input.scroll_method.or(settings.scroll_method),
+            click_method: input.click_method.or(settings.click_method),
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::input::{
+        settings_for, AccelProfile, DeviceMatch, DeviceType, InputConfig, InputSettings,
+    };
+    use speculoos::prelude::*;
+
+    fn input(matches: DeviceMatch) -> InputConfig {
+        InputConfig {
+            matches,
+            tap: None,
+            natural_scroll: None,
+            accel_profile: None,
+            accel_speed: None,
+            dwt: None,
+            left_handed: None,
+            scroll_method: None,
+            click_method: None,
+        }
+    }
+
+    #[test]
+    fn should_parse_input_config() {
+        let config: Vec<InputConfig> = ron::from_str(
+            r#"[(matches: Type(Touchpad), tap: Some(true), accel_profile: Some(Flat))]"#,
+        )
+        .unwrap();
+
+        assert_that!(config[0].matches).is_equal_to(DeviceMatch::Type(DeviceType::Touchpad));
+        assert_that!(config[0].tap).is_equal_to(Some(true));
+        assert_that!(config[0].accel_profile).is_equal_to(Some(AccelProfile::Flat));
+        assert_that!(config[0].dwt).is_none();
+    }
+
+    #[test]
+    fn should_ignore_devices_not_matching() {
+        let inputs = vec![InputConfig {
+            tap: Some(true),
+            ..input(DeviceMatch::Type(DeviceType::Touchpad))
+        }];
+
+        let settings = settings_for(&inputs, "Logitech Mouse", &[DeviceType::Pointer]);
+
+        assert_that!(settings).is_equal_to(InputSettings::default());
+    }
+
+    #[test]
+    fn name_match_should_override_type_match() {
+        let inputs = vec![
+            InputConfig {
+                natural_scroll: Some(false),
+                accel_speed: Some(0.5),
+                ..input(DeviceMatch::Name("SynPS/2 Touchpad".to_string()))
+            },
+            InputConfig {
+                natural_scroll: Some(true),
+                tap: Some(true),
+                ..input(DeviceMatch::Type(DeviceType::Touchpad))
+            },
+        ];
+
+        let settings = settings_for(
+            &inputs,
+            "SynPS/2 Touchpad",
+            &[DeviceType::Touchpad, DeviceType::Pointer],
+        );
+
+        assert_that!(settings.natural_scroll).is_equal_to(Some(false));
+        assert_that!(settings.tap).is_equal_to(Some(true));
+        assert_that!(settings.accel_speed).is_equal_to(Some(0.5));
+    }
+}
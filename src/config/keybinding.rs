@@ -1,8 +1,10 @@
+use crate::config::keyboard::LayoutSwitch;
 use crate::inputs::handlers::Direction;
 use crate::inputs::KeyAction;
 use serde::{Deserialize, Serialize, Serializer};
 use smithay::input::keyboard::ModifiersState;
 use std::collections::HashSet;
+use std::fmt;
 use std::hash::Hash;
 use xkbcommon::xkb;
 use xkbcommon::xkb::Keysym;
@@ -10,52 +12,194 @@ use xkbcommon::xkb::Keysym;
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct KeyBinding {
     pub modifiers: HashSet<Modifier>,
+    #[serde(default = "no_symbol", skip_serializing_if = "is_no_symbol")]
     #[serde(serialize_with = "serialize_key")]
     #[serde(deserialize_with = "deserialize_key")]
     pub key: Keysym,
+    /// Evdev keycode matched instead of `key`, the same physical key works with every layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keycode: Option<u32>,
+    /// Keys to press after `modifiers` + `key` to trigger the action
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub then: Vec<KeyStroke>,
+    /// Trigger the action when the last key is released instead of pressed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub on_release: bool,
     pub action: Action,
 }
 
+/// A key pressed with modifiers held, a step of a chord binding
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyStroke {
+    #[serde(default)]
+    pub modifiers: HashSet<Modifier>,
+    #[serde(default = "no_symbol", skip_serializing_if = "is_no_symbol")]
+    #[serde(serialize_with = "serialize_key")]
+    #[serde(deserialize_with = "deserialize_key")]
+    pub key: Keysym,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keycode: Option<u32>,
+}
+
+/// A key press as seen by the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PressedKey {
+    pub modifiers: ModifiersState,
+    pub keysym: Keysym,
+    pub keycode: u32,
+}
+
+/// How the keys pressed so far compare to a binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceMatch {
+    Full,
+    Prefix,
+    None,
+}
+
 impl KeyBinding {
-    pub fn match_action(&self, modifiers: ModifiersState, key: Keysym) -> Option<Action> {
-        let state: ModifiersState = self.into();
-        if match_modifier(state, modifiers) && key == self.key {
-            Some(self.action.clone())
-        } else {
-            None
+    /// Compare the key strokes of a pending chord to this binding
+    pub fn match_sequence(&self, pressed: &[PressedKey]) -> SequenceMatch {
+        let strokes = self.strokes();
+        if pressed.is_empty() || pressed.len() > strokes.len() {
+            return SequenceMatch::None;
+        }
+
+        let matches = strokes
+            .iter()
+            .zip(pressed)
+            .all(|(stroke, pressed)| stroke.matches(pressed));
+
+        match (matches, pressed.len() == strokes.len()) {
+            (false, _) => SequenceMatch::None,
+            (true, true) => SequenceMatch::Full,
+            (true, false) => SequenceMatch::Prefix,
         }
     }
+
+    /// Every key stroke of the binding, in order
+    pub fn strokes(&self) -> Vec<KeyStroke> {
+        let first = KeyStroke {
+            modifiers: self.modifiers.clone(),
+            key: self.key,
+            keycode: self.keycode,
+        };
+
+        std::iter::once(first).chain(self.then.iter().cloned()).collect()
+    }
 }
 
-fn match_modifier(modifier: ModifiersState, other: ModifiersState) -> bool {
-    (
-        modifier.ctrl,
-        modifier.alt,
-        modifier.shift,
-        modifier.logo,
-        modifier.caps_lock,
-        modifier.num_lock,
-    ) == (
-        other.ctrl,
-        other.alt,
-        other.shift,
-        other.logo,
-        other.caps_lock,
-        other.num_lock,
-    )
+impl KeyStroke {
+    pub fn from_pressed(pressed: &PressedKey) -> Self {
+        let modifiers = pressed.modifiers;
+        let modifiers = [
+            (modifiers.ctrl, Modifier::Ctrl),
+            (modifiers.alt, Modifier::Alt),
+            (modifiers.shift, Modifier::Shift),
+            (modifiers.logo, Modifier::Logo),
+            (modifiers.caps_lock, Modifier::CapsLock),
+            (modifiers.num_lock, Modifier::NumLock),
+        ]
+        .into_iter()
+        .filter_map(|(pressed, modifier)| pressed.then_some(modifier))
+        .collect();
+
+        Self {
+            modifiers,
+            key: pressed.keysym,
+            keycode: None,
+        }
+    }
+
+    pub fn matches(&self, pressed: &PressedKey) -> bool {
+        let expected = modifiers_state(&self.modifiers);
+        let modifiers = pressed.modifiers;
+
+        // CapsLock and NumLock are ignored unless the binding requires them
+        let ignore_caps_lock = modifiers.caps_lock && !expected.caps_lock;
+
+        let key_matches = match self.keycode {
+            Some(keycode) => keycode == pressed.keycode,
+            None if self.key == xkb::KEY_NoSymbol => false,
+            None if ignore_caps_lock => to_lower(self.key) == to_lower(pressed.keysym),
+            None => self.key == pressed.keysym,
+        };
+
+        key_matches && match_modifier(expected, modifiers)
+    }
+}
+
+impl fmt::Display for KeyStroke {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (Modifier::Ctrl, "Ctrl"),
+            (Modifier::Alt, "Alt"),
+            (Modifier::Shift, "Shift"),
+            (Modifier::Logo, "Logo"),
+            (Modifier::CapsLock, "CapsLock"),
+            (Modifier::NumLock, "NumLock"),
+        ];
+
+        for (modifier, name) in modifiers {
+            if self.modifiers.contains(&modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        match self.keycode {
+            Some(keycode) => write!(f, "keycode:{}", keycode),
+            None => write!(f, "{}", xkb::keysym_get_name(self.key)),
+        }
+    }
+}
+
+/// Compare the modifiers listed in a binding to the current modifiers state
+pub fn modifiers_match(modifiers: &HashSet<Modifier>, state: ModifiersState) -> bool {
+    match_modifier(modifiers_state(modifiers), state)
+}
+
+/// Held modifiers must match exactly, locked ones only when the binding lists them
+fn match_modifier(expected: ModifiersState, modifiers: ModifiersState) -> bool {
+    let held = (expected.ctrl, expected.alt, expected.shift, expected.logo)
+        == (modifiers.ctrl, modifiers.alt, modifiers.shift, modifiers.logo);
+
+    let locked = (!expected.caps_lock || modifiers.caps_lock)
+        && (!expected.num_lock || modifiers.num_lock);
+
+    held && locked
+}
+
+/// Lower case keysym of a letter, xkb prefers lower case keysyms on case insensitive lookup
+fn to_lower(key: Keysym) -> Keysym {
+    match xkb::keysym_from_name(&xkb::keysym_get_name(key), xkb::KEYSYM_CASE_INSENSITIVE) {
+        xkb::KEY_NoSymbol => key,
+        lower => lower,
+    }
+}
+
+fn no_symbol() -> Keysym {
+    xkb::KEY_NoSymbol
+}
+
+fn is_no_symbol(key: &Keysym) -> bool {
+    *key == xkb::KEY_NoSymbol
 }
 
 impl Into<ModifiersState> for &KeyBinding {
     fn into(self) -> ModifiersState {
-        ModifiersState {
-            ctrl: self.modifiers.contains(&Modifier::Ctrl),
-            alt: self.modifiers.contains(&Modifier::Alt),
-            shift: self.modifiers.contains(&Modifier::Shift),
-            caps_lock: self.modifiers.contains(&Modifier::CapsLock),
-            logo: self.modifiers.contains(&Modifier::Logo),
-            num_lock: self.modifiers.contains(&Modifier::NumLock),
-            serialized: Default::default(),
-        }
+        modifiers_state(&self.modifiers)
+    }
+}
+
+fn modifiers_state(modifiers: &HashSet<Modifier>) -> ModifiersState {
+    ModifiersState {
+        ctrl: modifiers.contains(&Modifier::Ctrl),
+        alt: modifiers.contains(&Modifier::Alt),
+        shift: modifiers.contains(&Modifier::Shift),
+        caps_lock: modifiers.contains(&Modifier::CapsLock),
+        logo: modifiers.contains(&Modifier::Logo),
+        num_lock: modifiers.contains(&Modifier::NumLock),
+        serialized: Default::default(),
     }
 }
 
@@ -75,16 +219,37 @@ pub enum Action {
     MoveFocusRight,
     MoveFocusDown,
     MoveFocusUp,
+    /// Focus the container holding the focused window or container
+    FocusParent,
     MoveToWorkspace(u8),
+    WorkspaceNext,
+    WorkspacePrevious,
+    MoveWindowToWorkspace(u8),
     LayoutVertical,
     LayoutHorizontal,
     ToggleFloating,
+    CycleWindows,
+    Overview,
     Run {
         env: Vec<(String, String)>,
         command: String,
+        /// Run the command with `sh -c`, allowing pipes, redirections and variables
+        #[serde(default)]
+        shell: bool,
     },
     Close,
+    /// Move a floating window with the pointer, for mouse bindings only
+    DragMove,
+    /// Resize a floating window with the pointer, for mouse bindings only
+    DragResize,
+    SetMode(String),
+    SwitchLayout(LayoutSwitch),
+    Reload,
     Quit,
+    /// Commands in the text syntax of [`crate::config::command`], `Command("focus parent; kill")`
+    Command(String),
+    /// Run `command_palette` and the commands it prints
+    CommandPalette,
 }
 
 impl Into<KeyAction> for Action {
@@ -102,13 +267,34 @@ impl Into<KeyAction> for Action {
             Action::MoveFocusRight => KeyAction::MoveFocus(Direction::Right),
             Action::MoveFocusDown => KeyAction::MoveFocus(Direction::Down),
             Action::MoveFocusUp => KeyAction::MoveFocus(Direction::Up),
+            Action::FocusParent => KeyAction::FocusParent,
             Action::MoveToWorkspace(num) => KeyAction::MoveToWorkspace(num),
+            Action::WorkspaceNext => KeyAction::CycleWorkspace(1),
+            Action::WorkspacePrevious => KeyAction::CycleWorkspace(-1),
+            // Dragging needs a pointer grab, see `CallLoopData::start_pointer_drag`
+            Action::DragMove | Action::DragResize => KeyAction::None,
             Action::LayoutVertical => KeyAction::LayoutVertical,
             Action::LayoutHorizontal => KeyAction::LayoutHorizontal,
             Action::ToggleFloating => KeyAction::ToggleFloating,
-            Action::Run { command, env } => KeyAction::Run(command, env),
+            Action::CycleWindows => KeyAction::CycleWindows,
+            Action::Overview => KeyAction::ToggleOverview,
+            Action::MoveWindowToWorkspace(num) => KeyAction::MoveWindowToWorkspace(num),
+            Action::Run {
+                command,
+                env,
+                shell,
+            } => KeyAction::Run {
+                command,
+                env,
+                shell,
+            },
             Action::Close => KeyAction::Close,
+            Action::SetMode(mode) => KeyAction::SetMode(mode),
+            Action::SwitchLayout(switch) => KeyAction::SwitchLayout(switch),
+            Action::Reload => KeyAction::Reload,
             Action::Quit => KeyAction::Quit,
+            Action::Command(command) => KeyAction::Command(command),
+            Action::CommandPalette => KeyAction::CommandPalette,
             Action::ToggleFullScreenWindow => KeyAction::ToggleFullScreenWindow,
             Action::ToggleFullScreenContainer => KeyAction::ToggleFullScreenContainer,
         }
@@ -139,6 +325,24 @@ where
     serializer.serialize_str(&name)
 }
 
+/// Result of resolving a keysym name from the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeysymLookup {
+    Exact(Keysym),
+    CaseInsensitive(Keysym),
+    Unknown,
+}
+
+pub fn lookup_keysym(name: &str) -> KeysymLookup {
+    match xkb::keysym_from_name(name, xkb::KEY_NoSymbol) {
+        xkb::KEY_NoSymbol => match xkb::keysym_from_name(name, xkb::KEYSYM_CASE_INSENSITIVE) {
+            xkb::KEY_NoSymbol => KeysymLookup::Unknown,
+            key => KeysymLookup::CaseInsensitive(key),
+        },
+        key => KeysymLookup::Exact(key),
+    }
+}
+
 #[allow(non_snake_case)]
 fn deserialize_key<'de, D>(deserializer: D) -> Result<Keysym, D::Error>
 where
@@ -147,22 +351,20 @@ where
     use serde::de::{Error, Unexpected};
 
     let name = String::deserialize(deserializer)?;
-    match xkb::keysym_from_name(&name, xkb::KEY_NoSymbol) {
-        xkb::KEY_NoSymbol => match xkb::keysym_from_name(&name, xkb::KEYSYM_CASE_INSENSITIVE) {
-            xkb::KEY_NoSymbol => Err(<D::Error as Error>::invalid_value(
-                Unexpected::Str(&name),
-                &"xkb keysym",
-            )),
-            key => {
-                slog_scope::warn!(
-                    "Key-Binding '{}' only matched case insensitive for {:?}",
-                    name,
-                    xkb::keysym_get_name(key)
-                );
-                Ok(key)
-            }
-        },
-        key => Ok(key),
+    match lookup_keysym(&name) {
+        KeysymLookup::Unknown => Err(<D::Error as Error>::invalid_value(
+            Unexpected::Str(&name),
+            &"xkb keysym",
+        )),
+        KeysymLookup::CaseInsensitive(key) => {
+            slog_scope::warn!(
+                "Key-Binding '{}' only matched case insensitive for {:?}",
+                name,
+                xkb::keysym_get_name(key)
+            );
+            Ok(key)
+        }
+        KeysymLookup::Exact(key) => Ok(key),
     }
 }
 
@@ -198,6 +400,7 @@ mod test {
         assert_that!(binding.action).is_equal_to(Action::Run {
             env: vec![],
             command: "alacritty".to_string(),
+            shell: false,
         });
     }
 
@@ -207,62 +410,97 @@ mod test {
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_t,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::Run {
                     env: vec![],
                     command: "alacritty".to_string(),
+                    shell: false,
                 },
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_g,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::Run {
                     env: vec![("WGPU_BACKEND".into(), "vulkan".into())],
                     command: "onagre".to_string(),
+                    shell: false,
                 },
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_a,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::Close,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_v,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::LayoutVertical,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_d,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::LayoutHorizontal,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Ctrl, Modifier::Shift]),
                 key: xkb::KEY_space,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::ToggleFloating,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_k,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::MoveFocusUp,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_h,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::MoveFocusLeft,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_l,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::MoveFocusRight,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_j,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::MoveFocusDown,
             },
             KeyBinding {
                 modifiers: HashSet::from([Modifier::Alt]),
                 key: xkb::KEY_k,
+                keycode: None,
+                then: vec![],
+                on_release: false,
                 action: Action::MoveFocusUp,
             },
         ];
@@ -270,6 +508,7 @@ mod test {
         let config = WazemmesConfig {
             gaps: 14,
             keybindings: binding,
+            ..WazemmesConfig::default()
         };
 
         let result = ron::to_string(&config).unwrap();
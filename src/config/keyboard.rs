@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use smithay::input::keyboard::XkbConfig;
+
+/// XKB keymap and key repeat settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeyboardConfig {
+    pub rules: String,
+    pub model: String,
+    /// Comma separated layouts, `SwitchLayout` cycles through them
+    pub layout: String,
+    /// Comma separated variants, one per layout
+    pub variant: String,
+    pub options: Option<String>,
+    /// Delay before a held key starts repeating, in milliseconds
+    pub repeat_delay: i32,
+    /// Repeated key presses per second
+    pub repeat_rate: i32,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self {
+            rules: String::new(),
+            model: String::new(),
+            layout: String::new(),
+            variant: String::new(),
+            options: None,
+            repeat_delay: 200,
+            repeat_rate: 200,
+        }
+    }
+}
+
+/// Target of a `SwitchLayout` action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LayoutSwitch {
+    Next,
+    Prev,
+    Index(usize),
+}
+
+impl KeyboardConfig {
+    pub fn layouts(&self) -> Vec<&str> {
+        self.layout.split(',').map(str::trim).collect()
+    }
+
+    pub fn layout_count(&self) -> usize {
+        self.layouts().len()
+    }
+
+    fn variant(&self, index: usize) -> &str {
+        self.variant.split(',').map(str::trim).nth(index).unwrap_or("")
+    }
+
+    /// Keymap of a single layout, layouts are switched by loading another keymap
+    pub fn xkb_config(&self, index: usize) -> XkbConfig<'_> {
+        XkbConfig {
+            rules: &self.rules,
+            model: &self.model,
+            layout: self.layouts().get(index).copied().unwrap_or(""),
+            variant: self.variant(index),
+            options: self.options.clone(),
+        }
+    }
+
+    /// Layout and variant of a layout, for example `fr(bepo)`
+    pub fn layout_name(&self, index: usize) -> String {
+        let layout = match self.layouts().get(index).copied() {
+            Some("") | None => "us",
+            Some(layout) => layout,
+        };
+
+        match self.variant(index) {
+            "" => layout.to_string(),
+            variant => format!("{}({})", layout, variant),
+        }
+    }
+
+    pub fn switch_index(&self, current: usize, switch: LayoutSwitch) -> usize {
+        let count = self.layout_count();
+        match switch {
+            LayoutSwitch::Next => (current + 1) % count,
+            LayoutSwitch::Prev => (current + count - 1) % count,
+            LayoutSwitch::Index(index) => index.min(count - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::keyboard::{KeyboardConfig, LayoutSwitch};
+    use speculoos::prelude::*;
+
+    fn config() -> KeyboardConfig {
+        KeyboardConfig {
+            layout: "fr,de".to_string(),
+            variant: "bepo".to_string(),
+            ..KeyboardConfig::default()
+        }
+    }
+
+    #[test]
+    fn should_name_layouts() {
+        let config = config();
+
+        assert_that!(config.layout_name(0)).is_equal_to("fr(bepo)".to_string());
+        assert_that!(config.layout_name(1)).is_equal_to("de".to_string());
+        assert_that!(KeyboardConfig::default().layout_name(0)).is_equal_to("us".to_string());
+    }
+
+    #[test]
+    fn should_switch_layouts() {
+        let config = config();
+
+        assert_that!(config.switch_index(1, LayoutSwitch::Next)).is_equal_to(0);
+        assert_that!(config.switch_index(0, LayoutSwitch::Prev)).is_equal_to(1);
+        assert_that!(config.switch_index(0, LayoutSwitch::Index(5))).is_equal_to(1);
+    }
+
+    #[test]
+    fn should_build_xkb_config_for_a_single_layout() {
+        let config = config();
+        let xkb = config.xkb_config(0);
+
+        assert_that!(xkb.layout).is_equal_to("fr");
+        assert_that!(xkb.variant).is_equal_to("bepo");
+    }
+}
@@ -1,54 +1,313 @@
+use crate::config::animation::AnimationConfig;
+use crate::config::input::InputConfig;
 use crate::config::keybinding::{Action, KeyBinding, Modifier};
-use once_cell::sync::Lazy;
+use crate::config::keyboard::KeyboardConfig;
+use crate::config::mousebinding::{default_mouse_bindings, MouseBinding};
+use crate::config::output::OutputConfig;
+use eyre::{eyre, WrapErr};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use slog_scope::warn;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
+use std::path::{Path, PathBuf};
 use xkbcommon::xkb;
 
+pub mod animation;
+pub mod check;
+pub mod command;
+pub mod input;
+pub mod keyboard;
 pub mod keybinding;
+pub mod mousebinding;
+pub mod output;
+pub mod reload;
 
-pub static CONFIG: Lazy<WazemmesConfig> = Lazy::new(WazemmesConfig::default);
+/// The binding mode using `keybindings`, other modes are declared in `modes`
+pub const DEFAULT_MODE: &str = "default";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WazemmesConfig {
     pub gaps: u32,
+    #[serde(default)]
+    pub animation: AnimationConfig,
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+    /// Mode, position, scale and transform of the outputs, matched when they are connected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputConfig>,
+    /// Libinput settings, matched against each device when it is added
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<InputConfig>,
     pub keybindings: Vec<KeyBinding>,
+    /// Milliseconds to wait for the next key of a chord binding
+    #[serde(default = "default_chord_timeout")]
+    pub chord_timeout: u64,
+    #[serde(default = "default_mouse_bindings")]
+    pub mouse_bindings: Vec<MouseBinding>,
+    /// Named sets of keybindings replacing `keybindings` while the mode is active
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub modes: HashMap<String, Vec<KeyBinding>>,
+    /// Shell commands run once when the compositor starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub autostart: Vec<String>,
+    /// Shell commands run when the compositor starts and again on every config reload
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exec_always: Vec<String>,
+    /// Directory where the output of spawned programs is written, one `<program>.log` file
+    /// per program. Output is discarded when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_dir: Option<PathBuf>,
+    /// Environment variables set for every spawned program, `Run` actions can override them
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Additional config files, relative paths are resolved from the including file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<PathBuf>,
+    /// Program started by `CommandPalette`, such as `fuzzel --dmenu`, the line it prints
+    /// is run as a command, see [`command`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_palette: Option<String>,
+    /// Every file this config was read from, the main config file comes first
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+/// The content of an included config file, its entries are appended to the main config
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFragment {
+    pub includes: Vec<PathBuf>,
+    pub outputs: Vec<OutputConfig>,
+    pub inputs: Vec<InputConfig>,
+    pub keybindings: Vec<KeyBinding>,
+    pub mouse_bindings: Vec<MouseBinding>,
+    pub modes: HashMap<String, Vec<KeyBinding>>,
+    pub autostart: Vec<String>,
+    pub exec_always: Vec<String>,
+    pub env: HashMap<String, String>,
 }
 
 impl WazemmesConfig {
-    pub fn get() -> eyre::Result<WazemmesConfig> {
-        let file = dirs::home_dir()
-            .expect("$HOME should be set")
-            .join(".config/wazemmes/config.ron");
+    /// Look for `wazemmes/config.ron` in `$XDG_CONFIG_HOME` then in `$XDG_CONFIG_DIRS`
+    pub fn path() -> Option<PathBuf> {
+        let config_dirs = env::var_os("XDG_CONFIG_DIRS")
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/etc/xdg".into());
 
-        let file = fs::read_to_string(file)?;
-        let config = ron::from_str(&file)?;
+        dirs::config_dir()
+            .into_iter()
+            .chain(env::split_paths(&config_dirs))
+            .map(|dir| dir.join("wazemmes/config.ron"))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the config from `path` if any, otherwise from the first config file found
+    /// in the XDG directories, falling back to the built-in default
+    pub fn get(path: Option<&Path>) -> eyre::Result<WazemmesConfig> {
+        match path.map(Path::to_path_buf).or_else(Self::path) {
+            Some(path) => Self::load(&path),
+            None => {
+                warn!("No config file found, using the default config");
+                Ok(WazemmesConfig::default())
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> eyre::Result<WazemmesConfig> {
+        let mut config: WazemmesConfig = read_ron(path)?;
+        config.sources = vec![path.to_path_buf()];
+        let includes = config.includes.clone();
+        config.include(path, &includes)?;
         Ok(config)
     }
+
+    pub fn has_mode(&self, mode: &str) -> bool {
+        mode == DEFAULT_MODE || self.modes.contains_key(mode)
+    }
+
+    /// Keybindings active in `mode`
+    pub fn bindings(&self, mode: &str) -> &[KeyBinding] {
+        match self.modes.get(mode) {
+            Some(bindings) if mode != DEFAULT_MODE => bindings,
+            _ => &self.keybindings,
+        }
+    }
+
+    fn include(&mut self, base: &Path, includes: &[PathBuf]) -> eyre::Result<()> {
+        for include in includes {
+            let path = resolve_include(base, include);
+            if self.sources.contains(&path) {
+                warn!("Config file {} is included more than once, skipping", path.display());
+                continue;
+            }
+
+            let fragment: ConfigFragment = read_ron(&path)?;
+            self.sources.push(path.clone());
+            self.outputs.extend(fragment.outputs);
+            self.inputs.extend(fragment.inputs);
+            self.keybindings.extend(fragment.keybindings);
+            self.mouse_bindings.extend(fragment.mouse_bindings);
+            for (mode, bindings) in fragment.modes {
+                self.modes.entry(mode).or_default().extend(bindings);
+            }
+            self.autostart.extend(fragment.autostart);
+            self.exec_always.extend(fragment.exec_always);
+            for (var, value) in fragment.env {
+                // Variables set by the including file take precedence
+                self.env.entry(var).or_insert(value);
+            }
+            self.include(&path, &fragment.includes)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn default_chord_timeout() -> u64 {
+    1000
+}
+
+/// Resolve an `includes` entry, `~/` is expanded and relative paths start from the including file
+pub fn resolve_include(base: &Path, include: &Path) -> PathBuf {
+    if let Ok(relative) = include.strip_prefix("~") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(relative);
+        }
+    }
+
+    match base.parent() {
+        Some(parent) if include.is_relative() => parent.join(include),
+        _ => include.to_path_buf(),
+    }
+}
+
+fn read_ron<T: DeserializeOwned>(path: &Path) -> eyre::Result<T> {
+    let source = fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read config file {}", path.display()))?;
+
+    ron::from_str(&source).map_err(|err| {
+        eyre!(
+            "{}:{}:{}: {}",
+            path.display(),
+            err.position.line,
+            err.position.col,
+            err.code
+        )
+    })
 }
 
 impl Default for WazemmesConfig {
     fn default() -> Self {
         Self {
             gaps: 14,
+            animation: AnimationConfig::default(),
+            keyboard: KeyboardConfig::default(),
+            inputs: vec![],
+            outputs: vec![],
+            chord_timeout: default_chord_timeout(),
+            mouse_bindings: default_mouse_bindings(),
+            modes: HashMap::new(),
+            autostart: vec![],
+            exec_always: vec![],
+            env: HashMap::new(),
+            log_dir: None,
+            command_palette: None,
+            includes: vec![],
+            sources: vec![],
             keybindings: vec![
                 KeyBinding {
                     modifiers: HashSet::from([Modifier::Alt]),
                     key: xkb::KEY_t,
+                    keycode: None,
+                    then: vec![],
+                    on_release: false,
                     action: Action::Run {
                         env: vec![],
                         command: "alacritty".to_string(),
+                        shell: false,
                     },
                 },
                 KeyBinding {
                     modifiers: HashSet::from([Modifier::Alt]),
                     key: xkb::KEY_g,
+                    keycode: None,
+                    then: vec![],
+                    on_release: false,
                     action: Action::Run {
                         env: vec![("WGPU_BACKEND".into(), "vulkan".into())],
                         command: "onagre".to_string(),
+                        shell: false,
                     },
                 },
             ],
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::config::output::OutputMatch;
+    use crate::config::{resolve_include, WazemmesConfig};
+    use indoc::indoc;
+    use speculoos::prelude::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn should_resolve_relative_include_from_including_file() {
+        let path = resolve_include(
+            Path::new("/etc/xdg/wazemmes/config.ron"),
+            Path::new("keys.ron"),
+        );
+
+        assert_that!(path).is_equal_to(PathBuf::from("/etc/xdg/wazemmes/keys.ron"));
+    }
+
+    #[test]
+    fn should_keep_absolute_include() {
+        let path = resolve_include(
+            Path::new("/etc/xdg/wazemmes/config.ron"),
+            Path::new("/tmp/keys.ron"),
+        );
+
+        assert_that!(path).is_equal_to(PathBuf::from("/tmp/keys.ron"));
+    }
+
+    #[test]
+    fn should_load_outputs_from_included_file() -> eyre::Result<()> {
+        let dir = std::env::temp_dir().join(format!("wazemmes-include-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let config_path = dir.join("config.ron");
+        fs::write(
+            &config_path,
+            indoc! {r#"
+                (
+                    gaps: 10,
+                    keybindings: [],
+                    includes: ["outputs.ron"],
+                )
+            "#},
+        )?;
+        fs::write(
+            dir.join("outputs.ron"),
+            indoc! {r#"
+                (
+                    outputs: [
+                        (matches: Name("DP-1"), scale: Some(1.5)),
+                    ],
+                )
+            "#},
+        )?;
+
+        let config = WazemmesConfig::load(&config_path);
+        fs::remove_dir_all(&dir)?;
+        let config = config?;
+
+        assert_that!(config.outputs).has_length(1);
+        assert_that!(config.outputs[0].matches).is_equal_to(OutputMatch::Name("DP-1".to_string()));
+        assert_that!(config.outputs[0].scale).is_equal_to(Some(1.5));
+        Ok(())
+    }
+}
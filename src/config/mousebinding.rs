@@ -0,0 +1,127 @@
+use crate::config::keybinding::{modifiers_match, Action, Modifier};
+use serde::{Deserialize, Serialize};
+use smithay::input::keyboard::ModifiersState;
+use std::collections::HashSet;
+
+/// Linux input event codes of the mouse buttons
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MouseBinding {
+    pub modifiers: HashSet<Modifier>,
+    pub trigger: MouseTrigger,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MouseTrigger {
+    Button(MouseButton),
+    Scroll(ScrollDirection),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Side,
+    Extra,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl MouseButton {
+    pub fn code(&self) -> u32 {
+        match self {
+            MouseButton::Left => BTN_LEFT,
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+            MouseButton::Side => BTN_SIDE,
+            MouseButton::Extra => BTN_EXTRA,
+        }
+    }
+}
+
+impl MouseBinding {
+    pub fn match_button(&self, modifiers: ModifiersState, button: u32) -> bool {
+        matches!(self.trigger, MouseTrigger::Button(expected) if expected.code() == button)
+            && modifiers_match(&self.modifiers, modifiers)
+    }
+
+    pub fn match_scroll(&self, modifiers: ModifiersState, direction: ScrollDirection) -> bool {
+        self.trigger == MouseTrigger::Scroll(direction)
+            && modifiers_match(&self.modifiers, modifiers)
+    }
+}
+
+/// Alt + left drag moves floating windows when no mouse binding is configured
+pub fn default_mouse_bindings() -> Vec<MouseBinding> {
+    vec![MouseBinding {
+        modifiers: HashSet::from([Modifier::Alt]),
+        trigger: MouseTrigger::Button(MouseButton::Left),
+        action: Action::DragMove,
+    }]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::keybinding::{Action, Modifier};
+    use crate::config::mousebinding::{MouseBinding, MouseButton, MouseTrigger, ScrollDirection};
+    use smithay::input::keyboard::ModifiersState;
+    use speculoos::prelude::*;
+    use std::collections::HashSet;
+
+    fn logo() -> ModifiersState {
+        ModifiersState {
+            logo: true,
+            ..ModifiersState::default()
+        }
+    }
+
+    #[test]
+    fn should_deserialize_mouse_bindings() {
+        let bindings = ron::from_str::<Vec<MouseBinding>>(
+            r#"[
+                (modifiers: [Logo], trigger: Button(Middle), action: ToggleFloating),
+                (modifiers: [Logo], trigger: Scroll(Down), action: WorkspaceNext),
+            ]"#,
+        );
+
+        assert_that!(bindings).is_ok();
+        assert_that!(bindings.unwrap()).is_equal_to(vec![
+            MouseBinding {
+                modifiers: HashSet::from([Modifier::Logo]),
+                trigger: MouseTrigger::Button(MouseButton::Middle),
+                action: Action::ToggleFloating,
+            },
+            MouseBinding {
+                modifiers: HashSet::from([Modifier::Logo]),
+                trigger: MouseTrigger::Scroll(ScrollDirection::Down),
+                action: Action::WorkspaceNext,
+            },
+        ]);
+    }
+
+    #[test]
+    fn should_match_button_with_modifiers() {
+        let binding = MouseBinding {
+            modifiers: HashSet::from([Modifier::Logo]),
+            trigger: MouseTrigger::Button(MouseButton::Right),
+            action: Action::DragResize,
+        };
+
+        assert_that!(binding.match_button(logo(), 0x111)).is_true();
+        assert_that!(binding.match_button(logo(), 0x110)).is_false();
+        assert_that!(binding.match_button(ModifiersState::default(), 0x111)).is_false();
+    }
+}
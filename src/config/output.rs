@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use smithay::output::Mode;
+use smithay::utils::Transform;
+
+/// Settings of the outputs selected by `matches`, unset options keep the output defaults
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OutputConfig {
+    pub matches: OutputMatch,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode: Option<OutputMode>,
+    /// Top left corner in the global space, in logical pixels.
+    /// Outputs without a position are placed on the right of the others
+    #[serde(default)]
+    pub position: Option<(i32, i32)>,
+    /// Integer or fractional scale, for example `1.5`
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub transform: Option<OutputTransform>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OutputMatch {
+    /// Connector name, for example `DP-1` or `eDP-1`
+    Name(String),
+    /// Monitor description read from its EDID, unset fields match anything
+    Description {
+        #[serde(default)]
+        make: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        serial: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct OutputMode {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in Hz, the highest one available is used when unset
+    #[serde(default)]
+    pub refresh: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OutputTransform {
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    Flipped,
+    Flipped90,
+    Flipped180,
+    Flipped270,
+}
+
+/// What an output is matched against
+#[derive(Debug, Clone, Copy)]
+pub struct OutputDescription<'a> {
+    pub name: &'a str,
+    pub make: &'a str,
+    pub model: &'a str,
+    pub serial: &'a str,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl OutputMatch {
+    pub fn matches(&self, output: &OutputDescription) -> bool {
+        match self {
+            OutputMatch::Name(name) => name == output.name,
+            OutputMatch::Description {
+                make,
+                model,
+                serial,
+            } => {
+                let field_matches =
+                    |expected: &Option<String>, actual: &str| match expected {
+                        Some(expected) => expected == actual,
+                        None => true,
+                    };
+
+                field_matches(make, output.make)
+                    && field_matches(model, output.model)
+                    && field_matches(serial, output.serial)
+            }
+        }
+    }
+}
+
+/// The first config matching the output
+pub fn config_for<'a>(
+    outputs: &'a [OutputConfig],
+    output: &OutputDescription,
+) -> Option<&'a OutputConfig> {
+    outputs.iter().find(|config| config.matches.matches(output))
+}
+
+impl OutputMode {
+    /// The available mode with the requested size and the closest refresh rate
+    pub fn pick(&self, modes: &[Mode]) -> Option<Mode> {
+        let candidates = modes
+            .iter()
+            .filter(|mode| mode.size.w == self.width && mode.size.h == self.height);
+
+        match self.refresh {
+            Some(refresh) => {
+                let millihertz = (refresh * 1000.0) as i32;
+                candidates.min_by_key(|mode| (mode.refresh - millihertz).abs())
+            }
+            None => candidates.max_by_key(|mode| mode.refresh),
+        }
+        .copied()
+    }
+}
+
+impl From<OutputTransform> for Transform {
+    fn from(transform: OutputTransform) -> Self {
+        match transform {
+            OutputTransform::Normal => Transform::Normal,
+            OutputTransform::Rotate90 => Transform::_90,
+            OutputTransform::Rotate180 => Transform::_180,
+            OutputTransform::Rotate270 => Transform::_270,
+            OutputTransform::Flipped => Transform::Flipped,
+            OutputTransform::Flipped90 => Transform::Flipped90,
+            OutputTransform::Flipped180 => Transform::Flipped180,
+            OutputTransform::Flipped270 => Transform::Flipped270,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::output::{config_for, OutputConfig, OutputDescription, OutputMode};
+    use smithay::output::Mode;
+    use speculoos::prelude::*;
+
+    const DELL: OutputDescription = OutputDescription {
+        name: "DP-1",
+        make: "DEL",
+        model: "DELL U2720Q",
+        serial: "ABC123",
+    };
+
+    fn mode(width: i32, height: i32, refresh: i32) -> Mode {
+        Mode {
+            size: (width, height).into(),
+            refresh,
+        }
+    }
+
+    #[test]
+    fn should_parse_output_config() {
+        let config: Vec<OutputConfig> = ron::from_str(
+            r#"[
+                (matches: Name("eDP-1"), enabled: false),
+                (
+                    matches: Description(model: Some("DELL U2720Q")),
+                    mode: Some((width: 3840, height: 2160, refresh: Some(60.0))),
+                    position: Some((1920, 0)),
+                    scale: Some(1.5),
+                    transform: Some(Rotate90),
+                ),
+            ]"#,
+        )
+        .unwrap();
+
+        assert_that!(config[0].enabled).is_false();
+        assert_that!(config[1].enabled).is_true();
+        assert_that!(config[1].scale).is_equal_to(Some(1.5));
+        assert_that!(config_for(&config, &DELL)).is_equal_to(Some(&config[1]));
+    }
+
+    #[test]
+    fn should_use_first_matching_config() {
+        let config: Vec<OutputConfig> = ron::from_str(
+            r#"[
+                (matches: Description(make: Some("DEL"), serial: Some("OTHER")), scale: Some(2.0)),
+                (matches: Name("DP-1"), scale: Some(1.0)),
+                (matches: Description(make: Some("DEL")), scale: Some(1.5)),
+            ]"#,
+        )
+        .unwrap();
+
+        let found = config_for(&config, &DELL).unwrap();
+
+        assert_that!(found.scale).is_equal_to(Some(1.0));
+    }
+
+    #[test]
+    fn should_pick_closest_refresh_rate() {
+        let modes = [
+            mode(2560, 1440, 59_951),
+            mode(2560, 1440, 143_912),
+            mode(1920, 1080, 60_000),
+        ];
+
+        let wanted = OutputMode {
+            width: 2560,
+            height: 1440,
+            refresh: Some(144.0),
+        };
+        let highest = OutputMode {
+            refresh: None,
+            ..wanted
+        };
+        let missing = OutputMode {
+            width: 800,
+            ..wanted
+        };
+
+        assert_that!(wanted.pick(&modes)).is_equal_to(Some(modes[1]));
+        assert_that!(highest.pick(&modes)).is_equal_to(Some(modes[1]));
+        assert_that!(missing.pick(&modes)).is_none();
+    }
+}
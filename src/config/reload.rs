@@ -0,0 +1,209 @@
+use crate::config::{WazemmesConfig, DEFAULT_MODE};
+use crate::ipc::protocol::Event;
+use crate::CallLoopData;
+use eyre::WrapErr;
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use slog_scope::{debug, info, warn};
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::{EventLoop, Interest, Mode, PostAction};
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+/// Watch the config files and reload them whenever one is modified, created or removed.
+/// Their parent directories are watched since editors often replace a file instead of writing it
+pub fn watch_config(
+    event_loop: &mut EventLoop<CallLoopData>,
+    data: &CallLoopData,
+) -> eyre::Result<()> {
+    let inotify = Inotify::init().wrap_err("Failed to initialize inotify")?;
+    let mut watcher = ConfigWatcher {
+        inotify,
+        files: vec![],
+    };
+    watcher.watch(&data.watched_config_files());
+
+    event_loop
+        .handle()
+        .insert_source(
+            Generic::new(watcher, Interest::READ, Mode::Level),
+            |_, watcher, data| {
+                if watcher.changed()? {
+                    debug!("Config files changed");
+                    data.reload_config();
+                    // Includes might have changed
+                    watcher.watch(&data.watched_config_files());
+                }
+
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|err| eyre::eyre!("Failed to insert the config watcher: {}", err))?;
+
+    Ok(())
+}
+
+struct ConfigWatcher {
+    inotify: Inotify,
+    /// Watched files, as the watch of their directory and their name in it
+    files: Vec<(WatchDescriptor, OsString)>,
+}
+
+impl ConfigWatcher {
+    fn watch(&mut self, files: &[PathBuf]) {
+        for (watch, _) in self.files.drain(..) {
+            // The watch is shared by files of the same directory and might be gone already
+            self.inotify.rm_watch(watch).ok();
+        }
+
+        let mask = WatchMask::CLOSE_WRITE
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_TO
+            | WatchMask::MOVED_FROM;
+
+        for file in files {
+            let name = match file.file_name() {
+                Some(name) => name.to_os_string(),
+                None => continue,
+            };
+
+            let dir = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+
+            match self.inotify.add_watch(dir, mask) {
+                Ok(watch) => self.files.push((watch, name)),
+                Err(err) => warn!("Failed to watch {}: {}", dir.display(), err),
+            }
+        }
+    }
+
+    /// Read the pending events, returns true if one of them is about a config file
+    fn changed(&mut self) -> io::Result<bool> {
+        let mut buffer = [0; 4096];
+        let mut changed = false;
+
+        loop {
+            let events = match self.inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(changed),
+                Err(err) => return Err(err),
+            };
+
+            for event in events {
+                changed |= self.files.iter().any(|(watch, name)| {
+                    *watch == event.wd && event.name == Some(name.as_os_str())
+                });
+            }
+        }
+    }
+}
+
+impl AsRawFd for ConfigWatcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+}
+
+impl CallLoopData {
+    /// Files read by the current config, or the file that would be read when running
+    /// on the default config
+    fn watched_config_files(&self) -> Vec<PathBuf> {
+        if self.config.sources.is_empty() {
+            self.config_path
+                .clone()
+                .or_else(WazemmesConfig::path)
+                .into_iter()
+                .collect()
+        } else {
+            self.config.sources.clone()
+        }
+    }
+
+    /// Load the config file again and apply it to the running compositor,
+    /// the current config is kept if the file cannot be read or parsed
+    pub fn reload_config(&mut self) {
+        let path = match self.config_path.clone().or_else(WazemmesConfig::path) {
+            Some(path) => path,
+            None => {
+                warn!("No config file found, keeping the current config");
+                return;
+            }
+        };
+
+        let config = match WazemmesConfig::load(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("Failed to reload config, keeping the current one: {}", err);
+                return;
+            }
+        };
+
+        info!("Reloading config");
+        let gaps_changed = config.gaps != self.config.gaps;
+        let keyboard_changed = config.keyboard != self.config.keyboard;
+        let inputs_changed = config.inputs != self.config.inputs;
+        let outputs_changed = config.outputs != self.config.outputs;
+
+        // Keybindings and animations are read from `self.config` on use
+        self.config = config;
+
+        if outputs_changed {
+            self.arrange_outputs();
+        }
+
+        if gaps_changed {
+            self.apply_gaps();
+        }
+
+        if keyboard_changed {
+            self.apply_keyboard();
+        }
+
+        if inputs_changed {
+            self.configure_input_devices();
+        }
+
+        self.run_exec_always();
+
+        if !self.config.has_mode(&self.state.binding_mode) {
+            warn!(
+                "Binding mode '{}' was removed, back to the default mode",
+                self.state.binding_mode
+            );
+            self.state.binding_mode = DEFAULT_MODE.to_string();
+            self.state.ipc_event(Event::Mode {
+                mode: DEFAULT_MODE.to_string(),
+            });
+        }
+
+        self.state.ipc_event(Event::ConfigReloaded);
+    }
+
+    fn apply_keyboard(&mut self) {
+        let keyboard = self.state.seat.get_keyboard().unwrap();
+        let config = &self.config.keyboard;
+        keyboard.change_repeat_info(config.repeat_rate, config.repeat_delay);
+
+        let layout = self.state.keyboard_layout.min(config.layout_count() - 1);
+        self.set_keyboard_layout(layout);
+    }
+
+    fn apply_gaps(&mut self) {
+        let gaps = self.config.gaps;
+
+        for (num, workspace) in self.state.workspaces.iter() {
+            let mut workspace = workspace.get_mut();
+            workspace.set_gaps(&self.state.space, gaps);
+            // Workspaces of a disabled output are laid out when it is enabled again
+            if self.state.space.output_geometry(&workspace.output).is_some() {
+                workspace.update_layout(&self.state.space);
+                workspace.update_borders();
+            }
+            workspace.needs_redraw = *num == self.state.current_workspace;
+        }
+    }
+}
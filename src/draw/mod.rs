@@ -1 +1,2 @@
 pub(crate) mod pointer;
+pub(crate) mod thumbnail;
@@ -0,0 +1,88 @@
+use smithay::backend::renderer::gles2::{Gles2Error, Gles2Frame, Gles2Renderer, Gles2Texture};
+use smithay::backend::renderer::{Frame, Texture};
+use smithay::desktop::space::{RenderElement, SpaceOutputTuple};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Scale, Transform};
+
+/// An offscreen rendered texture drawn scaled down into `geometry`
+#[derive(Clone, Debug)]
+pub struct ThumbnailElement {
+    id: usize,
+    texture: Gles2Texture,
+    geometry: Rectangle<i32, Logical>,
+    z_index: u8,
+}
+
+impl ThumbnailElement {
+    pub fn new(
+        workspace: u8,
+        texture: Gles2Texture,
+        geometry: Rectangle<i32, Logical>,
+        z_index: u8,
+    ) -> Self {
+        Self {
+            // Keep clear of the ids used by the pointer and border elements
+            id: usize::MAX - workspace as usize,
+            texture,
+            geometry,
+            z_index,
+        }
+    }
+}
+
+impl RenderElement<Gles2Renderer> for ThumbnailElement {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn location(&self, scale: impl Into<Scale<f64>>) -> Point<f64, Physical> {
+        self.geometry.loc.to_f64().to_physical(scale)
+    }
+
+    fn geometry(&self, scale: impl Into<Scale<f64>>) -> Rectangle<i32, Physical> {
+        self.geometry.to_physical_precise_round(scale)
+    }
+
+    // Thumbnails are re-rendered on every frame
+    fn accumulated_damage(
+        &self,
+        scale: impl Into<Scale<f64>>,
+        _: Option<SpaceOutputTuple<'_, '_>>,
+    ) -> Vec<Rectangle<i32, Physical>> {
+        vec![self.geometry.to_physical_precise_round(scale)]
+    }
+
+    fn opaque_regions(
+        &self,
+        _scale: impl Into<Scale<f64>>,
+    ) -> Option<Vec<Rectangle<i32, Physical>>> {
+        None
+    }
+
+    fn draw(
+        &self,
+        _renderer: &mut Gles2Renderer,
+        frame: &mut Gles2Frame,
+        scale: impl Into<Scale<f64>>,
+        location: Point<f64, Physical>,
+        _damage: &[Rectangle<i32, Physical>],
+        _log: &slog::Logger,
+    ) -> Result<(), Gles2Error> {
+        let size = self.geometry.size.to_physical_precise_round(scale);
+        let dst = Rectangle::from_loc_and_size(location.to_i32_round(), size);
+        let src = Rectangle::from_loc_and_size((0.0, 0.0), self.texture.size().to_f64());
+
+        // Offscreen framebuffers are stored bottom-up
+        frame.render_texture_from_to(
+            &self.texture,
+            src,
+            dst,
+            &[Rectangle::from_loc_and_size((0, 0), size)],
+            Transform::Flipped180,
+            1.0,
+        )
+    }
+
+    fn z_index(&self) -> u8 {
+        self.z_index
+    }
+}
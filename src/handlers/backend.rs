@@ -22,14 +22,16 @@ impl BackendHandler for CallLoopData {
         ::std::env::set_var("WAYLAND_DISPLAY", &self.state.socket_name);
 
         if let Some(output) = self.state.space.outputs().next() {
-            self.state
-                .workspaces
-                .insert(0, WorkspaceRef::new(output.clone(), &self.state.space));
+            let workspace = WorkspaceRef::new(output.clone(), &self.state.space, self.config.gaps);
+            self.state.workspaces.insert(0, workspace);
         } else {
             panic!("Failed to create Workspace 0 on default Output");
         }
 
         dbg!(&self.state.socket_name);
+
+        // Deferred to the first loop iteration, once XWayland exported its display
+        self.state._loop_handle.insert_idle(|data| data.run_autostart());
     }
 
     fn close_compositor(&mut self) {
@@ -1,22 +1,18 @@
 use crate::shell::windows::toplevel::WindowState;
 use crate::Wazemmes;
 use smithay::backend::renderer::utils::on_commit_buffer_handler;
-use smithay::desktop::{
-    layer_map_for_output, Kind as SurfaceKind, PopupKind, PopupManager, Space, WindowSurfaceType,
-};
+use smithay::desktop::{Kind as SurfaceKind, PopupKind, PopupManager, Space, WindowSurfaceType};
 
 use crate::backend::xwayland;
 use crate::shell::workspace::Workspace;
 use slog_scope::debug;
 use smithay::reexports::wayland_server::protocol::wl_buffer;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::reexports::wayland_server::DisplayHandle;
 use smithay::utils::{Logical, Point, Rectangle, Serial, Size};
 use smithay::wayland::buffer::BufferHandler;
 use smithay::wayland::compositor::{
     with_states, with_surface_tree_upward, CompositorHandler, CompositorState, TraversalAction,
 };
-use smithay::wayland::shell::wlr_layer::LayerSurfaceAttributes;
 use smithay::wayland::shell::xdg::{
     XdgPopupSurfaceRoleAttributes, XdgToplevelSurfaceRoleAttributes,
 };
@@ -88,6 +84,7 @@ impl CompositorHandler for Wazemmes {
             if let Some(x11) = self.x11_state.as_mut() {
                 xwayland::commit_hook(surface, &self.display, x11, ws);
             }
+            self.report_new_x11_windows();
         }
 
         self.space.commit(surface);
@@ -95,18 +92,14 @@ impl CompositorHandler for Wazemmes {
         let workspace = self.get_current_workspace();
         let workspace = workspace.get_mut();
 
-        ensure_initial_configure(
-            &self.display,
-            surface,
-            &mut self.space,
-            &mut self.popups,
-            workspace,
-        );
+        ensure_initial_configure(surface, &mut self.space, &mut self.popups, workspace);
+        self.layer_commit(surface);
+
+        self.ipc_check_title(surface);
     }
 }
 
 fn ensure_initial_configure(
-    dh: &DisplayHandle,
     surface: &WlSurface,
     space: &mut Space,
     popups: &mut PopupManager,
@@ -210,35 +203,6 @@ fn ensure_initial_configure(
             // allowed.
             popup.send_configure().expect("initial configure failed");
         }
-
-        return;
-    };
-
-    if let Some(output) = space.outputs().find(|o| {
-        let map = layer_map_for_output(o);
-        map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
-            .is_some()
-    }) {
-        let mut map = layer_map_for_output(output);
-        let layer = map
-            .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
-            .unwrap();
-
-        // send the initial configure if relevant
-        let initial_configure_sent = with_states(surface, |states| {
-            states
-                .data_map
-                .get::<Mutex<LayerSurfaceAttributes>>()
-                .unwrap()
-                .lock()
-                .unwrap()
-                .initial_configure_sent
-        });
-        if !initial_configure_sent {
-            layer.layer_surface().send_configure();
-        }
-
-        map.arrange(dh);
     };
 }
 
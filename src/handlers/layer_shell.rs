@@ -0,0 +1,197 @@
+use crate::Wazemmes;
+use slog_scope::{debug, warn};
+use smithay::delegate_layer_shell;
+use smithay::desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType};
+use smithay::output::Output;
+use smithay::reexports::wayland_server::protocol::wl_output::WlOutput;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::SERIAL_COUNTER;
+use smithay::wayland::compositor::with_states;
+use smithay::wayland::shell::wlr_layer::{
+    KeyboardInteractivity, Layer, LayerSurface as WlrLayerSurface, LayerSurfaceAttributes,
+    WlrLayerShellHandler, WlrLayerShellState,
+};
+use std::sync::Mutex;
+
+impl WlrLayerShellHandler for Wazemmes {
+    fn shell_state(&mut self) -> &mut WlrLayerShellState {
+        &mut self.layer_shell_state
+    }
+
+    fn new_layer_surface(
+        &mut self,
+        surface: WlrLayerSurface,
+        wl_output: Option<WlOutput>,
+        _layer: Layer,
+        namespace: String,
+    ) {
+        // Surfaces without an output go to the one showing the current workspace
+        let output = wl_output
+            .as_ref()
+            .and_then(Output::from_resource)
+            .unwrap_or_else(|| self.get_current_workspace().get().output.clone());
+
+        debug!("New layer surface '{}' on {}", namespace, output.name());
+        let mut map = layer_map_for_output(&output);
+        if let Err(err) = map.map_layer(&self.display, &LayerSurface::new(surface, namespace)) {
+            warn!("Failed to map layer surface: {}", err);
+        }
+    }
+
+    fn layer_destroyed(&mut self, surface: WlrLayerSurface) {
+        let destroyed = self.space.outputs().find_map(|output| {
+            let map = layer_map_for_output(output);
+            map.layers()
+                .find(|layer| layer.layer_surface() == &surface)
+                .map(|layer| (output.clone(), layer.clone()))
+        });
+
+        if let Some((output, layer)) = destroyed {
+            layer_map_for_output(&output).unmap_layer(&layer);
+            self.arrange_layers(&output);
+        }
+
+        self.refresh_layer_focus();
+    }
+}
+
+delegate_layer_shell!(Wazemmes);
+
+impl Wazemmes {
+    /// Send the initial configure of a layer surface and arrange its output again
+    pub fn layer_commit(&mut self, surface: &WlSurface) {
+        let output = self
+            .space
+            .outputs()
+            .find(|output| {
+                let map = layer_map_for_output(output);
+                map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                    .is_some()
+            })
+            .cloned();
+
+        let output = match output {
+            Some(output) => output,
+            None => return,
+        };
+
+        let initial_configure_sent = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<Mutex<LayerSurfaceAttributes>>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .initial_configure_sent
+        });
+
+        if !initial_configure_sent {
+            let map = layer_map_for_output(&output);
+            if let Some(layer) = map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL) {
+                layer.layer_surface().send_configure();
+            }
+        }
+
+        self.arrange_layers(&output);
+        self.refresh_layer_focus();
+    }
+
+    /// Arrange the layer surfaces of `output`, its workspaces are tiled again
+    /// when the exclusive zones changed
+    pub fn arrange_layers(&mut self, output: &Output) {
+        let changed = {
+            let mut map = layer_map_for_output(output);
+            let zone = map.non_exclusive_zone();
+            map.arrange(&self.display);
+            map.non_exclusive_zone() != zone
+        };
+
+        if !changed || self.space.output_geometry(output).is_none() {
+            return;
+        }
+
+        debug!("Exclusive zones of {} changed", output.name());
+        for workspace in self.workspaces.values() {
+            let mut workspace = workspace.get_mut();
+            if workspace.output == *output {
+                workspace.update_layout(&self.space);
+                workspace.update_borders();
+                workspace.needs_redraw = true;
+            }
+        }
+    }
+
+    /// Give the keyboard to a top or overlay layer surface asking for exclusive focus,
+    /// or back to the focused window once the layer surface holding it lets go
+    pub fn refresh_layer_focus(&mut self) {
+        if self.session_lock.is_locked() {
+            return;
+        }
+
+        let keyboard = self.seat.get_keyboard().expect("Should have a keyboard seat");
+        let focus = keyboard.current_focus();
+
+        if let Some(surface) = self.exclusive_layer() {
+            if focus.as_ref() != Some(&surface) {
+                debug!("Layer surface takes exclusive keyboard focus");
+                self.focus_layer(surface);
+            }
+
+            return;
+        }
+
+        let released = match &self.layer_focus {
+            Some(surface) => !self.layer_accepts_focus(surface),
+            None => false,
+        };
+
+        if !released {
+            return;
+        }
+
+        let layer_focus = self.layer_focus.take();
+        if focus.is_some() && focus != layer_focus {
+            return;
+        }
+
+        let window = self.get_current_workspace().get().get_focus().1;
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard.set_focus(self, window.map(|window| window.wl_surface()), serial);
+    }
+
+    /// Focus a layer surface clicked by the user if its keyboard interactivity allows it
+    pub fn focus_layer_on_demand(&mut self, layer: &LayerSurface) {
+        if layer.can_receive_keyboard_focus() {
+            self.focus_layer(layer.wl_surface().clone());
+        }
+    }
+
+    fn focus_layer(&mut self, surface: WlSurface) {
+        let keyboard = self.seat.get_keyboard().expect("Should have a keyboard seat");
+        keyboard.set_focus(self, Some(surface.clone()), SERIAL_COUNTER.next_serial());
+        self.layer_focus = Some(surface);
+    }
+
+    fn exclusive_layer(&self) -> Option<WlSurface> {
+        self.space.outputs().find_map(|output| {
+            let map = layer_map_for_output(output);
+            let surface = [Layer::Overlay, Layer::Top].into_iter().find_map(|layer| {
+                map.layers_on(layer)
+                    .find(|surface| {
+                        surface.cached_state().keyboard_interactivity
+                            == KeyboardInteractivity::Exclusive
+                    })
+                    .map(|surface| surface.wl_surface().clone())
+            });
+            surface
+        })
+    }
+
+    fn layer_accepts_focus(&self, surface: &WlSurface) -> bool {
+        self.space.outputs().any(|output| {
+            let map = layer_map_for_output(output);
+            map.layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                .map_or(false, LayerSurface::can_receive_keyboard_focus)
+        })
+    }
+}
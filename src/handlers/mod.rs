@@ -1,4 +1,5 @@
 mod compositor;
+mod layer_shell;
 mod xdg_decoration;
 mod xdg_shell;
 
@@ -6,6 +7,7 @@ pub mod backend;
 pub mod dmabuf;
 pub mod output;
 pub mod screencopy;
+pub mod session_lock;
 
 use crate::Wazemmes;
 
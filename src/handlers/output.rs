@@ -1,51 +1,92 @@
+use crate::backend::drawing::OVERLAY_Z_INDEX;
 use crate::backend::{NewOutputDescriptor, OutputHandler, OutputId};
 use crate::border::QuadElement;
 use crate::draw::pointer::PointerElement;
-use crate::shell::drawable::Borders;
-use crate::state::output::OutputState;
+use crate::draw::thumbnail::ThumbnailElement;
+use crate::shell::drawable::{Borders, Quad};
+use crate::shell::workspace::WorkspaceRef;
+use crate::config::output::{config_for, OutputDescription};
+use crate::ipc::protocol::{Event, OutputChange};
+use crate::state::output::{OutputInfo, OutputState};
 use crate::{BackendState, CallLoopData, Wazemmes};
-use slog_scope::debug;
+use slog_scope::{debug, info, warn};
 use smithay::backend::renderer::gles2::{Gles2Renderer, Gles2Texture};
+use smithay::backend::renderer::utils::draw_surface_tree;
+use smithay::backend::renderer::{Frame, Renderer};
+use smithay::backend::SwapBuffersError;
+use smithay::desktop::layer_map_for_output;
 use smithay::desktop::space::SurfaceTree;
-use smithay::utils::{Physical, Rectangle, Transform};
-use smithay::output::{Mode, Output};
+use smithay::desktop::Space;
+use smithay::desktop::utils::send_frames_surface_tree;
+use smithay::output::{Mode, Output, Scale};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Size, Transform};
+use std::collections::HashMap;
 
 smithay::custom_elements! {
     pub CustomElem<=Gles2Renderer>;
     Quad=QuadElement,
     SurfaceTree=SurfaceTree,
     PointerElement=PointerElement,
+    Thumbnail=ThumbnailElement,
 }
 
 impl OutputHandler for CallLoopData {
     fn output_created(&mut self, desc: NewOutputDescriptor) {
         let output = Output::new(desc.name.clone(), desc.physical_properties, None);
+        for mode in &desc.possible_modes {
+            output.add_mode(*mode);
+        }
         output.set_preferred(desc.prefered_mode);
 
         output.user_data().insert_if_missing(|| desc.id);
 
-        output.create_global::<Wazemmes>(&self.display.handle());
+        let global = output.create_global::<Wazemmes>(&self.display.handle());
+        output.user_data().insert_if_missing(|| OutputInfo {
+            serial: desc.serial,
+            default_transform: desc.transform,
+            global,
+        });
 
         output.change_current_state(Some(desc.prefered_mode), Some(desc.transform), None, None);
 
-        let outputs: Vec<_> = self
-            .state
-            .space
-            .outputs()
-            .cloned()
-            .chain(std::iter::once(output))
-            .collect();
-
-        let mut x = 0;
-
-        // Put unconfigured outputs on the end
-        for output in outputs.into_iter().rev() {
-            let location = (x, 0).into();
-            self.state.space.map_output(&output, location);
-            output.change_current_state(None, None, None, Some(location));
+        self.state.ipc_event(Event::Output {
+            change: OutputChange::Added,
+            name: output.name(),
+        });
+        self.state.outputs.push(output);
+        self.arrange_outputs();
+    }
 
-            x += output.current_mode().unwrap().size.w;
+    fn output_removed(&mut self, output_id: &OutputId) {
+        let position = self
+            .state
+            .outputs
+            .iter()
+            .position(|o| o.user_data().get::<OutputId>() == Some(output_id));
+
+        let output = match position {
+            Some(position) => self.state.outputs.remove(position),
+            None => return,
+        };
+
+        info!("Output {} disconnected", output.name());
+        self.state.space.unmap_output(&output);
+        self.display
+            .handle()
+            .remove_global::<Wazemmes>(OutputInfo::for_output(&output).global.clone());
+
+        if matches!(&self.state.overview, Some(overview) if overview.output == output) {
+            self.state.overview = None;
         }
+
+        self.migrate_workspaces(&output);
+        self.arrange_outputs();
+        // A locker waiting for every output to show the lock should not wait for this one
+        self.state.session_lock.output_locked(&output);
+        self.state.ipc_event(Event::Output {
+            change: OutputChange::Removed,
+            name: output.name(),
+        });
     }
 
     fn output_mode_updated(&mut self, output_id: &OutputId, mode: Mode) {
@@ -60,13 +101,26 @@ impl OutputHandler for CallLoopData {
         }
     }
 
+    fn output_prepare(&mut self, renderer: &mut Gles2Renderer, output_id: &OutputId) {
+        let overview = match &mut self.state.overview {
+            Some(overview) if overview.output.user_data().get::<OutputId>() == Some(output_id) => {
+                overview
+            }
+            _ => return,
+        };
+
+        if let Some(geometry) = self.state.space.output_geometry(&overview.output) {
+            overview.render_thumbnails(renderer, &self.state.workspaces, geometry);
+        }
+    }
+
     fn output_render(
         &mut self,
         renderer: &mut Gles2Renderer,
         output_id: &OutputId,
         age: usize,
         pointer_image: Option<&Gles2Texture>,
-    ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, smithay::backend::SwapBuffersError> {
+    ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, SwapBuffersError> {
         let mut elems: Vec<CustomElem> = Vec::new();
 
         let location = self
@@ -92,8 +146,19 @@ impl OutputHandler for CallLoopData {
             .space
             .outputs()
             .find(|o| o.user_data().get::<OutputId>() == Some(output_id))
-            .unwrap()
-            .clone();
+            .cloned();
+
+        let output = match output {
+            Some(output) => output,
+            None => {
+                Self::blank(renderer)?;
+                return Ok(None);
+            }
+        };
+
+        if self.state.session_lock.is_locked() {
+            return self.render_lock(renderer, &output);
+        }
 
         let ws = self.state.get_current_workspace();
         let mut ws = ws.get_mut();
@@ -109,19 +174,66 @@ impl OutputHandler for CallLoopData {
         if ws.needs_redraw {
             debug!("Redraw Workspace({})", self.state.current_workspace);
 
-            ws.redraw(&mut self.state.space, self.state.x11_state.as_mut());
+            ws.redraw(
+                &mut self.state.space,
+                self.state.x11_state.as_mut(),
+                &self.config.animation,
+            );
 
             ws.update_borders();
         }
 
+        if ws.animate(&mut self.state.space, self.state.x11_state.as_mut()) {
+            ws.update_borders();
+        }
+
         let output_geometry = ws.get_output_geometry_f64(&self.state.space);
+        drop(ws);
+
+        // Borders are not drawn while workspaces slide
+        let sliding = self.state.update_workspace_transition();
 
-        if let Some(geometry) = output_geometry {
-            self.draw_border(ws.borders.as_slice(), renderer, &mut elems, geometry);
+        if let (Some(geometry), false) = (output_geometry, sliding) {
+            let ws = self.state.get_current_workspace();
+            let borders = ws.get().borders.clone();
+            self.draw_border(borders.as_slice(), renderer, &mut elems, geometry);
+        }
+
+        if let (Some(geometry), Some(switcher)) = (output_geometry, &self.state.window_switcher) {
+            let output_logical_geometry = self.state.space.output_geometry(&output).unwrap();
+            let quads = switcher.overlay(output_logical_geometry);
+            self.draw_quads(quads.as_slice(), renderer, &mut elems, geometry);
+        }
+
+        let overview = self
+            .state
+            .overview
+            .as_ref()
+            .filter(|overview| overview.output == output);
+
+        if let (Some(geometry), Some(overview)) = (output_geometry, overview) {
+            let output_logical_geometry = self.state.space.output_geometry(&output).unwrap();
+            let quads = overview.overlay(
+                &self.state.workspaces,
+                output_logical_geometry,
+                location.to_f64(),
+            );
+            self.draw_quads(quads.as_slice(), renderer, &mut elems, geometry);
+
+            let thumbnails = overview
+                .thumbnails(output_logical_geometry)
+                .into_iter()
+                .map(|(num, texture, geometry)| {
+                    ThumbnailElement::new(num, texture, geometry, OVERLAY_Z_INDEX + 2)
+                })
+                .map(CustomElem::from);
+            elems.extend(thumbnails);
         }
 
         let output_state = OutputState::for_output(&output);
+        let age = output_state.buffer_age(age);
 
+        // Layer surfaces are drawn with the windows, ordered by the z-indices in `drawing`
         let render_result = self
             .state
             .space
@@ -158,16 +270,131 @@ impl OutputHandler for CallLoopData {
 
         for output in self.state.space.outputs() {
             if output.user_data().get::<OutputId>() == Some(output_id) {
-                let map = smithay::desktop::layer_map_for_output(output);
+                let map = layer_map_for_output(output);
                 for layer in map.layers() {
                     layer.send_frame(time);
                 }
+
+                if let Some(surface) = self.state.session_lock.surface_for(output) {
+                    send_frames_surface_tree(surface, time);
+                }
             }
         }
     }
 }
 
 impl CallLoopData {
+    /// Apply the `outputs` config to every known output and place them in the space
+    pub fn arrange_outputs(&mut self) {
+        let outputs = self.state.outputs.clone();
+        let mut placed = vec![];
+        let mut disabled = vec![];
+
+        for output in &outputs {
+            let info = OutputInfo::for_output(output);
+            let properties = output.physical_properties();
+            let description = OutputDescription {
+                name: &output.name(),
+                make: &properties.make,
+                model: &properties.model,
+                serial: &info.serial,
+            };
+            let config = config_for(&self.config.outputs, &description);
+
+            if !config.map_or(true, |config| config.enabled) {
+                debug!("Output {} is disabled", output.name());
+                self.state.space.unmap_output(output);
+                disabled.push(output.clone());
+                continue;
+            }
+
+            let mode = config.and_then(|config| config.mode).and_then(|wanted| {
+                let mode = wanted.pick(&output.modes());
+                if mode.is_none() {
+                    warn!("Output {} has no {:?} mode", output.name(), wanted);
+                }
+                mode
+            });
+            // Removing a mode from the config goes back to the preferred one
+            let mode = mode
+                .or_else(|| output.preferred_mode())
+                .or_else(|| output.current_mode());
+
+            let transform = config
+                .and_then(|config| config.transform)
+                .map(Transform::from)
+                .unwrap_or(info.default_transform);
+
+            let scale = match config.and_then(|config| config.scale) {
+                Some(scale) if scale.fract() == 0.0 => Scale::Integer(scale as i32),
+                Some(scale) => Scale::Fractional(scale),
+                None => Scale::Integer(1),
+            };
+
+            if let (Some(mode), Some(id)) = (mode, output.user_data().get::<OutputId>()) {
+                if output.current_mode() != Some(mode) {
+                    self.state.backend.update_mode(id, &mode);
+                }
+            }
+
+            output.change_current_state(mode, Some(transform), Some(scale), None);
+            placed.push((output.clone(), config.and_then(|config| config.position)));
+        }
+
+        // Outputs without a position go on the right of the positioned ones
+        let mut x = 0;
+        for (output, position) in &placed {
+            if let (Some(position), Some(size)) = (position, logical_size(output)) {
+                x = x.max(position.0 + size.w);
+            }
+        }
+
+        for (output, position) in placed {
+            let location: Point<i32, Logical> = match position {
+                Some(position) => position.into(),
+                None => {
+                    let location = (x, 0).into();
+                    x += logical_size(&output).map_or(0, |size| size.w);
+                    location
+                }
+            };
+
+            self.state.space.map_output(&output, location);
+            output.change_current_state(None, None, None, Some(location));
+        }
+
+        for output in &disabled {
+            self.migrate_workspaces(output);
+        }
+
+        for (output, _) in &placed {
+            self.restore_workspaces(output);
+            layer_map_for_output(output).arrange(&self.state.display);
+        }
+
+        for workspace in self.state.workspaces.values() {
+            let mut workspace = workspace.get_mut();
+            if self.state.space.output_geometry(&workspace.output).is_some() {
+                workspace.update_layout(&self.state.space);
+                workspace.update_borders();
+                workspace.needs_redraw = true;
+            }
+        }
+    }
+
+    /// Move the workspaces of a disconnected output to the first remaining one
+    fn migrate_workspaces(&mut self, removed: &Output) {
+        let state = &self.state;
+        migrate_workspaces(&state.space, &state.workspaces, state.current_workspace, removed);
+    }
+
+    /// Bring back the workspaces which were on `output` before it was disconnected,
+    /// and give a home to the workspaces left without any output
+    fn restore_workspaces(&mut self, output: &Output) {
+        let state = &self.state;
+        restore_workspaces(&state.space, &state.workspaces, state.current_workspace, output);
+    }
+
     // QuadElement needs to be flipped when running via udev
     fn transform_custom_element(&self) -> Transform {
         match self.state.backend {
@@ -195,4 +422,306 @@ impl CallLoopData {
                 .expect("Failed to render borders")
         }
     }
+
+    /// Draw the lock surface of the output and nothing else, the space is bypassed so
+    /// neither windows nor layer surfaces are rendered. The output stays black until
+    /// the locker creates a surface for it
+    fn render_lock(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        output: &Output,
+    ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, SwapBuffersError> {
+        let mode = match output.current_mode() {
+            Some(mode) => mode,
+            None => {
+                Self::blank(renderer)?;
+                return Ok(None);
+            }
+        };
+
+        let transform = output.current_transform();
+        let scale = output.current_scale().fractional_scale();
+        let damage = vec![Rectangle::from_loc_and_size(
+            (0, 0),
+            transform.transform_size(mode.size),
+        )];
+        let surface = self.state.session_lock.surface_for(output).cloned();
+
+        renderer
+            .render(mode.size, transform, |renderer, frame| {
+                frame.clear([0.0, 0.0, 0.0, 1.0], &damage)?;
+                match &surface {
+                    Some(surface) => draw_surface_tree(
+                        renderer,
+                        frame,
+                        surface,
+                        scale,
+                        Point::from((0.0, 0.0)),
+                        &damage,
+                        &slog_scope::logger(),
+                    ),
+                    None => Ok(()),
+                }
+            })
+            .and_then(|result| result)
+            .map_err(SwapBuffersError::from)?;
+
+        // The space did not draw this frame, its damage history is of no use anymore
+        let output_state = OutputState::for_output(output);
+        output_state.reset_damage();
+        output_state.fps_tick();
+        self.state.session_lock.output_locked(output);
+
+        Ok(Some(damage))
+    }
+
+    /// Disabled outputs keep receiving frames so they can be enabled again, they are cleared
+    fn blank(renderer: &mut Gles2Renderer) -> Result<(), SwapBuffersError> {
+        renderer
+            .render(
+                (i32::MAX, i32::MAX).into(),
+                Transform::Normal,
+                |_, frame| {
+                    let size = Size::from((i32::MAX, i32::MAX));
+                    frame.clear([0.0, 0.0, 0.0, 1.0], &[Rectangle::from_loc_and_size((0, 0), size)])
+                },
+            )
+            .and_then(|result| result)
+            .map_err(SwapBuffersError::from)
+    }
+
+    fn draw_quads(
+        &self,
+        quads: &[Quad],
+        renderer: &mut Gles2Renderer,
+        elems: &mut Vec<CustomElem>,
+        geometry: Rectangle<f64, Physical>,
+    ) {
+        let transform = self.transform_custom_element();
+        renderer
+            .with_context(|_renderer, gles| {
+                let quads = quads
+                    .iter()
+                    .map(|quad| QuadElement::quad(gles, geometry, quad, transform))
+                    .map(CustomElem::from);
+                elems.extend(quads);
+            })
+            .expect("Failed to render overlay")
+    }
+}
+
+/// Size of the output in the global space, once scaled and transformed
+fn logical_size(output: &Output) -> Option<Size<i32, Logical>> {
+    let mode = output.current_mode()?;
+    let size = output.current_transform().transform_size(mode.size);
+    Some(size.to_f64().to_logical(output.current_scale().fractional_scale()).to_i32_round())
+}
+
+fn migrate_workspaces(
+    space: &Space,
+    workspaces: &HashMap<u8, WorkspaceRef>,
+    current_workspace: u8,
+    removed: &Output,
+) {
+    let fallback = space.outputs().next().cloned();
+
+    for (num, workspace) in workspaces.iter() {
+        let mut workspace = workspace.get_mut();
+        if workspace.output != *removed {
+            continue;
+        }
+
+        if workspace.home_output.is_none() {
+            workspace.home_output = Some(removed.name());
+        }
+
+        match &fallback {
+            Some(output) => {
+                debug!("Moving workspace {} to output {}", num, output.name());
+                workspace.set_output(output, space);
+                workspace.needs_redraw = *num == current_workspace;
+            }
+            None => warn!("No output left for workspace {}", num),
+        }
+    }
+}
+
+fn restore_workspaces(
+    space: &Space,
+    workspaces: &HashMap<u8, WorkspaceRef>,
+    current_workspace: u8,
+    output: &Output,
+) {
+    if space.output_geometry(output).is_none() {
+        return;
+    }
+
+    for (num, workspace) in workspaces.iter() {
+        let mut workspace = workspace.get_mut();
+        let is_home = workspace.home_output.as_deref() == Some(output.name().as_str());
+        let orphan = space.output_geometry(&workspace.output).is_none();
+
+        if !is_home && !orphan {
+            continue;
+        }
+
+        if is_home {
+            workspace.home_output = None;
+        }
+
+        debug!("Restoring workspace {} to output {}", num, output.name());
+        workspace.set_output(output, space);
+        workspace.needs_redraw = *num == current_workspace;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::handlers::output::{migrate_workspaces, restore_workspaces};
+    use crate::shell::workspace::WorkspaceRef;
+    use smithay::desktop::Space;
+    use smithay::output::{Mode, Output, PhysicalProperties, Subpixel};
+    use speculoos::prelude::*;
+    use std::collections::HashMap;
+
+    const GAPS: u32 = 10;
+
+    fn output(name: &str) -> Output {
+        let output = Output::new(
+            name.to_string(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "wazemmes".to_string(),
+                model: "test".to_string(),
+            },
+            None,
+        );
+
+        let mode = Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        };
+        output.add_mode(mode);
+        output.set_preferred(mode);
+        output.change_current_state(Some(mode), None, None, None);
+        output
+    }
+
+    fn output_name(workspaces: &HashMap<u8, WorkspaceRef>, num: u8) -> String {
+        workspaces[&num].get().output.name()
+    }
+
+    // Workspace 0 on `DP-1` and workspace 1 on `DP-2`, placed side by side
+    fn setup() -> (Space, HashMap<u8, WorkspaceRef>, Output, Output) {
+        let mut space = Space::new(slog_scope::logger());
+        let first = output("DP-1");
+        let second = output("DP-2");
+        space.map_output(&first, (0, 0));
+        space.map_output(&second, (1920, 0));
+
+        let workspaces = HashMap::from([
+            (0, WorkspaceRef::new(first.clone(), &space, GAPS)),
+            (1, WorkspaceRef::new(second.clone(), &space, GAPS)),
+        ]);
+
+        (space, workspaces, first, second)
+    }
+
+    #[test]
+    fn should_move_workspaces_of_unplugged_output() {
+        let (mut space, workspaces, _, second) = setup();
+
+        space.unmap_output(&second);
+        migrate_workspaces(&space, &workspaces, 0, &second);
+
+        assert_that!(output_name(&workspaces, 1)).is_equal_to("DP-1".to_string());
+        assert_that!(workspaces[&1].get().home_output)
+            .is_equal_to(Some("DP-2".to_string()));
+        assert_that!(workspaces[&0].get().home_output).is_none();
+    }
+
+    #[test]
+    fn should_restore_workspaces_on_replugged_output() {
+        let (mut space, workspaces, _, second) = setup();
+        space.unmap_output(&second);
+        migrate_workspaces(&space, &workspaces, 0, &second);
+
+        let second = output("DP-2");
+        space.map_output(&second, (1920, 0));
+        restore_workspaces(&space, &workspaces, 0, &second);
+
+        assert_that!(output_name(&workspaces, 0)).is_equal_to("DP-1".to_string());
+        assert_that!(output_name(&workspaces, 1)).is_equal_to("DP-2".to_string());
+        assert_that!(workspaces[&1].get().home_output).is_none();
+    }
+
+    #[test]
+    fn should_keep_home_output_across_several_unplugs() {
+        let (mut space, mut workspaces, first, second) = setup();
+        space.unmap_output(&second);
+        migrate_workspaces(&space, &workspaces, 0, &second);
+
+        // The fallback output is unplugged too, nothing is left to move to
+        space.unmap_output(&first);
+        migrate_workspaces(&space, &workspaces, 0, &first);
+
+        // Workspaces are still used while no output is mapped
+        workspaces.insert(2, WorkspaceRef::new(first.clone(), &space, GAPS));
+        for workspace in workspaces.values() {
+            workspace.get_mut().update_layout(&space);
+        }
+
+        assert_that!(workspaces[&0].get().home_output)
+            .is_equal_to(Some("DP-1".to_string()));
+        assert_that!(workspaces[&1].get().home_output)
+            .is_equal_to(Some("DP-2".to_string()));
+
+        let second = output("DP-2");
+        space.map_output(&second, (0, 0));
+        restore_workspaces(&space, &workspaces, 0, &second);
+
+        // Workspace 0 is adopted while its home output is away
+        assert_that!(output_name(&workspaces, 0)).is_equal_to("DP-2".to_string());
+        assert_that!(output_name(&workspaces, 2)).is_equal_to("DP-2".to_string());
+        assert_that!(output_name(&workspaces, 1)).is_equal_to("DP-2".to_string());
+        assert_that!(workspaces[&0].get().home_output)
+            .is_equal_to(Some("DP-1".to_string()));
+        assert_that!(workspaces[&1].get().home_output).is_none();
+
+        let first = output("DP-1");
+        space.map_output(&first, (1920, 0));
+        restore_workspaces(&space, &workspaces, 0, &first);
+
+        assert_that!(output_name(&workspaces, 0)).is_equal_to("DP-1".to_string());
+        assert_that!(workspaces[&0].get().home_output).is_none();
+    }
+
+    #[test]
+    fn should_adopt_orphan_workspaces() {
+        let (mut space, workspaces, first, second) = setup();
+        space.unmap_output(&first);
+        space.unmap_output(&second);
+
+        let third = output("HDMI-1");
+        space.map_output(&third, (0, 0));
+        restore_workspaces(&space, &workspaces, 0, &third);
+
+        assert_that!(output_name(&workspaces, 0)).is_equal_to("HDMI-1".to_string());
+        assert_that!(output_name(&workspaces, 1)).is_equal_to("HDMI-1".to_string());
+    }
+
+    #[test]
+    fn should_not_restore_workspaces_on_disabled_output() {
+        let (mut space, workspaces, _, second) = setup();
+        space.unmap_output(&second);
+        migrate_workspaces(&space, &workspaces, 0, &second);
+
+        // A disabled output stays out of the space
+        restore_workspaces(&space, &workspaces, 0, &second);
+
+        assert_that!(output_name(&workspaces, 1)).is_equal_to("DP-1".to_string());
+        assert_that!(workspaces[&1].get().home_output)
+            .is_equal_to(Some("DP-2".to_string()));
+    }
 }
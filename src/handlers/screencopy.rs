@@ -57,6 +57,11 @@ impl Dispatch<ZwlrScreencopyManagerV1, ()> for Wazemmes {
                     .unwrap();
 
                 let frame = data_init.init(frame, ());
+                if state.session_lock.is_locked() {
+                    debug!("Screen capture refused while the session is locked");
+                    frame.failed();
+                    return;
+                }
 
                 let drm = match &state.backend {
                     BackendState::Drm(drm) => drm,
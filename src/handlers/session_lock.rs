@@ -0,0 +1,331 @@
+//! ext-session-lock-v1, a locker client such as swaylock hides the session until it unlocks it.
+//!
+//! Only lock surfaces are shown while the session is locked and they get every input.
+//! The session stays locked when the locker dies, a new locker can then take over.
+
+use crate::Wazemmes;
+use slog_scope::{debug, info, warn};
+use smithay::output::Output;
+use smithay::reexports::wayland_server::backend::{ClientId, GlobalId, ObjectId};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use smithay::utils::SERIAL_COUNTER;
+use smithay::wayland::compositor;
+use wayland_protocols::ext::session_lock::v1::server::ext_session_lock_manager_v1::{
+    self, ExtSessionLockManagerV1,
+};
+use wayland_protocols::ext::session_lock::v1::server::ext_session_lock_surface_v1::{
+    self, ExtSessionLockSurfaceV1,
+};
+use wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::{
+    self, ExtSessionLockV1,
+};
+
+const LOCK_SURFACE_ROLE: &str = "ext_session_lock_surface_v1";
+
+pub struct SessionLockManagerState {
+    global: GlobalId,
+}
+
+impl SessionLockManagerState {
+    pub(crate) fn new(dh: &DisplayHandle) -> Self {
+        SessionLockManagerState {
+            global: dh.create_global::<Wazemmes, ExtSessionLockManagerV1, ()>(1, ()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionLock {
+    Unlocked,
+    Locked {
+        /// Lock of the client holding the session, `None` once it died without unlocking
+        locker: Option<ExtSessionLockV1>,
+        surfaces: Vec<LockSurface>,
+        /// Outputs which did not draw a locked frame yet
+        pending_outputs: Vec<Output>,
+        /// Whether the locker was told the session is locked
+        confirmed: bool,
+    },
+}
+
+/// A surface shown on `output` while the session is locked
+#[derive(Debug)]
+pub struct LockSurface {
+    pub surface: WlSurface,
+    pub output: Output,
+    resource: ExtSessionLockSurfaceV1,
+}
+
+impl Default for SessionLock {
+    fn default() -> Self {
+        SessionLock::Unlocked
+    }
+}
+
+impl SessionLock {
+    pub fn is_locked(&self) -> bool {
+        matches!(self, SessionLock::Locked { .. })
+    }
+
+    /// The lock surface shown on `output`, if the locker created one
+    pub fn surface_for(&self, output: &Output) -> Option<&WlSurface> {
+        match self {
+            SessionLock::Locked { surfaces, .. } => surfaces
+                .iter()
+                .find(|lock_surface| lock_surface.output == *output)
+                .map(|lock_surface| &lock_surface.surface),
+            SessionLock::Unlocked => None,
+        }
+    }
+
+    /// Record that `output` only shows the lock, the locker is told the session
+    /// is locked once every output did
+    pub fn output_locked(&mut self, output: &Output) {
+        if let SessionLock::Locked {
+            pending_outputs, ..
+        } = self
+        {
+            pending_outputs.retain(|pending| pending != output);
+        }
+
+        self.confirm_if_drawn();
+    }
+
+    fn confirm_if_drawn(&mut self) {
+        if let SessionLock::Locked {
+            locker: Some(locker),
+            pending_outputs,
+            confirmed,
+            ..
+        } = self
+        {
+            if pending_outputs.is_empty() && !*confirmed {
+                debug!("Every output shows the lock");
+                *confirmed = true;
+                locker.locked();
+            }
+        }
+    }
+
+    fn is_locker(&self, lock: &ExtSessionLockV1) -> bool {
+        matches!(self, SessionLock::Locked { locker: Some(locker), .. } if locker == lock)
+    }
+}
+
+impl GlobalDispatch<ExtSessionLockManagerV1, ()> for Wazemmes {
+    fn bind(
+        _state: &mut Wazemmes,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtSessionLockManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Wazemmes>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ExtSessionLockManagerV1, ()> for Wazemmes {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ExtSessionLockManagerV1,
+        request: ext_session_lock_manager_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            ext_session_lock_manager_v1::Request::Lock { id } => {
+                let lock = data_init.init(id, ());
+                state.lock_session(lock);
+            }
+            ext_session_lock_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ExtSessionLockV1, ()> for Wazemmes {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ExtSessionLockV1,
+        request: ext_session_lock_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            ext_session_lock_v1::Request::GetLockSurface {
+                id,
+                surface,
+                output,
+            } => {
+                let lock_surface = data_init.init(id, ());
+                let output = match Output::from_resource(&output) {
+                    Some(output) => output,
+                    None => return,
+                };
+
+                // A lock the compositor refused gets no surface
+                if !state.session_lock.is_locker(resource) {
+                    return;
+                }
+
+                if state.session_lock.surface_for(&output).is_some() {
+                    resource.post_error(
+                        ext_session_lock_v1::Error::DuplicateOutput,
+                        "Output already has a lock surface",
+                    );
+                    return;
+                }
+
+                if compositor::give_role(&surface, LOCK_SURFACE_ROLE).is_err() {
+                    resource.post_error(
+                        ext_session_lock_v1::Error::Role,
+                        "Surface already has a role",
+                    );
+                    return;
+                }
+
+                let size = state
+                    .space
+                    .output_geometry(&output)
+                    .map(|geometry| geometry.size)
+                    .unwrap_or_default();
+
+                debug!("New lock surface on {}", output.name());
+                let serial = SERIAL_COUNTER.next_serial();
+                lock_surface.configure(serial.into(), size.w as u32, size.h as u32);
+
+                if let SessionLock::Locked { surfaces, .. } = &mut state.session_lock {
+                    surfaces.push(LockSurface {
+                        surface,
+                        output,
+                        resource: lock_surface,
+                    });
+                }
+
+                state.refresh_lock_focus();
+            }
+            ext_session_lock_v1::Request::UnlockAndDestroy => {
+                if state.session_lock.is_locker(resource) {
+                    state.unlock_session();
+                }
+            }
+            ext_session_lock_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: ObjectId, _data: &()) {
+        if let SessionLock::Locked { locker, .. } = &mut state.session_lock {
+            if locker.as_ref().map(Resource::id) == Some(resource) {
+                warn!("Session locker is gone, the session stays locked");
+                *locker = None;
+            }
+        }
+
+        state.refresh_lock_focus();
+    }
+}
+
+impl Dispatch<ExtSessionLockSurfaceV1, ()> for Wazemmes {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &ExtSessionLockSurfaceV1,
+        request: ext_session_lock_surface_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            ext_session_lock_surface_v1::Request::AckConfigure { .. } => {}
+            ext_session_lock_surface_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: ObjectId, _data: &()) {
+        if let SessionLock::Locked { surfaces, .. } = &mut state.session_lock {
+            surfaces.retain(|lock_surface| lock_surface.resource.id() != resource);
+        }
+
+        state.refresh_lock_focus();
+    }
+}
+
+impl Wazemmes {
+    /// Lock the session, a new locker replaces one that died while holding the lock
+    fn lock_session(&mut self, lock: ExtSessionLockV1) {
+        match &mut self.session_lock {
+            SessionLock::Locked {
+                locker: Some(_), ..
+            } => {
+                warn!("Session is already locked, refusing the new lock");
+                lock.finished();
+                return;
+            }
+            SessionLock::Locked {
+                locker, confirmed, ..
+            } => {
+                info!("New session locker connected");
+                *locker = Some(lock);
+                *confirmed = false;
+            }
+            SessionLock::Unlocked => {
+                info!("Session locked");
+                self.session_lock = SessionLock::Locked {
+                    locker: Some(lock),
+                    surfaces: vec![],
+                    pending_outputs: self.space.outputs().cloned().collect(),
+                    confirmed: false,
+                };
+
+                self.window_switcher = None;
+                self.overview = None;
+                let keyboard = self.seat.get_keyboard().expect("Should have a keyboard seat");
+                keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+            }
+        }
+
+        // Confirmed once every output drew a locked frame, right away for a locker taking over
+        self.session_lock.confirm_if_drawn();
+    }
+
+    fn unlock_session(&mut self) {
+        info!("Session unlocked");
+        self.session_lock = SessionLock::Unlocked;
+
+        let window = self.get_current_workspace().get().get_focus().1;
+        let keyboard = self.seat.get_keyboard().expect("Should have a keyboard seat");
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard.set_focus(self, window.map(|window| window.wl_surface()), serial);
+        self.refresh_layer_focus();
+    }
+
+    /// Keep the keyboard on the lock surface of the current output, or any lock surface.
+    /// Also called before every key press in case something moved the focus meanwhile
+    pub fn refresh_lock_focus(&mut self) {
+        let output = self.get_current_workspace().get().output.clone();
+        let surface = match &self.session_lock {
+            SessionLock::Locked { surfaces, .. } => self
+                .session_lock
+                .surface_for(&output)
+                .or_else(|| surfaces.first().map(|lock_surface| &lock_surface.surface))
+                .cloned(),
+            SessionLock::Unlocked => return,
+        };
+
+        // Without a lock surface nothing gets the keyboard
+        let keyboard = self.seat.get_keyboard().expect("Should have a keyboard seat");
+        if keyboard.current_focus() != surface {
+            keyboard.set_focus(self, surface, SERIAL_COUNTER.next_serial());
+        }
+    }
+}
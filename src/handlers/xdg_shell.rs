@@ -14,6 +14,8 @@ use smithay::wayland::shell::xdg::{
     Configure, PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
 };
 
+use crate::ipc::protocol::WindowChange;
+use crate::shell::windows::toplevel::WindowWrap;
 use crate::Wazemmes;
 use smithay::utils::SERIAL_COUNTER;
 
@@ -26,6 +28,9 @@ impl XdgShellHandler for Wazemmes {
         let workspace = self.get_current_workspace();
         let mut workspace = workspace.get_mut();
         debug!("New toplevel window");
+        if let Some(launcher) = self.window_launcher(surface.wl_surface()) {
+            debug!("Window launched by '{}' ({})", launcher.command, launcher.pid);
+        }
         {
             let container = if let Some(layout) = self.next_layout {
                 self.next_layout = None;
@@ -34,10 +39,11 @@ impl XdgShellHandler for Wazemmes {
                 workspace.get_focus().0
             };
 
-            {
+            let window: Option<WindowWrap> = {
                 let mut container = container.get_mut();
-                container.push_toplevel(surface.clone());
-            }
+                let id = container.push_toplevel(surface.clone());
+                container.nodes.get(&id).and_then(|node| node.try_into().ok())
+            };
 
             // Grab keyboard focus
             let handle = self
@@ -46,8 +52,15 @@ impl XdgShellHandler for Wazemmes {
                 .expect("Should have a keyboard seat");
 
             let serial = SERIAL_COUNTER.next_serial();
-            handle.set_focus(self, Some(surface.wl_surface().clone()), serial);
+            if !self.session_lock.is_locked() {
+                handle.set_focus(self, Some(surface.wl_surface().clone()), serial);
+            }
             workspace.needs_redraw = true;
+            drop(workspace);
+
+            if let Some(window) = window {
+                self.ipc_window_event(&window, WindowChange::New);
+            }
         }
     }
 
@@ -0,0 +1,244 @@
+use crate::config::keybinding::{Action, KeyBinding, KeyStroke, PressedKey, SequenceMatch};
+use crate::ipc::protocol::Event;
+use crate::Wazemmes;
+use slog_scope::{debug, warn};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use std::time::{Duration, Instant};
+use xkbcommon::xkb;
+use xkbcommon::xkb::Keysym;
+
+/// The first keys of a chord binding, waiting for the next stroke
+#[derive(Debug, Clone)]
+pub struct PendingChord {
+    pub strokes: Vec<PressedKey>,
+    last_stroke: Instant,
+}
+
+/// What to do with a key stroke once matched against the bindings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordResult {
+    /// A binding matched every stroke
+    Action(Action),
+    /// A binding matched every stroke, its action runs once the key is released
+    OnRelease(Action),
+    /// Strokes are the start of at least one binding
+    Pending(Vec<PressedKey>),
+    /// Strokes did not match any binding, a pending chord was cancelled when `cancelled` is set
+    NoMatch { cancelled: bool },
+}
+
+impl PendingChord {
+    pub fn new(strokes: Vec<PressedKey>) -> Self {
+        Self {
+            strokes,
+            last_stroke: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self, timeout: Duration) -> bool {
+        self.last_stroke.elapsed() > timeout
+    }
+
+    /// Modifier keys are part of the next stroke, they neither advance nor cancel a chord
+    pub fn is_modifier_key(key: Keysym) -> bool {
+        matches!(
+            key,
+            xkb::KEY_Shift_L..=xkb::KEY_Hyper_R | xkb::KEY_ISO_Level3_Shift
+        )
+    }
+
+    /// The keys pressed so far, for example `Alt+w`
+    pub fn describe(&self) -> String {
+        self.strokes
+            .iter()
+            .map(|pressed| KeyStroke::from_pressed(pressed).to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl Wazemmes {
+    /// Wait `timeout` for the next stroke of `pending`, the chord is dropped afterward
+    pub fn start_chord(&mut self, pending: PendingChord, timeout: Duration) {
+        self.cancel_chord_timer();
+
+        let timer = Timer::from_duration(timeout);
+        let token = self._loop_handle.insert_source(timer, |_, _, data| {
+            debug!("Key chord timed out");
+            data.state.pending_chord = None;
+            data.state.chord_timer = None;
+            data.state.ipc_event(Event::Chord { pending: None });
+            TimeoutAction::Drop
+        });
+
+        match token {
+            Ok(token) => self.chord_timer = Some(token),
+            Err(err) => warn!("Failed to insert the key chord timer: {}", err),
+        }
+
+        self.ipc_event(Event::Chord {
+            pending: Some(pending.describe()),
+        });
+        self.pending_chord = Some(pending);
+    }
+
+    pub fn cancel_chord_timer(&mut self) {
+        if let Some(token) = self.chord_timer.take() {
+            self._loop_handle.remove(token);
+        }
+    }
+}
+
+/// Match the pending strokes followed by a new one, a full match takes precedence over longer chords
+pub fn match_chord(
+    bindings: &[KeyBinding],
+    pending: Option<PendingChord>,
+    pressed: PressedKey,
+) -> ChordResult {
+    let cancelled = pending.is_some();
+    let mut strokes = pending.map(|pending| pending.strokes).unwrap_or_default();
+    strokes.push(pressed);
+
+    let mut prefix = false;
+    for binding in bindings {
+        match binding.match_sequence(&strokes) {
+            SequenceMatch::Full if binding.on_release => {
+                return ChordResult::OnRelease(binding.action.clone())
+            }
+            SequenceMatch::Full => return ChordResult::Action(binding.action.clone()),
+            SequenceMatch::Prefix => prefix = true,
+            SequenceMatch::None => {}
+        }
+    }
+
+    if prefix {
+        ChordResult::Pending(strokes)
+    } else {
+        ChordResult::NoMatch { cancelled }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::keybinding::{Action, KeyBinding, KeyStroke, Modifier, PressedKey};
+    use crate::inputs::chord::{match_chord, ChordResult, PendingChord};
+    use smithay::input::keyboard::ModifiersState;
+    use speculoos::prelude::*;
+    use std::collections::HashSet;
+    use xkbcommon::xkb;
+    use xkbcommon::xkb::Keysym;
+
+    fn alt() -> ModifiersState {
+        ModifiersState {
+            alt: true,
+            ..ModifiersState::default()
+        }
+    }
+
+    fn press(modifiers: ModifiersState, keysym: Keysym, keycode: u32) -> PressedKey {
+        PressedKey {
+            modifiers,
+            keysym,
+            keycode,
+        }
+    }
+
+    fn bindings() -> Vec<KeyBinding> {
+        vec![
+            KeyBinding {
+                modifiers: HashSet::from([Modifier::Alt]),
+                key: xkb::KEY_w,
+                keycode: None,
+                then: vec![KeyStroke {
+                    modifiers: HashSet::new(),
+                    key: xkb::KEY_1,
+                    keycode: None,
+                }],
+                on_release: false,
+                action: Action::MoveToWorkspace(1),
+            },
+            KeyBinding {
+                modifiers: HashSet::from([Modifier::Alt]),
+                key: xkb::KEY_a,
+                keycode: None,
+                then: vec![],
+                on_release: false,
+                action: Action::Close,
+            },
+            KeyBinding {
+                modifiers: HashSet::from([Modifier::Alt]),
+                key: xkb::KEY_NoSymbol,
+                keycode: Some(16),
+                then: vec![],
+                on_release: true,
+                action: Action::Quit,
+            },
+        ]
+    }
+
+    #[test]
+    fn should_match_single_stroke_binding() {
+        let result = match_chord(&bindings(), None, press(alt(), xkb::KEY_a, 30));
+
+        assert_that!(result).is_equal_to(ChordResult::Action(Action::Close));
+    }
+
+    #[test]
+    fn should_ignore_locked_modifiers() {
+        let modifiers = ModifiersState {
+            num_lock: true,
+            caps_lock: true,
+            ..alt()
+        };
+
+        let result = match_chord(&bindings(), None, press(modifiers, xkb::KEY_A, 30));
+
+        assert_that!(result).is_equal_to(ChordResult::Action(Action::Close));
+    }
+
+    #[test]
+    fn should_match_keycode_with_any_layout() {
+        // Keycode 16 is `q` on qwerty and `a` on azerty
+        let result = match_chord(&bindings(), None, press(alt(), xkb::KEY_a, 16));
+
+        assert_that!(result).is_equal_to(ChordResult::OnRelease(Action::Quit));
+    }
+
+    #[test]
+    fn should_wait_for_the_next_stroke() {
+        let result = match_chord(&bindings(), None, press(alt(), xkb::KEY_w, 17));
+
+        assert_that!(result).is_equal_to(ChordResult::Pending(vec![press(alt(), xkb::KEY_w, 17)]));
+    }
+
+    #[test]
+    fn should_complete_chord() {
+        let pending = PendingChord::new(vec![press(alt(), xkb::KEY_w, 17)]);
+        let result = match_chord(
+            &bindings(),
+            Some(pending),
+            press(ModifiersState::default(), xkb::KEY_1, 2),
+        );
+
+        assert_that!(result).is_equal_to(ChordResult::Action(Action::MoveToWorkspace(1)));
+    }
+
+    #[test]
+    fn should_cancel_chord_on_unbound_stroke() {
+        let pending = PendingChord::new(vec![press(alt(), xkb::KEY_w, 17)]);
+        let result = match_chord(
+            &bindings(),
+            Some(pending),
+            press(ModifiersState::default(), xkb::KEY_2, 3),
+        );
+
+        assert_that!(result).is_equal_to(ChordResult::NoMatch { cancelled: true });
+    }
+
+    #[test]
+    fn should_describe_pending_chord() {
+        let pending = PendingChord::new(vec![press(alt(), xkb::KEY_w, 17)]);
+
+        assert_that!(pending.describe()).is_equal_to("Alt+w".to_string());
+    }
+}
@@ -7,7 +7,7 @@ use smithay::input::pointer::{
     AxisFrame, ButtonEvent, GrabStartData, MotionEvent, PointerGrab, PointerInnerHandle,
 };
 use smithay::input::SeatHandler;
-use smithay::utils::{Logical, Point};
+use smithay::utils::{Logical, Point, Size};
 
 pub struct MoveSurfaceGrab {
     pub start_data: GrabStartData<Wazemmes>,
@@ -63,3 +63,68 @@ impl PointerGrab<Wazemmes> for MoveSurfaceGrab {
         &self.start_data
     }
 }
+
+/// Smallest size a floating window can be resized to with the pointer
+const MIN_SIZE: i32 = 50;
+
+pub struct ResizeSurfaceGrab {
+    pub start_data: GrabStartData<Wazemmes>,
+    pub window: Window,
+    pub initial_window_size: Size<i32, Logical>,
+}
+
+impl PointerGrab<Wazemmes> for ResizeSurfaceGrab {
+    fn motion(
+        &mut self,
+        data: &mut Wazemmes,
+        handle: &mut PointerInnerHandle<'_, Wazemmes>,
+        focus: Option<(<Wazemmes as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // While the grab is active, no client has pointer focus
+        handle.motion(data, focus, event);
+
+        let delta: Point<i32, Logical> = (event.location - self.start_data.location).to_i32_round();
+        let size: Size<i32, Logical> = (
+            (self.initial_window_size.w + delta.x).max(MIN_SIZE),
+            (self.initial_window_size.h + delta.y).max(MIN_SIZE),
+        )
+            .into();
+
+        let window = WindowWrap::from(self.window.clone());
+        // Floating windows are laid out with their initial size
+        window.get_state().set_initial_geometry(size);
+        window.update_loc_and_size(Some(size), window.loc());
+        window.map(&mut data.space, data.x11_state.as_mut(), true);
+        let ws = data.get_current_workspace();
+        let mut ws = ws.get_mut();
+        ws.update_borders();
+        ws.needs_redraw = true;
+    }
+
+    fn button(
+        &mut self,
+        data: &mut Wazemmes,
+        handle: &mut PointerInnerHandle<'_, Wazemmes>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            // No more buttons are pressed, release the grab.
+            handle.unset_grab(data, event.serial, event.time);
+        }
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut Wazemmes,
+        handle: &mut PointerInnerHandle<'_, Wazemmes>,
+        details: AxisFrame,
+    ) {
+        handle.axis(data, details)
+    }
+
+    fn start_data(&self) -> &GrabStartData<Wazemmes> {
+        &self.start_data
+    }
+}
@@ -1,66 +1,215 @@
-use crate::inputs::grabs::MoveSurfaceGrab;
+use crate::config::command;
+use crate::config::keybinding::Action;
+use crate::config::keyboard::LayoutSwitch;
+use crate::config::mousebinding::ScrollDirection;
+use crate::inputs::grabs::{MoveSurfaceGrab, ResizeSurfaceGrab};
+use crate::ipc;
+use crate::ipc::protocol::{Event as IpcEvent, WindowChange};
+use crate::process::{SpawnError, SpawnOptions};
 use crate::shell::container::{ContainerLayout, ContainerState};
 use crate::shell::node::Node;
 use crate::state::CallLoopData;
 
+use crate::shell::overview::Overview;
+use crate::shell::switcher::{SwitcherEntry, WindowSwitcher};
 use crate::shell::windows::toplevel::{WindowState, WindowWrap};
-use slog_scope::{debug, warn};
+use slog_scope::{debug, info, warn};
 use smithay::backend::input::{
     Axis, AxisSource, ButtonState, Event, InputBackend, MouseButton, PointerAxisEvent,
     PointerButtonEvent,
 };
 use smithay::desktop::{Kind, Window};
 use smithay::input::pointer::{AxisFrame, ButtonEvent, Focus};
-use smithay::nix::libc;
-use smithay::utils::{Logical, Point, Serial, SERIAL_COUNTER};
-use std::io;
-use std::os::unix::process::CommandExt;
-use std::process::{Command, Stdio};
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::{Interest, Mode, PostAction};
+use smithay::utils::{IsAlive, Logical, Point, Serial, SERIAL_COUNTER};
+use std::io::{self, Read};
+
+/// Scroll amount of a mouse wheel step
+const SCROLL_STEP: f64 = 15.0;
 
 impl CallLoopData {
-    pub fn run_command(cmd: String, env: impl IntoIterator<Item = (String, String)>) {
-        let cmd = cmd.split_whitespace().collect::<Vec<&str>>();
-        let mut command = Command::new(&cmd[0]);
-        if cmd.len() > 1 {
-            command.args(&cmd[1..]);
+    /// Variables describing the session, set for every spawned program
+    pub fn session_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![
+            (
+                "WAYLAND_DISPLAY".to_string(),
+                self.state.socket_name.to_string_lossy().to_string(),
+            ),
+            ("XDG_CURRENT_DESKTOP".to_string(), "wazemmes".to_string()),
+        ];
+
+        if let Some(server) = &self.ipc {
+            env.push((
+                ipc::SOCKET_VAR.to_string(),
+                server.path.to_string_lossy().to_string(),
+            ));
+
+            // Lets i3 and sway tools such as waybar modules or swaymsg find the compositor
+            if let Some(path) = &server.i3_path {
+                let path = path.to_string_lossy().to_string();
+                env.push(("SWAYSOCK".to_string(), path.clone()));
+                env.push(("I3SOCK".to_string(), path));
+            }
         }
 
-        for (var, value) in env {
-            command.env(var, value);
+        // XWayland exports its display when started
+        #[cfg(feature = "xwayland")]
+        if let Ok(display) = std::env::var("DISPLAY") {
+            env.push(("DISPLAY".to_string(), display));
         }
 
-        command.stdin(Stdio::null());
-        command.stdout(Stdio::null());
-        command.stderr(Stdio::null());
+        env
+    }
 
-        // Setup double-fork to avoid zombies.
-        unsafe {
-            command.pre_exec(|| {
-                match libc::fork() {
-                    -1 => return Err(io::Error::last_os_error()),
-                    0 => (),
-                    _ => libc::_exit(0),
-                }
+    /// Spawn a command with the session and config environment, errors are logged
+    pub fn run_command(
+        &mut self,
+        command: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+        shell: bool,
+    ) -> Result<u32, SpawnError> {
+        self.spawn(command, env, shell, false)
+    }
 
-                if libc::setsid() == -1 {
-                    return Err(io::Error::last_os_error());
+    fn spawn(
+        &mut self,
+        command: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+        shell: bool,
+        capture_stdout: bool,
+    ) -> Result<u32, SpawnError> {
+        // Later entries win: session, then `env` from the config, then the action
+        let config_env = self
+            .config
+            .env
+            .iter()
+            .map(|(var, value)| (var.clone(), value.clone()));
+        let env = self
+            .session_env()
+            .into_iter()
+            .chain(config_env)
+            .chain(env)
+            .collect::<Vec<_>>();
+
+        let options = SpawnOptions {
+            shell,
+            log_dir: self.config.log_dir.as_deref(),
+            capture_stdout,
+        };
+
+        let result = self.state.children.spawn(command, env, options);
+        if let Err(err) = &result {
+            warn!("{}", err);
+        }
+
+        result
+    }
+
+    /// Run `autostart` then `exec_always` commands, called once the compositor is started.
+    /// They go through `sh -c` like `exec` commands, so pipes and redirections work
+    pub fn run_autostart(&mut self) {
+        let commands = self
+            .config
+            .autostart
+            .iter()
+            .chain(&self.config.exec_always)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for command in commands {
+            info!("Autostart: {}", command);
+            self.run_command(&command, vec![], true).ok();
+        }
+    }
+
+    /// Run `exec_always` commands again after a config reload
+    pub fn run_exec_always(&mut self) {
+        for command in self.config.exec_always.clone() {
+            info!("Exec always: {}", command);
+            self.run_command(&command, vec![], true).ok();
+        }
+    }
+
+    /// Run the actions of a text command, nothing runs if it does not parse
+    pub fn run_text_command(&mut self, command: &str) {
+        match command::parse(command) {
+            Ok(actions) => {
+                for action in actions {
+                    self.run_action(action.into(), None);
                 }
+            }
+            Err(err) => warn!("Invalid command '{}': {}", command, err),
+        }
+    }
 
-                Ok(())
-            });
+    /// Start `command_palette` and run the commands it prints once it exits
+    pub fn open_command_palette(&mut self) {
+        let palette = match self.config.command_palette.clone() {
+            Some(palette) => palette,
+            None => {
+                warn!("CommandPalette needs `command_palette` to be set in the config");
+                return;
+            }
+        };
+
+        let stdout = match self.spawn(&palette, vec![], true, true) {
+            Ok(pid) => self.state.children.take_stdout(pid),
+            Err(_) => return,
+        };
+
+        let mut output = vec![];
+        let inserted = stdout.map(|stdout| {
+            self.state._loop_handle.insert_source(
+                Generic::new(stdout, Interest::READ, Mode::Level),
+                move |_, stdout, data| {
+                    let mut buffer = [0; 4096];
+                    match stdout.read(&mut buffer) {
+                        Ok(0) => {
+                            let command = String::from_utf8_lossy(&output).trim().to_string();
+                            if !command.is_empty() {
+                                data.run_text_command(&command);
+                            }
+                            Ok(PostAction::Remove)
+                        }
+                        Ok(len) => {
+                            output.extend_from_slice(&buffer[..len]);
+                            Ok(PostAction::Continue)
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                            Ok(PostAction::Continue)
+                        }
+                        Err(err) => {
+                            warn!("Failed to read the command palette output: {}", err);
+                            Ok(PostAction::Remove)
+                        }
+                    }
+                },
+            )
+        });
+
+        if let Some(Err(err)) = inserted {
+            warn!("Failed to watch the command palette: {}", err);
         }
+    }
 
-        command.spawn().unwrap().wait().unwrap();
+    pub fn focus_parent(&mut self) {
+        let ws = self.state.get_current_workspace();
+        let mut ws = ws.get_mut();
+        if ws.focus_parent() {
+            ws.update_borders();
+        }
     }
 
     pub fn close(&mut self) {
-        let state = {
+        let (state, closed) = {
             let container = self.state.get_current_workspace().get_mut().get_focus().0;
 
             let mut container = container.get_mut();
             debug!("Closing window in container: {}", container.id);
+            let closed = container.get_focused_window().map(|window| window.id());
             container.close_window(self.state.x11_state.as_mut());
-            container.state()
+            (container.state(), closed)
         };
 
         match state {
@@ -69,56 +218,49 @@ impl CallLoopData {
                 let ws = self.state.get_current_workspace();
                 let mut ws = ws.get_mut();
                 ws.pop_container();
-                if let Some(window) = ws.get_focus().1 {
-                    self.toggle_window_focus(SERIAL_COUNTER.next_serial(), window.inner());
-                }
             }
             ContainerState::HasContainersOnly => {
                 debug!("Draining window from container");
-                {
-                    let container = {
-                        let ws = self.state.get_current_workspace();
-                        let ws = &ws.get_mut();
-                        ws.get_focus().0
-                    };
-
-                    let children: Option<Vec<(u32, Node)>> = {
-                        let mut container = container.get_mut();
-                        if container.parent.is_some() {
-                            Some(container.nodes.drain_all())
-                        } else {
-                            None
-                        }
-                    };
+                let container = {
+                    let ws = self.state.get_current_workspace();
+                    let ws = &ws.get_mut();
+                    ws.get_focus().0
+                };
 
+                let children: Option<Vec<(u32, Node)>> = {
                     let mut container = container.get_mut();
-                    let id = container.id;
-
-                    if let (Some(parent), Some(children)) = (&mut container.parent, children) {
-                        let mut parent = parent.get_mut();
-                        parent.nodes.remove(&id);
-                        parent.nodes.extend(children);
+                    if container.parent.is_some() {
+                        Some(container.nodes.drain_all())
+                    } else {
+                        None
                     }
-                }
+                };
 
-                let ws = self.state.get_current_workspace();
-                let ws = ws.get();
+                let (id, parent) = {
+                    let container = container.get();
+                    (container.id, container.parent.clone())
+                };
 
-                if let Some(window) = ws.get_focus().1 {
-                    self.toggle_window_focus(SERIAL_COUNTER.next_serial(), window.inner());
+                if let (Some(parent), Some(children)) = (parent, children) {
+                    parent.get_mut().nodes.remove(&id);
+                    parent.adopt(children);
+
+                    let ws = self.state.get_current_workspace();
+                    ws.get_mut().set_container_focused(&parent);
                 }
             }
             ContainerState::HasWindows => {
-                let ws = self.state.get_current_workspace();
-                let ws = ws.get();
-
-                if let Some(window) = ws.get_focus().1 {
-                    self.toggle_window_focus(SERIAL_COUNTER.next_serial(), window.inner());
-                }
                 debug!("Cannot remove non empty container");
             }
         };
 
+        if let Some(id) = closed {
+            self.state.focus_history.remove(id);
+        }
+
+        // Give the focus back to the most recently focused window
+        self.focus_previous_window();
+
         // Reset focus
         let workspace = self.state.get_current_workspace();
         let mut workspace = workspace.get_mut();
@@ -140,6 +282,265 @@ impl CallLoopData {
         workspace.update_layout(&self.state.space);
     }
 
+    fn focus_previous_window(&mut self) {
+        let ws = self.state.get_current_workspace();
+        let root = ws.get().root();
+
+        let previous = self.state.focus_history.iter().find_map(|id| {
+            let container = root.container_having_window(*id)?;
+            let window: Option<WindowWrap> = container
+                .get()
+                .nodes
+                .get(id)
+                .and_then(|node| node.try_into().ok());
+
+            window
+                .filter(|window| window.inner().alive())
+                .map(|window| (container.clone(), window))
+        });
+
+        if let Some((container, window)) = previous {
+            debug!("Focusing previously focused window({})", window.id());
+            ws.get_mut()
+                .set_container_and_window_focus(&container, &window);
+        }
+    }
+
+    /// Give the keyboard focus to a window of the current workspace
+    fn focus_window(&mut self, window: &WindowWrap) {
+        let ws = self.state.get_current_workspace();
+        let mut ws = ws.get_mut();
+
+        if let Some(container) = ws.root().container_having_window(window.id()) {
+            ws.set_container_and_window_focus(&container, window);
+            let serial = SERIAL_COUNTER.next_serial();
+
+            if self.state.space.window_bbox(window.inner()).is_some() {
+                self.toggle_window_focus(serial, window.inner());
+            } else {
+                // The window will be mapped on the next redraw
+                let keyboard = self.state.seat.get_keyboard().unwrap();
+                keyboard.set_focus(&mut self.state, Some(window.wl_surface()), serial);
+                ws.needs_redraw = true;
+            }
+
+            ws.update_borders();
+        }
+    }
+
+    pub fn cycle_windows(&mut self) {
+        match &mut self.state.window_switcher {
+            Some(switcher) => switcher.next(),
+            None => {
+                let entries = self.state.windows_by_recent_focus();
+                self.state.window_switcher = WindowSwitcher::new(entries, self.state.modifiers);
+            }
+        }
+    }
+
+    pub fn commit_window_switcher(&mut self) {
+        if let Some(switcher) = self.state.window_switcher.take() {
+            let SwitcherEntry { workspace, window } = switcher.selected().clone();
+            debug!("Window switcher selected window({})", window.id());
+            self.state
+                .move_to_workspace(workspace, &self.config);
+            self.focus_window(&window);
+        }
+    }
+
+    pub fn cancel_window_switcher(&mut self) {
+        self.state.window_switcher = None;
+    }
+
+    pub fn move_focused_window_to_workspace(&mut self, num: u8) {
+        let window = self.state.get_current_workspace().get().get_focus().1;
+
+        if let Some(window) = window {
+            let current = self.state.current_workspace;
+            debug!("Moving window({}) to workspace({})", window.id(), num);
+            self.state
+                .move_window_to_workspace(&window, current, num, &self.config);
+            self.focus_previous_window();
+        }
+    }
+
+    /// Switch to the next or previous existing workspace, wrapping around
+    pub fn cycle_workspace(&mut self, offset: i8) {
+        let mut workspaces: Vec<u8> = self.state.workspaces.keys().copied().collect();
+        workspaces.sort_unstable();
+
+        let current = workspaces
+            .iter()
+            .position(|num| *num == self.state.current_workspace);
+
+        if let Some(current) = current {
+            let len = workspaces.len() as i32;
+            let next = (current as i32 + offset as i32).rem_euclid(len) as usize;
+            let num = workspaces[next];
+            if num != self.state.current_workspace {
+                self.state.move_to_workspace(num, &self.config);
+            }
+        }
+    }
+
+    pub fn set_binding_mode(&mut self, mode: String) {
+        if !self.config.has_mode(&mode) {
+            warn!("Unknown binding mode '{}'", mode);
+            return;
+        }
+
+        debug!("Entering binding mode '{}'", mode);
+        self.state.binding_mode = mode.clone();
+        self.state.ipc_event(IpcEvent::Mode { mode });
+    }
+
+    pub fn switch_layout(&mut self, switch: LayoutSwitch) {
+        let index = self
+            .config
+            .keyboard
+            .switch_index(self.state.keyboard_layout, switch);
+
+        if index != self.state.keyboard_layout {
+            self.set_keyboard_layout(index);
+        }
+    }
+
+    /// Load the keymap of a configured layout
+    pub fn set_keyboard_layout(&mut self, index: usize) {
+        let keyboard = self.state.seat.get_keyboard().unwrap();
+        let xkb_config = self.config.keyboard.xkb_config(index);
+
+        match keyboard.set_xkb_config(&mut self.state, xkb_config) {
+            Ok(()) => {
+                self.state.keyboard_layout = index;
+                debug!(
+                    "Keyboard layout set to {}",
+                    self.config.keyboard.layout_name(index)
+                );
+            }
+            Err(err) => warn!("Failed to load keyboard layout: {:?}", err),
+        }
+    }
+
+    pub fn toggle_overview(&mut self) {
+        if self.state.overview.take().is_some() {
+            return;
+        }
+
+        let output = self.state.get_current_workspace().get().output.clone();
+        let workspaces = self
+            .state
+            .workspaces
+            .iter()
+            .filter(|(_, workspace)| workspace.get().output == output)
+            .map(|(num, _)| *num)
+            .collect();
+
+        self.state.window_switcher = None;
+        self.state.overview = Some(Overview::new(
+            output,
+            workspaces,
+            self.state.current_workspace,
+        ));
+
+        // Release the pointer focus so clients don't get stale enter events
+        let pointer = self.state.seat.get_pointer().unwrap();
+        let location = pointer.current_location();
+        let time = self.state.start_time.elapsed().as_millis() as u32;
+        self.state.pointer_motion(pointer, location, time);
+    }
+
+    pub fn overview_move(&mut self, direction: Direction) {
+        if let Some(overview) = &mut self.state.overview {
+            overview.move_selection(direction);
+        }
+    }
+
+    pub fn overview_next_window(&mut self) {
+        if let Some(overview) = &mut self.state.overview {
+            overview.next_window(&self.state.workspaces);
+        }
+    }
+
+    /// Close the overview, focusing the selected workspace and window
+    pub fn commit_overview(&mut self) {
+        if let Some(overview) = self.state.overview.take() {
+            let workspace = overview.selected_workspace();
+            let window = overview.selected_window(&self.state.workspaces);
+            debug!("Overview selected workspace({})", workspace);
+
+            self.state
+                .move_to_workspace(workspace, &self.config);
+
+            if let Some(window) = window {
+                self.focus_window(&window);
+            }
+
+            let pointer = self.state.seat.get_pointer().unwrap();
+            let location = pointer.current_location();
+            let time = self.state.start_time.elapsed().as_millis() as u32;
+            self.state.pointer_motion(pointer, location, time);
+        }
+    }
+
+    /// Clicking a thumbnail selects it, dropping a window thumbnail on another workspace moves it
+    fn overview_pointer_button(&mut self, button: Option<MouseButton>, state: ButtonState) {
+        if button != Some(MouseButton::Left) {
+            return;
+        }
+
+        let location = self.state.seat.get_pointer().unwrap().current_location();
+        let workspaces = &self.state.workspaces;
+
+        let overview = match &mut self.state.overview {
+            Some(overview) => overview,
+            None => return,
+        };
+
+        let output_geometry = match self.state.space.output_geometry(&overview.output) {
+            Some(geometry) => geometry,
+            None => return,
+        };
+
+        match state {
+            ButtonState::Pressed => {
+                if let Some((num, idx, window)) =
+                    overview.window_under(workspaces, output_geometry, location)
+                {
+                    overview.select(num, Some(idx));
+                    overview.start_drag(num, window, output_geometry, location);
+                } else if let Some((num, _)) = overview.workspace_under(output_geometry, location) {
+                    overview.select(num, None);
+                }
+            }
+            ButtonState::Released => {
+                let target = overview
+                    .workspace_under(output_geometry, location)
+                    .map(|(num, _)| num);
+
+                match (overview.drag.take(), target) {
+                    (Some(drag), Some(target)) if drag.workspace != target => {
+                        debug!(
+                            "Overview: moving window({}) to workspace({})",
+                            drag.window.id(),
+                            target
+                        );
+                        self.state.move_window_to_workspace(
+                            &drag.window,
+                            drag.workspace,
+                            target,
+                            &self.config,
+                        );
+                    }
+                    (_, Some(target)) if target == overview.selected_workspace() => {
+                        self.commit_overview()
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
     pub fn handle_pointer_button<I: InputBackend>(
         &mut self,
         event: &<I as InputBackend>::PointerButtonEvent,
@@ -150,6 +551,52 @@ impl CallLoopData {
         let state = event.state();
         let time = event.time();
 
+        if self.state.overview.is_some() {
+            self.overview_pointer_button(event.button(), state);
+            return;
+        }
+
+        // The lock surface gets every click, bindings and focus changes are disabled
+        if self.state.session_lock.is_locked() {
+            let event = ButtonEvent {
+                button,
+                state,
+                serial,
+                time,
+            };
+            pointer.button(&mut self.state, &event);
+            return;
+        }
+
+        let binding = match state {
+            ButtonState::Pressed => self
+                .config
+                .mouse_bindings
+                .iter()
+                .find(|binding| binding.match_button(self.state.modifiers, button))
+                .map(|binding| binding.action.clone()),
+            ButtonState::Released => None,
+        };
+
+        // Clients neither get the press nor the release of a button triggering an action
+        match &binding {
+            Some(Action::DragMove | Action::DragResize) | None => {}
+            Some(action) => {
+                debug!("mouse action triggered: {:?}", action);
+                self.state.ipc_event(IpcEvent::Binding {
+                    action: action.clone(),
+                });
+                self.state.intercepted_button = Some(button);
+                self.run_action(action.clone().into(), None);
+                return;
+            }
+        }
+
+        if state == ButtonState::Released && self.state.intercepted_button == Some(button) {
+            self.state.intercepted_button = None;
+            return;
+        }
+
         pointer.button(
             &mut self.state,
             &ButtonEvent {
@@ -160,9 +607,17 @@ impl CallLoopData {
             },
         );
 
+        if let Some(action) = binding {
+            if self.start_pointer_drag(action, serial) {
+                return;
+            }
+        }
+
         if let Some(MouseButton::Left) = event.button() {
             if ButtonState::Pressed == state {
-                if let Some(window) = self
+                if let Some((layer, _)) = self.state.layer_under(pointer.current_location()) {
+                    self.state.focus_layer_on_demand(&layer);
+                } else if let Some(window) = self
                     .state
                     .space
                     .window_under(pointer.current_location())
@@ -174,31 +629,14 @@ impl CallLoopData {
                     }
 
                     let window = WindowWrap::from(window);
-
-                    if self.state.mod_pressed && window.is_floating() {
-                        let pos = pointer.current_location();
-                        let initial_window_location = (pos.x as i32, pos.y as i32).into();
-                        let start_data = pointer.grab_start_data().unwrap();
-
-                        let window = window.inner().clone();
-
-                        let grab = MoveSurfaceGrab {
-                            start_data,
-                            window,
-                            initial_window_location,
-                        };
-
-                        pointer.set_grab(&mut self.state, grab, serial, Focus::Clear);
-                    } else {
-                        let id = window.id();
-                        let ws = self.state.get_current_workspace();
-                        let mut ws = ws.get_mut();
-                        let container = ws.root().container_having_window(id);
-                        if let Some(container) = container {
-                            ws.set_container_and_window_focus(&container, &window);
-                            ws.update_borders();
-                            self.toggle_window_focus(serial, window.inner());
-                        }
+                    let id = window.id();
+                    let ws = self.state.get_current_workspace();
+                    let mut ws = ws.get_mut();
+                    let container = ws.root().container_having_window(id);
+                    if let Some(container) = container {
+                        ws.set_container_and_window_focus(&container, &window);
+                        ws.update_borders();
+                        self.toggle_window_focus(serial, window.inner());
                     }
                 } else {
                     self.state.space.windows().for_each(|window| {
@@ -218,6 +656,140 @@ impl CallLoopData {
         }
     }
 
+    /// Grab the pointer to move or resize the floating window under it,
+    /// returns false when there is no floating window to drag
+    fn start_pointer_drag(&mut self, action: Action, serial: Serial) -> bool {
+        let pointer = self.state.seat.get_pointer().unwrap();
+        let window = match self
+            .state
+            .space
+            .window_under(pointer.current_location())
+            .cloned()
+        {
+            Some(window) => window,
+            None => return false,
+        };
+
+        if window.user_data().get::<WindowState>().is_none() {
+            return false;
+        }
+
+        let window = WindowWrap::from(window);
+        if !window.is_floating() {
+            return false;
+        }
+
+        let start_data = match pointer.grab_start_data() {
+            Some(start_data) => start_data,
+            None => return false,
+        };
+
+        match action {
+            Action::DragMove => {
+                let grab = MoveSurfaceGrab {
+                    start_data,
+                    window: window.inner().clone(),
+                    initial_window_location: window.loc(),
+                };
+
+                pointer.set_grab(&mut self.state, grab, serial, Focus::Clear);
+            }
+            Action::DragResize => {
+                let grab = ResizeSurfaceGrab {
+                    start_data,
+                    window: window.inner().clone(),
+                    initial_window_size: window.size(),
+                };
+
+                pointer.set_grab(&mut self.state, grab, serial, Focus::Clear);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Run the mouse binding matching a scroll event, returns true when the event was consumed
+    pub fn handle_scroll_binding<I: InputBackend>(
+        &mut self,
+        event: &<I as InputBackend>::PointerAxisEvent,
+    ) -> bool {
+        if self.state.session_lock.is_locked() {
+            return false;
+        }
+
+        let modifiers = self.state.modifiers;
+        let bound = |direction| {
+            self.config
+                .mouse_bindings
+                .iter()
+                .any(|binding| binding.match_scroll(modifiers, direction))
+        };
+
+        let amount = |axis| {
+            event
+                .amount(axis)
+                .unwrap_or_else(|| event.amount_discrete(axis).unwrap_or(0.0) * SCROLL_STEP)
+        };
+
+        let (mut horizontal, mut vertical) = self.state.scroll_accumulator;
+        horizontal += amount(Axis::Horizontal);
+        vertical += amount(Axis::Vertical);
+
+        // Touchpads send many small events, an action runs once per wheel step
+        let direction = if vertical <= -SCROLL_STEP {
+            Some(ScrollDirection::Up)
+        } else if vertical >= SCROLL_STEP {
+            Some(ScrollDirection::Down)
+        } else if horizontal <= -SCROLL_STEP {
+            Some(ScrollDirection::Left)
+        } else if horizontal >= SCROLL_STEP {
+            Some(ScrollDirection::Right)
+        } else {
+            None
+        };
+
+        let direction = match direction {
+            Some(direction) => direction,
+            None => {
+                // Small steps are only held back while they head to a bound direction
+                let accumulating = (vertical < 0.0 && bound(ScrollDirection::Up))
+                    || (vertical > 0.0 && bound(ScrollDirection::Down))
+                    || (horizontal < 0.0 && bound(ScrollDirection::Left))
+                    || (horizontal > 0.0 && bound(ScrollDirection::Right));
+
+                self.state.scroll_accumulator = if accumulating {
+                    (horizontal, vertical)
+                } else {
+                    (0.0, 0.0)
+                };
+
+                return accumulating;
+            }
+        };
+
+        self.state.scroll_accumulator = (0.0, 0.0);
+        let action = self
+            .config
+            .mouse_bindings
+            .iter()
+            .find(|binding| binding.match_scroll(modifiers, direction))
+            .map(|binding| binding.action.clone());
+
+        match action {
+            Some(action) => {
+                debug!("mouse action triggered: {:?}", action);
+                self.state.ipc_event(IpcEvent::Binding {
+                    action: action.clone(),
+                });
+                self.run_action(action.into(), None);
+                true
+            }
+            // Scrolling in an unbound direction goes to the client
+            None => false,
+        }
+    }
+
     fn toggle_window_focus(&mut self, serial: Serial, window: &Window) {
         let keyboard = self.state.seat.get_keyboard().unwrap();
 
@@ -360,43 +932,60 @@ impl CallLoopData {
         let mut ws = ws.get_mut();
         let focus = ws.get_focus();
 
-        if let Some(window) = focus.1 {
+        if let Some(window) = &focus.1 {
             window.toggle_floating();
-            let output_geometry = self.state.space.output_geometry(&ws.output).unwrap();
-            let redraw = focus.0.get_mut().update_layout(output_geometry);
-            ws.needs_redraw = redraw;
+            if let Some(output_geometry) = self.state.space.output_geometry(&ws.output) {
+                let redraw = focus.0.get_mut().update_layout(output_geometry);
+                ws.needs_redraw = redraw;
+            }
         }
 
         ws.update_borders();
+        drop(ws);
+
+        if let Some(window) = focus.1 {
+            self.state.ipc_window_event(&window, WindowChange::Floating);
+        }
     }
 
     pub fn toggle_fullscreen_window(&mut self) {
         let ws = self.state.get_current_workspace();
         let mut ws = ws.get_mut();
 
-        if ws.fullscreen_layer.is_some() {
-            ws.fullscreen_layer = None;
+        let changed = if let Some(layer) = ws.fullscreen_layer.take() {
             ws.update_layout(&self.state.space);
+            match layer {
+                Node::Window(window) => Some((window, false)),
+                Node::Container(_) => None,
+            }
         } else {
             let (_c, window) = ws.get_focus();
-            if let Some(window) = window {
-                ws.fullscreen_layer = Some(Node::Window(window));
-            }
-        }
+            window.map(|window| {
+                ws.fullscreen_layer = Some(Node::Window(window.clone()));
+                (window, true)
+            })
+        };
 
         ws.needs_redraw = true;
+        drop(ws);
+
+        if let Some((window, fullscreen)) = changed {
+            self.state.ipc_fullscreen_event(&window, fullscreen);
+        }
     }
 
     pub fn toggle_fullscreen_container(&mut self) {
         let ws = self.state.get_current_workspace();
         let mut ws = ws.get_mut();
-        if ws.fullscreen_layer.is_some() {
+        let mut left_window = None;
+        if let Some(layer) = ws.fullscreen_layer.take() {
             ws.reset_gaps(&self.state.space);
-            ws.fullscreen_layer = None;
             ws.update_layout(&self.state.space);
-        } else {
+            if let Node::Window(window) = layer {
+                left_window = Some(window);
+            }
+        } else if let Some(output_geometry) = self.state.space.output_geometry(&ws.output) {
             let (container, _) = ws.get_focus();
-            let output_geometry = self.state.space.output_geometry(&ws.output).unwrap();
             container
                 .get_mut()
                 .set_fullscreen_loc_and_size(output_geometry);
@@ -404,7 +993,12 @@ impl CallLoopData {
         }
 
         ws.update_borders();
-        ws.needs_redraw = true
+        ws.needs_redraw = true;
+        drop(ws);
+
+        if let Some(window) = left_window {
+            self.state.ipc_fullscreen_event(&window, false);
+        }
     }
 
     fn scan_window(&mut self, direction: Direction) -> Option<Window> {
@@ -1,6 +1,11 @@
-use crate::backend::{BackendHandler, InputHandler, OutputId};
-use crate::config::keybinding::Action;
+use crate::backend::{libinput, BackendHandler, InputHandler, OutputId};
+use crate::config;
+use crate::config::keybinding::PressedKey;
+use crate::config::keyboard::LayoutSwitch;
+use crate::inputs::chord::{match_chord, ChordResult, PendingChord};
 use crate::inputs::handlers::Direction;
+use crate::ipc::protocol::{Event as IpcEvent, WindowChange};
+use crate::shell::windows::toplevel::{WindowState, WindowWrap};
 use crate::state::seat::SeatState;
 use crate::{CallLoopData, Wazemmes};
 use slog_scope::{debug, info};
@@ -14,6 +19,7 @@ use smithay::desktop::WindowSurfaceType;
 use smithay::input::keyboard::{keysyms as xkb, FilterResult};
 use smithay::input::pointer::{CursorImageStatus, MotionEvent, PointerHandle};
 use smithay::input::{Seat, SeatHandler};
+use smithay::reexports::input;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::reexports::wayland_server::Resource;
 use smithay::utils::{Logical, Point, SERIAL_COUNTER};
@@ -22,7 +28,10 @@ use smithay::wayland::primary_selection::{
     set_primary_focus, PrimarySelectionHandler, PrimarySelectionState,
 };
 use smithay::{delegate_primary_selection, delegate_seat};
+use std::any::Any;
+use std::time::Duration;
 
+pub mod chord;
 pub(crate) mod grabs;
 pub mod handlers;
 
@@ -33,19 +42,37 @@ pub enum KeyAction {
     MoveWindow(Direction),
     MoveContainer(Direction),
     MoveFocus(Direction),
-    Run(String, Vec<(String, String)>),
+    FocusParent,
+    Run {
+        command: String,
+        env: Vec<(String, String)>,
+        shell: bool,
+    },
     MoveToWorkspace(u8),
+    CycleWorkspace(i8),
+    MoveWindowToWorkspace(u8),
     LayoutVertical,
     LayoutHorizontal,
     ToggleFloating,
+    CycleWindows,
+    CancelWindowSwitcher,
+    ToggleOverview,
+    OverviewMove(Direction),
+    OverviewNextWindow,
+    CommitOverview,
     VtSwitch(i32),
     Close,
+    SetMode(String),
+    SwitchLayout(LayoutSwitch),
+    Reload,
     Quit,
+    Command(String),
+    CommandPalette,
     None,
 }
 
 impl InputHandler for CallLoopData {
-    fn process_input_event<I: InputBackend>(
+    fn process_input_event<I: InputBackend + 'static>(
         &mut self,
         event: InputEvent<I>,
         output_id: Option<&OutputId>,
@@ -59,6 +86,20 @@ impl InputHandler for CallLoopData {
             .cloned();
 
         match event {
+            InputEvent::DeviceAdded { mut device } => {
+                // Only libinput devices can be configured
+                let device: &mut dyn Any = &mut device;
+                if let Some(device) = device.downcast_mut::<input::Device>() {
+                    self.configure_input_device(device);
+                    self.state.input_devices.push(device.clone());
+                }
+            }
+            InputEvent::DeviceRemoved { device } => {
+                let device: &dyn Any = &device;
+                if let Some(device) = device.downcast_ref::<input::Device>() {
+                    self.state.input_devices.retain(|known| known != device);
+                }
+            }
             InputEvent::Keyboard { event, .. } => self.process_shortcut::<I>(event, session),
             InputEvent::PointerMotion { event } => {
                 let pointer = self.state.seat.get_pointer().unwrap();
@@ -88,8 +129,11 @@ impl InputHandler for CallLoopData {
                 let pointer = self.state.seat.get_pointer().unwrap();
 
 
-                let output = absolute_output
-                    .unwrap_or_else(|| self.state.space.outputs().next().unwrap().clone());
+                let output = absolute_output.or_else(|| self.state.space.outputs().next().cloned());
+                let output = match output {
+                    Some(output) => output,
+                    None => return,
+                };
                 let output_geo = self.state.space.output_geometry(&output).unwrap();
                 let output_loc = output_geo.loc.to_f64();
 
@@ -100,6 +144,10 @@ impl InputHandler for CallLoopData {
             }
             InputEvent::PointerButton { event, .. } => self.handle_pointer_button::<I>(&event),
             InputEvent::PointerAxis { event, .. } => {
+                if self.handle_scroll_binding::<I>(&event) {
+                    return;
+                }
+
                 let frame = handlers::basic_axis_frame::<I>(&event);
 
                 let pointer = self.state.seat.get_pointer().unwrap();
@@ -111,28 +159,97 @@ impl InputHandler for CallLoopData {
 }
 
 impl CallLoopData {
+    fn configure_input_device(&self, device: &mut input::Device) {
+        let types = libinput::device_types(device);
+        let settings = config::input::settings_for(&self.config.inputs, device.name(), &types);
+        debug!("Configuring input device '{}': {:?}", device.name(), settings);
+        libinput::configure_device(device, &settings);
+    }
+
+    /// Apply the `inputs` config again to every connected device
+    pub fn configure_input_devices(&mut self) {
+        let mut devices = std::mem::take(&mut self.state.input_devices);
+        for device in devices.iter_mut() {
+            self.configure_input_device(device);
+        }
+
+        self.state.input_devices = devices;
+    }
+
     fn process_shortcut<I: InputBackend>(
         &mut self,
         event: <I as InputBackend>::KeyboardKeyEvent,
         session: Option<&mut AutoSession>,
     ) {
+        // Windows mapped meanwhile might have taken the focus from the lock or a layer surface
+        self.state.refresh_lock_focus();
+        self.state.refresh_layer_focus();
         let action = self.keyboard_key_to_action::<I>(event);
         if action != KeyAction::None {
             debug!("keyboard action triggered: {:?}", action)
         };
 
+        self.run_action(action, session);
+
+        let commit_switcher = self
+            .state
+            .window_switcher
+            .as_ref()
+            .map(|switcher| switcher.should_commit(&self.state.modifiers))
+            .unwrap_or(false);
+
+        if commit_switcher {
+            self.commit_window_switcher();
+        }
+    }
+
+    /// Run an action triggered by a key binding or a mouse binding
+    pub fn run_action(&mut self, action: KeyAction, session: Option<&mut AutoSession>) {
+        // Other actions could move the keyboard focus away from the lock surface
+        let allowed_when_locked = matches!(
+            action,
+            KeyAction::Run { .. } | KeyAction::VtSwitch(_) | KeyAction::None
+        );
+
+        if self.state.session_lock.is_locked() && !allowed_when_locked {
+            debug!("Ignoring {:?} while the session is locked", action);
+            return;
+        }
+
         match action {
-            KeyAction::Run(cmd, env) => Self::run_command(cmd, env),
+            KeyAction::Run {
+                command,
+                env,
+                shell,
+            } => {
+                self.run_command(&command, env, shell).ok();
+            }
             KeyAction::Close => self.close(),
             KeyAction::LayoutVertical => self.set_layout_v(),
             KeyAction::LayoutHorizontal => self.set_layout_h(),
-            KeyAction::MoveToWorkspace(num) => self.state.move_to_workspace(num),
+            KeyAction::MoveToWorkspace(num) => {
+                self.state.move_to_workspace(num, &self.config)
+            }
+            KeyAction::CycleWorkspace(offset) => self.cycle_workspace(offset),
+            KeyAction::MoveWindowToWorkspace(num) => self.move_focused_window_to_workspace(num),
             KeyAction::MoveFocus(direction) => self.move_focus(direction),
+            KeyAction::FocusParent => self.focus_parent(),
             KeyAction::MoveWindow(direction) => self.move_window(direction),
             KeyAction::MoveContainer(direction) => self.move_container(direction),
             KeyAction::ToggleFloating => self.toggle_floating(),
+            KeyAction::CycleWindows => self.cycle_windows(),
+            KeyAction::CancelWindowSwitcher => self.cancel_window_switcher(),
+            KeyAction::ToggleOverview => self.toggle_overview(),
+            KeyAction::OverviewMove(direction) => self.overview_move(direction),
+            KeyAction::OverviewNextWindow => self.overview_next_window(),
+            KeyAction::CommitOverview => self.commit_overview(),
             KeyAction::ToggleFullScreenWindow => self.toggle_fullscreen_window(),
             KeyAction::ToggleFullScreenContainer => self.toggle_fullscreen_container(),
+            KeyAction::SetMode(mode) => self.set_binding_mode(mode),
+            KeyAction::SwitchLayout(switch) => self.switch_layout(switch),
+            KeyAction::Reload => self.reload_config(),
+            KeyAction::Command(command) => self.run_text_command(&command),
+            KeyAction::CommandPalette => self.open_command_palette(),
             KeyAction::Quit => {
                 info!("Quitting");
                 self.close_compositor();
@@ -157,24 +274,98 @@ impl CallLoopData {
         let serial = SERIAL_COUNTER.next_serial();
         let time = Event::time(&evt);
         let keyboard = self.state.seat.get_keyboard().unwrap();
+        let bindings = self.config.bindings(&self.state.binding_mode);
+        let chord_timeout = Duration::from_millis(self.config.chord_timeout);
         let data = &mut self.state;
-        let bindings = &self.config.keybindings;
-
-        let mut mod_pressed = false;
 
-        let action = keyboard
-            .input(data, keycode, state, serial, time, | app_state, modifiers, key_handle| {
+        keyboard
+            .input(data, keycode, state, serial, time, |app_state, modifiers, key_handle| {
                 let keysym = key_handle.modified_sym();
+                app_state.modifiers = *modifiers;
+
+                // Bindings are disabled while the session is locked, switching VT is still allowed
+                if app_state.session_lock.is_locked() {
+                    return match keysym {
+                        xkb::KEY_XF86Switch_VT_1..=xkb::KEY_XF86Switch_VT_12
+                            if state == KeyState::Pressed =>
+                        {
+                            FilterResult::Intercept(KeyAction::VtSwitch(
+                                (keysym - xkb::KEY_XF86Switch_VT_1 + 1) as i32,
+                            ))
+                        }
+                        _ => FilterResult::Forward,
+                    };
+                }
+
                 if state == KeyState::Pressed {
-                    if modifiers.alt {
-                        mod_pressed = true;
+                    if app_state.window_switcher.is_some() && keysym == xkb::KEY_Escape {
+                        return FilterResult::Intercept(KeyAction::CancelWindowSwitcher);
                     }
 
-                    let action = bindings
-                        .iter()
-                        .find_map(|binding| binding.match_action(*modifiers, keysym))
-                        .map(Action::into)
-                        .map(FilterResult::Intercept);
+                    let chording = app_state.pending_chord.is_some();
+                    let pending = app_state
+                        .pending_chord
+                        .take()
+                        .filter(|pending| !pending.is_expired(chord_timeout));
+
+                    if pending.is_some() && PendingChord::is_modifier_key(keysym) {
+                        app_state.pending_chord = pending;
+                        return FilterResult::Forward;
+                    }
+
+                    app_state.cancel_chord_timer();
+
+                    // Keys are swallowed while a chord is in progress
+                    let pressed = PressedKey {
+                        modifiers: *modifiers,
+                        keysym,
+                        keycode,
+                    };
+
+                    let action = match match_chord(bindings, pending, pressed) {
+                        ChordResult::Action(action) => {
+                            app_state.ipc_event(IpcEvent::Binding {
+                                action: action.clone(),
+                            });
+                            Some(FilterResult::Intercept(action.into()))
+                        }
+                        ChordResult::OnRelease(action) => {
+                            app_state.pending_release = Some((keycode, action));
+                            Some(FilterResult::Intercept(KeyAction::None))
+                        }
+                        ChordResult::Pending(strokes) => {
+                            let pending = PendingChord::new(strokes);
+                            debug!("Key chord pending: {}", pending.describe());
+                            app_state.start_chord(pending, chord_timeout);
+                            Some(FilterResult::Intercept(KeyAction::None))
+                        }
+                        ChordResult::NoMatch { cancelled: true } => {
+                            debug!("Key chord cancelled");
+                            Some(FilterResult::Intercept(KeyAction::None))
+                        }
+                        ChordResult::NoMatch { cancelled: false } => None,
+                    };
+
+                    // The chord completed, was cancelled or expired
+                    if chording && app_state.pending_chord.is_none() {
+                        app_state.ipc_event(IpcEvent::Chord { pending: None });
+                    }
+
+                    // Clients do not get keyboard input while the overview is shown
+                    if app_state.overview.is_some() {
+                        let overview_action = match keysym {
+                            xkb::KEY_Escape => KeyAction::ToggleOverview,
+                            xkb::KEY_Return => KeyAction::CommitOverview,
+                            xkb::KEY_Tab => KeyAction::OverviewNextWindow,
+                            xkb::KEY_Left => KeyAction::OverviewMove(Direction::Left),
+                            xkb::KEY_Right => KeyAction::OverviewMove(Direction::Right),
+                            xkb::KEY_Up => KeyAction::OverviewMove(Direction::Up),
+                            xkb::KEY_Down => KeyAction::OverviewMove(Direction::Down),
+                            _ => return action.unwrap_or(FilterResult::Intercept(KeyAction::None)),
+                        };
+
+                        return FilterResult::Intercept(overview_action);
+                    }
 
                     match action {
                         None => match keysym {
@@ -188,14 +379,21 @@ impl CallLoopData {
                         Some(action) => action,
                     }
                 } else {
-                    mod_pressed = false;
-                    FilterResult::Forward
+                    match app_state.pending_release.take() {
+                        Some((pressed, action)) if pressed == keycode => {
+                            app_state.ipc_event(IpcEvent::Binding {
+                                action: action.clone(),
+                            });
+                            FilterResult::Intercept(action.into())
+                        }
+                        pending => {
+                            app_state.pending_release = pending;
+                            FilterResult::Forward
+                        }
+                    }
                 }
             })
-            .unwrap_or(KeyAction::None);
-
-        self.state.mod_pressed = mod_pressed;
-        action
+            .unwrap_or(KeyAction::None)
     }
 }
 
@@ -206,10 +404,12 @@ impl Wazemmes {
         position: Point<f64, Logical>,
         time: u32,
     ) {
-        let under = self
-            .space
-            .surface_under(position, WindowSurfaceType::all())
-            .map(|(_, surface, location)| (surface, location));
+        // Thumbnails are not real surfaces, keep the pointer focus away from clients
+        let under = if self.overview.is_some() {
+            None
+        } else {
+            self.surface_under(position)
+        };
 
         pointer.motion(
             self,
@@ -233,6 +433,17 @@ impl SeatHandler for Wazemmes {
     }
 
     fn focus_changed(&mut self, seat: &Seat<Self>, surface: Option<&WlSurface>) {
+        let focused_window = surface
+            .and_then(|surface| self.space.window_for_surface(surface, WindowSurfaceType::TOPLEVEL))
+            .filter(|window| window.user_data().get::<WindowState>().is_some())
+            .cloned()
+            .map(WindowWrap::from);
+
+        if let Some(window) = focused_window {
+            self.focus_history.push(window.id());
+            self.ipc_window_event(&window, WindowChange::Focus);
+        }
+
         let dh = &self.display;
 
         let focus = surface.and_then(|s| dh.get_client(s.id()).ok());
@@ -0,0 +1,227 @@
+//! `wazemmes msg`: talk to a running compositor from the command line
+
+use crate::cli::MsgArgs;
+use crate::config::keybinding::Action;
+use crate::ipc::protocol::{read_message, write_message, Reply, Request};
+use crate::ipc::{socket_path, SOCKET_VAR};
+use crate::process::quote_arg;
+use serde::Serialize;
+use std::env;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MsgError {
+    #[error("No IPC socket found, is {0} or WAYLAND_DISPLAY set?")]
+    NoSocket(&'static str),
+    #[error("Failed to connect to {0}: {1}")]
+    Connect(PathBuf, io::Error),
+    #[error("IPC error: {0}")]
+    Io(#[from] io::Error),
+    #[error("No message given")]
+    EmptyMessage,
+    #[error("Too many arguments for '{0}'")]
+    TooManyArguments(String),
+    #[error("{0}")]
+    Compositor(String),
+}
+
+/// Send the message given on the command line, print the reply then the subscribed events
+pub fn run(args: &MsgArgs) -> Result<(), MsgError> {
+    let path = match args.socket.clone() {
+        Some(path) => path,
+        None => find_socket()?,
+    };
+
+    let mut stream = UnixStream::connect(&path).map_err(|err| MsgError::Connect(path, err))?;
+
+    if !args.message.is_empty() {
+        let request = parse_message(&args.message)?;
+        let reply = send(&mut stream, &request)?;
+        print(&reply, args.pretty)?;
+    }
+
+    if args.subscribe.is_empty() {
+        return Ok(());
+    }
+
+    send(
+        &mut stream,
+        &Request::Subscribe {
+            events: args.subscribe.clone(),
+        },
+    )?;
+
+    loop {
+        let event: Reply = read_message(&mut stream)?;
+        print(&event, args.pretty)?;
+    }
+}
+
+fn find_socket() -> Result<PathBuf, MsgError> {
+    env::var_os(SOCKET_VAR)
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("WAYLAND_DISPLAY").and_then(|display| socket_path(&display)))
+        .ok_or(MsgError::NoSocket(SOCKET_VAR))
+}
+
+/// Send a request and wait for its reply, an error reply is turned into an error
+fn send(stream: &mut UnixStream, request: &Request) -> Result<Reply, MsgError> {
+    write_message(stream, request)?;
+    match read_message(stream)? {
+        Reply::Error { message } => Err(MsgError::Compositor(message)),
+        reply => Ok(reply),
+    }
+}
+
+fn print<T: Serialize>(message: &T, pretty: bool) -> Result<(), MsgError> {
+    let json = if pretty {
+        serde_json::to_string_pretty(message)
+    } else {
+        serde_json::to_string(message)
+    };
+
+    println!("{}", json.map_err(io::Error::from)?);
+    Ok(())
+}
+
+/// A query such as `get-tree`, an action in kebab case followed by its argument,
+/// for example `move-to-workspace 3`, `set-mode resize` or `run alacritty -e htop`,
+/// anything else is sent as a command such as `focus parent; workspace 3`
+pub fn parse_message(message: &[String]) -> Result<Request, MsgError> {
+    let (name, args) = match message.split_first() {
+        Some((name, args)) => (name.as_str(), args),
+        None => return Err(MsgError::EmptyMessage),
+    };
+
+    let query = match name {
+        "get-tree" => Some(Request::GetTree),
+        "get-workspaces" => Some(Request::GetWorkspaces),
+        "get-outputs" => Some(Request::GetOutputs),
+        "get-inputs" => Some(Request::GetInputs),
+        "get-config" => Some(Request::GetConfig),
+        "get-version" => Some(Request::GetVersion),
+        _ => None,
+    };
+
+    match query {
+        Some(_) if !args.is_empty() => Err(MsgError::TooManyArguments(name.to_string())),
+        Some(query) => Ok(query),
+        None => match parse_action(name, args) {
+            Some(action) => Ok(Request::Run { action }),
+            // The compositor parses commands and reports where they are wrong
+            None => Ok(Request::Command {
+                command: message.join(" "),
+            }),
+        },
+    }
+}
+
+fn parse_action(name: &str, args: &[String]) -> Option<Action> {
+    // Arguments are quoted so the compositor splits them back as given
+    if name == "run" {
+        let args: Vec<String> = args.iter().map(|arg| quote_arg(arg)).collect();
+        return Some(Action::Run {
+            env: vec![],
+            command: args.join(" "),
+            shell: false,
+        });
+    }
+
+    let variant = pascal_case(name);
+    // The argument is tried as a number, an enum variant then a string
+    let candidates = match args {
+        [] => vec![variant],
+        [arg] => vec![
+            format!("{}({})", variant, arg),
+            format!("{}({})", variant, pascal_case(arg)),
+            format!("{}({:?})", variant, arg),
+        ],
+        _ => return None,
+    };
+
+    candidates
+        .iter()
+        .find_map(|candidate| ron::from_str(candidate).ok())
+}
+
+/// `move-to-workspace` -> `MoveToWorkspace`
+fn pascal_case(name: &str) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::keybinding::Action;
+    use crate::config::keyboard::LayoutSwitch;
+    use crate::ipc::client::{parse_message, MsgError};
+    use crate::ipc::protocol::Request;
+    use speculoos::prelude::*;
+
+    fn parse(message: &str) -> Result<Request, MsgError> {
+        let message: Vec<String> = message.split_whitespace().map(String::from).collect();
+        parse_message(&message)
+    }
+
+    fn action(message: &str) -> Action {
+        match parse(message).unwrap() {
+            Request::Run { action } => action,
+            request => panic!("expected an action, got {:?}", request),
+        }
+    }
+
+    #[test]
+    fn should_parse_queries() {
+        assert_that!(parse("get-tree").unwrap()).is_equal_to(Request::GetTree);
+        assert_that!(parse("get-version").unwrap()).is_equal_to(Request::GetVersion);
+        assert_that!(matches!(parse("get-tree now"), Err(MsgError::TooManyArguments(_))))
+            .is_true();
+    }
+
+    #[test]
+    fn should_parse_actions_and_their_argument() {
+        assert_that!(action("close")).is_equal_to(Action::Close);
+        assert_that!(action("move-to-workspace 3")).is_equal_to(Action::MoveToWorkspace(3));
+        assert_that!(action("switch-layout next"))
+            .is_equal_to(Action::SwitchLayout(LayoutSwitch::Next));
+        assert_that!(action("set-mode resize")).is_equal_to(Action::SetMode("resize".to_string()));
+    }
+
+    #[test]
+    fn should_join_run_arguments() {
+        assert_that!(action("run alacritty -e htop")).is_equal_to(Action::Run {
+            env: vec![],
+            command: "alacritty -e htop".to_string(),
+            shell: false,
+        });
+    }
+
+    #[test]
+    fn should_quote_run_arguments() {
+        let message = ["run", "notify-send", "a b"].map(String::from);
+
+        assert_that!(parse_message(&message).unwrap()).is_equal_to(Request::Run {
+            action: Action::Run {
+                env: vec![],
+                command: "notify-send 'a b'".to_string(),
+                shell: false,
+            },
+        });
+    }
+
+    #[test]
+    fn should_send_other_messages_as_commands() {
+        assert_that!(parse("focus parent; workspace 3").unwrap()).is_equal_to(Request::Command {
+            command: "focus parent; workspace 3".to_string(),
+        });
+    }
+}
@@ -0,0 +1,131 @@
+//! Events pushed to subscribed IPC clients.
+//!
+//! Handlers queue events on [`Wazemmes`], they are sent once the event loop is idle
+//! so no workspace or container is borrowed while they are serialized.
+
+use crate::ipc::protocol::{Event, WindowChange, WindowNode, WorkspaceChange};
+use crate::ipc::tree::window_node;
+use crate::shell::windows::toplevel::WindowWrap;
+use crate::state::{CallLoopData, Wazemmes};
+use smithay::desktop::Kind;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::IsAlive;
+use std::mem;
+
+#[derive(Debug, Default)]
+pub struct IpcEvents {
+    pending: Vec<Event>,
+    /// Windows reported with a `new` event and their last reported state,
+    /// used to notice title changes and windows closed by their client
+    windows: Vec<(WindowWrap, WindowNode)>,
+}
+
+impl Wazemmes {
+    pub fn ipc_event(&mut self, event: Event) {
+        self.ipc_events.pending.push(event);
+    }
+
+    pub fn ipc_window_event(&mut self, window: &WindowWrap, change: WindowChange) {
+        self.report_window(window, change, None);
+    }
+
+    pub fn ipc_fullscreen_event(&mut self, window: &WindowWrap, fullscreen: bool) {
+        self.report_window(window, WindowChange::Fullscreen, Some(fullscreen));
+    }
+
+    pub fn ipc_workspace_event(&mut self, change: WorkspaceChange, num: u8, old: Option<u8>) {
+        let output = match self.workspaces.get(&num) {
+            Some(workspace) => workspace.get().output.name(),
+            None => return,
+        };
+
+        self.ipc_event(Event::Workspace {
+            change,
+            num,
+            output,
+            old,
+        });
+    }
+
+    /// Report a title change if `surface` is a known window which title was updated
+    pub fn ipc_check_title(&mut self, surface: &WlSurface) {
+        let window = self
+            .ipc_events
+            .windows
+            .iter()
+            .find(|(window, _)| {
+                matches!(window.inner().toplevel(), Kind::Xdg(_)) && window.wl_surface() == *surface
+            });
+
+        if let Some((window, reported)) = window {
+            if window.xdg_surface_attributes().title != reported.title {
+                let window = window.clone();
+                self.ipc_window_event(&window, WindowChange::Title);
+            }
+        }
+    }
+
+    fn report_window(
+        &mut self,
+        window: &WindowWrap,
+        change: WindowChange,
+        fullscreen: Option<bool>,
+    ) {
+        let tracked = self
+            .ipc_events
+            .windows
+            .iter()
+            .position(|(tracked, _)| tracked.id() == window.id());
+
+        // Workspaces may be borrowed by the caller, the fullscreen state is tracked here instead
+        let fullscreen = fullscreen
+            .or_else(|| tracked.map(|index| self.ipc_events.windows[index].1.fullscreen))
+            .unwrap_or(false);
+        let focused = self.focus_history.iter().next() == Some(&window.id());
+        let node = window_node(window, focused, fullscreen);
+
+        match tracked {
+            Some(index) => self.ipc_events.windows[index].1 = node.clone(),
+            None => self.ipc_events.windows.push((window.clone(), node.clone())),
+        }
+
+        self.ipc_event(Event::Window { change, window: node });
+    }
+
+    /// Report windows destroyed since the last call, with their last known state
+    fn ipc_check_closed(&mut self) {
+        let (alive, closed): (Vec<_>, Vec<_>) = mem::take(&mut self.ipc_events.windows)
+            .into_iter()
+            .partition(|(window, _)| window.inner().alive());
+
+        self.ipc_events.windows = alive;
+        for (_, window) in closed {
+            self.ipc_event(Event::Window {
+                change: WindowChange::Close,
+                window,
+            });
+        }
+    }
+}
+
+impl CallLoopData {
+    /// Send the queued events to their subscribers, called once per event loop iteration
+    pub fn dispatch_ipc_events(&mut self) {
+        self.state.ipc_check_closed();
+        let events = mem::take(&mut self.state.ipc_events.pending);
+
+        for event in events {
+            let i3_event = match &self.ipc {
+                Some(server) if server.has_i3_subscriber(event.event_type()) => {
+                    self.i3_event(&event)
+                }
+                Some(_) => None,
+                None => return,
+            };
+
+            if let Some(server) = &mut self.ipc {
+                server.broadcast(&event, i3_event, &self.state._loop_handle);
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Compatibility with the i3 and sway IPC protocol, so bars and scripts written
+//! for them work unchanged.
+//!
+//! A message is the `i3-ipc` magic string, the payload length and the message type
+//! as native endian `u32`, then a JSON payload. Events have the highest bit of their type set.
+
+use crate::config::{command, DEFAULT_MODE};
+use crate::ipc::protocol::{Event, EventType, Reply, Request, WindowChange, WorkspaceChange};
+use crate::state::CallLoopData;
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+
+pub mod tree;
+
+pub const MAGIC: &[u8; 6] = b"i3-ipc";
+const HEADER_SIZE: usize = MAGIC.len() + 8;
+
+pub const RUN_COMMAND: u32 = 0;
+pub const GET_WORKSPACES: u32 = 1;
+pub const SUBSCRIBE: u32 = 2;
+pub const GET_OUTPUTS: u32 = 3;
+pub const GET_TREE: u32 = 4;
+pub const GET_MARKS: u32 = 5;
+pub const GET_BAR_CONFIG: u32 = 6;
+pub const GET_VERSION: u32 = 7;
+pub const GET_BINDING_MODES: u32 = 8;
+pub const GET_CONFIG: u32 = 9;
+pub const SEND_TICK: u32 = 10;
+pub const SYNC: u32 = 11;
+pub const GET_BINDING_STATE: u32 = 12;
+pub const GET_INPUTS: u32 = 100;
+pub const GET_SEATS: u32 = 101;
+
+pub const EVENT_WORKSPACE: u32 = 0x8000_0000;
+pub const EVENT_OUTPUT: u32 = 0x8000_0001;
+pub const EVENT_MODE: u32 = 0x8000_0002;
+pub const EVENT_WINDOW: u32 = 0x8000_0003;
+pub const EVENT_BINDING: u32 = 0x8000_0005;
+pub const EVENT_TICK: u32 = 0x8000_0007;
+
+pub fn encode(kind: u32, payload: &Value) -> serde_json::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(payload)?;
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    bytes.extend_from_slice(&kind.to_ne_bytes());
+    bytes.extend(payload);
+    Ok(bytes)
+}
+
+/// Take the next complete message type and payload out of `buffer`,
+/// `Ok(None)` if more bytes are needed
+pub fn decode(buffer: &mut Vec<u8>) -> io::Result<Option<(u32, Vec<u8>)>> {
+    if buffer.len() < HEADER_SIZE {
+        return Ok(None);
+    }
+
+    if &buffer[..MAGIC.len()] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing i3-ipc magic string",
+        ));
+    }
+
+    let word = |offset: usize| {
+        u32::from_ne_bytes([
+            buffer[offset],
+            buffer[offset + 1],
+            buffer[offset + 2],
+            buffer[offset + 3],
+        ])
+    };
+
+    let len = word(MAGIC.len()) as usize;
+    let kind = word(MAGIC.len() + 4);
+    if len > crate::ipc::protocol::MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is too big", len),
+        ));
+    }
+
+    if buffer.len() < HEADER_SIZE + len {
+        return Ok(None);
+    }
+
+    let payload = buffer[HEADER_SIZE..HEADER_SIZE + len].to_vec();
+    buffer.drain(..HEADER_SIZE + len);
+    Ok(Some((kind, payload)))
+}
+
+/// Event types matching i3 subscription names, unsupported names are ignored.
+/// A config reload is a `workspace` event for i3
+pub fn event_types(names: &[String]) -> Vec<EventType> {
+    names
+        .iter()
+        .flat_map(|name| match name.as_str() {
+            "workspace" => vec![EventType::Workspace, EventType::Config],
+            "window" => vec![EventType::Window],
+            "output" => vec![EventType::Output],
+            "mode" => vec![EventType::Mode],
+            "binding" => vec![EventType::Binding],
+            _ => vec![],
+        })
+        .collect()
+}
+
+impl CallLoopData {
+    pub fn handle_i3_message(&mut self, kind: u32, payload: &[u8]) -> Value {
+        match kind {
+            RUN_COMMAND => self.run_i3_command(&String::from_utf8_lossy(payload)),
+            GET_WORKSPACES => tree::workspaces(&self.state.ipc_tree()),
+            GET_OUTPUTS => tree::outputs(&self.ipc_outputs(), &self.state.ipc_tree()),
+            GET_TREE => tree::root(&self.state.ipc_tree(), &self.ipc_outputs()),
+            GET_MARKS | GET_BAR_CONFIG | GET_SEATS => json!([]),
+            GET_VERSION => json!({
+                "major": env!("CARGO_PKG_VERSION_MAJOR").parse::<u32>().unwrap_or(0),
+                "minor": env!("CARGO_PKG_VERSION_MINOR").parse::<u32>().unwrap_or(0),
+                "patch": env!("CARGO_PKG_VERSION_PATCH").parse::<u32>().unwrap_or(0),
+                "human_readable": format!("wazemmes {}", env!("CARGO_PKG_VERSION")),
+                "loaded_config_file_name": self.config.sources.first(),
+            }),
+            GET_BINDING_MODES => {
+                let mut modes = vec![DEFAULT_MODE.to_string()];
+                modes.extend(self.config.modes.keys().cloned());
+                json!(modes)
+            }
+            GET_BINDING_STATE => json!({ "name": self.state.binding_mode }),
+            GET_CONFIG => {
+                let config = self
+                    .config
+                    .sources
+                    .first()
+                    .and_then(|path| fs::read_to_string(path).ok())
+                    .unwrap_or_default();
+                json!({ "config": config })
+            }
+            SEND_TICK => json!({ "success": true }),
+            SYNC => json!({ "success": false }),
+            GET_INPUTS => tree::inputs(&self.ipc_inputs()),
+            kind => json!({
+                "success": false,
+                "error": format!("Unsupported message type {}", kind),
+            }),
+        }
+    }
+
+    /// Run each command of the list, i3 replies with one result per command
+    /// and a single one when the list cannot be parsed
+    fn run_i3_command(&mut self, commands: &str) -> Value {
+        let commands = match command::parse_list(commands) {
+            Ok(commands) => commands,
+            Err(err) => {
+                let error = err.show(commands);
+                return json!([{ "success": false, "parse_error": true, "error": error }]);
+            }
+        };
+
+        let mut results = vec![];
+        for actions in commands {
+            let mut result = json!({ "success": true });
+            for action in actions {
+                if let Reply::Error { message } = self.handle_ipc_request(Request::Run { action }) {
+                    result = json!({ "success": false, "error": message });
+                    break;
+                }
+            }
+
+            results.push(result);
+        }
+
+        json!(results)
+    }
+
+    /// The event as sent to i3 clients, with the workspaces it mentions read from the tree
+    pub fn i3_event(&self, event: &Event) -> Option<(u32, Value)> {
+        let workspace = |num: u8| {
+            self.state
+                .ipc_tree()
+                .iter()
+                .find(|workspace| workspace.num == num)
+                .map(tree::workspace)
+        };
+
+        let event = match event {
+            Event::Window { change, window } => {
+                let change = match change {
+                    WindowChange::New => "new",
+                    WindowChange::Close => "close",
+                    WindowChange::Focus => "focus",
+                    WindowChange::Title => "title",
+                    WindowChange::Floating => "floating",
+                    WindowChange::Fullscreen => "fullscreen_mode",
+                };
+
+                (
+                    EVENT_WINDOW,
+                    json!({ "change": change, "container": tree::window(window) }),
+                )
+            }
+            Event::Workspace {
+                change, num, old, ..
+            } => {
+                let change = match change {
+                    WorkspaceChange::Focus => "focus",
+                    WorkspaceChange::Init => "init",
+                    WorkspaceChange::Empty => "empty",
+                };
+
+                (
+                    EVENT_WORKSPACE,
+                    json!({
+                        "change": change,
+                        "current": workspace(*num),
+                        "old": old.and_then(workspace),
+                    }),
+                )
+            }
+            Event::Output { .. } => (EVENT_OUTPUT, json!({ "change": "unspecified" })),
+            Event::Binding { action } => (
+                EVENT_BINDING,
+                json!({
+                    "change": "run",
+                    "binding": {
+                        "command": ron::to_string(action).ok()?,
+                        "event_state_mask": [],
+                        "input_code": 0,
+                        "symbol": null,
+                        "input_type": "keyboard",
+                    },
+                }),
+            ),
+            Event::Mode { mode } => (
+                EVENT_MODE,
+                json!({ "change": mode, "pango_markup": false }),
+            ),
+            Event::ConfigReloaded => (
+                EVENT_WORKSPACE,
+                json!({ "change": "reload", "current": null, "old": null }),
+            ),
+            // i3 has no chord bindings
+            Event::Chord { .. } => return None,
+        };
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ipc::i3::{decode, encode, event_types, GET_TREE, MAGIC};
+    use crate::ipc::protocol::EventType;
+    use serde_json::json;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn should_decode_encoded_message() {
+        let mut buffer = encode(GET_TREE, &json!("")).unwrap();
+
+        assert_that!(&buffer[..6]).is_equal_to(&MAGIC[..]);
+        assert_that!(decode(&mut buffer).unwrap()).is_equal_to(Some((GET_TREE, b"\"\"".to_vec())));
+        assert_that!(buffer).is_empty();
+    }
+
+    #[test]
+    fn should_decode_message_without_payload() {
+        let mut buffer = MAGIC.to_vec();
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        buffer.extend_from_slice(&GET_TREE.to_ne_bytes());
+
+        assert_that!(decode(&mut buffer).unwrap()).is_equal_to(Some((GET_TREE, vec![])));
+    }
+
+    #[test]
+    fn should_reject_missing_magic() {
+        let mut buffer = b"sway-ipc\0\0\0\0\0\0\0\0".to_vec();
+
+        assert_that!(decode(&mut buffer)).is_err();
+    }
+
+    #[test]
+    fn should_map_subscriptions() {
+        let names = vec![
+            "workspace".to_string(),
+            "window".to_string(),
+            "barconfig_update".to_string(),
+        ];
+
+        assert_that!(event_types(&names)).is_equal_to(vec![
+            EventType::Workspace,
+            EventType::Config,
+            EventType::Window,
+        ]);
+    }
+}
@@ -0,0 +1,392 @@
+//! The wazemmes tree in the i3 JSON schema: workspaces hold split containers and windows,
+//! floating windows are moved to the `floating_nodes` of their workspace
+
+use crate::ipc::protocol::{
+    ContainerNode, InputsInfo, ModeInfo, OutputInfo, Rect, TreeNode, WindowNode, WorkspaceNode,
+};
+use serde_json::{json, Value};
+
+/// Outputs and the root have no id in wazemmes, theirs are above every node id
+const ROOT_ID: u64 = 1 << 32;
+
+pub fn root(workspaces: &[WorkspaceNode], outputs: &[OutputInfo]) -> Value {
+    let nodes: Vec<Value> = outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| {
+            let workspaces: Vec<Value> = workspaces
+                .iter()
+                .filter(|workspace| workspace.output == output.name)
+                .map(workspace)
+                .collect();
+
+            json!({
+                "id": ROOT_ID + 1 + index as u64,
+                "type": "output",
+                "name": output.name,
+                "layout": "output",
+                "orientation": "none",
+                "rect": output.geometry.as_ref().map(rect).unwrap_or_else(empty_rect),
+                "focused": false,
+                "marks": [],
+                "nodes": workspaces,
+                "floating_nodes": [],
+                "focus": [],
+            })
+        })
+        .collect();
+
+    json!({
+        "id": ROOT_ID,
+        "type": "root",
+        "name": "root",
+        "layout": "splith",
+        "orientation": "horizontal",
+        "rect": outputs
+            .iter()
+            .filter_map(|output| output.geometry.as_ref())
+            .fold(empty_rect(), union),
+        "focused": false,
+        "marks": [],
+        "nodes": nodes,
+        "floating_nodes": [],
+        "focus": [],
+    })
+}
+
+pub fn workspace(workspace: &WorkspaceNode) -> Value {
+    let mut floating = vec![];
+    let root = container(&workspace.root, &mut floating);
+
+    json!({
+        "id": workspace.root.id,
+        "type": "workspace",
+        "name": workspace.num.to_string(),
+        "num": workspace.num,
+        "output": workspace.output,
+        "layout": root["layout"],
+        "orientation": root["orientation"],
+        "rect": root["rect"],
+        "focused": root["focused"],
+        "visible": workspace.focused,
+        "urgent": false,
+        "marks": [],
+        "nodes": root["nodes"],
+        "floating_nodes": floating,
+        "focus": [],
+    })
+}
+
+pub fn workspaces(workspaces: &[WorkspaceNode]) -> Value {
+    workspaces
+        .iter()
+        .map(|workspace| {
+            json!({
+                "id": workspace.root.id,
+                "num": workspace.num,
+                "name": workspace.num.to_string(),
+                "visible": workspace.focused,
+                "focused": workspace.focused,
+                "urgent": false,
+                "rect": rect(&workspace.root.geometry),
+                "output": workspace.output,
+            })
+        })
+        .collect()
+}
+
+pub fn outputs(outputs: &[OutputInfo], workspaces: &[WorkspaceNode]) -> Value {
+    outputs
+        .iter()
+        .map(|output| {
+            let current_workspace = workspaces
+                .iter()
+                .find(|workspace| workspace.output == output.name && workspace.focused)
+                .or_else(|| {
+                    workspaces
+                        .iter()
+                        .find(|workspace| workspace.output == output.name)
+                })
+                .map(|workspace| workspace.num.to_string());
+
+            json!({
+                "name": output.name,
+                "make": output.make,
+                "model": output.model,
+                "serial": output.serial,
+                "active": output.enabled,
+                "primary": false,
+                "rect": output.geometry.as_ref().map(rect).unwrap_or_else(empty_rect),
+                "current_workspace": current_workspace,
+                "scale": output.scale,
+                "transform": transform(&output.transform),
+                "current_mode": output.mode.as_ref().map(mode),
+                "modes": output.modes.iter().map(mode).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+/// Devices in the sway format, keyboards also list their layouts
+pub fn inputs(inputs: &InputsInfo) -> Value {
+    inputs
+        .devices
+        .iter()
+        .map(|device| {
+            let has_type = |device_type: &str| device.types.iter().any(|t| t == device_type);
+            let input_type = if has_type("touchpad") {
+                "touchpad"
+            } else if has_type("keyboard") {
+                "keyboard"
+            } else {
+                "pointer"
+            };
+
+            let mut value = json!({
+                "identifier": format!("0:0:{}", device.name.replace(' ', "_")),
+                "name": device.name,
+                "vendor": 0,
+                "product": 0,
+                "type": input_type,
+            });
+
+            if input_type == "keyboard" {
+                value["xkb_layout_names"] = json!(inputs.keyboard_layouts);
+                value["xkb_active_layout_index"] = json!(inputs.active_layout);
+                value["xkb_active_layout_name"] =
+                    json!(inputs.keyboard_layouts.get(inputs.active_layout));
+            }
+
+            value
+        })
+        .collect()
+}
+
+fn container(container: &ContainerNode, floating: &mut Vec<Value>) -> Value {
+    let mut nodes = vec![];
+    for child in &container.children {
+        match child {
+            TreeNode::Container(child) => nodes.push(self::container(child, floating)),
+            TreeNode::Window(child) if child.floating => {
+                let window = window(child);
+                floating.push(json!({
+                    "id": child.id,
+                    "type": "floating_con",
+                    "name": window["name"],
+                    "layout": "none",
+                    "orientation": "none",
+                    "rect": window["rect"],
+                    "focused": false,
+                    "marks": [],
+                    "nodes": [window],
+                    "floating_nodes": [],
+                    "focus": [child.id],
+                }));
+            }
+            TreeNode::Window(child) => nodes.push(window(child)),
+        }
+    }
+
+    let (layout, orientation) = match container.layout.as_str() {
+        "vertical" => ("splitv", "vertical"),
+        _ => ("splith", "horizontal"),
+    };
+
+    json!({
+        "id": container.id,
+        "type": "con",
+        "name": null,
+        "layout": layout,
+        "orientation": orientation,
+        "rect": rect(&container.geometry),
+        "focused": container.focused,
+        "marks": [],
+        "nodes": nodes,
+        "floating_nodes": [],
+        "focus": [],
+    })
+}
+
+pub fn window(window: &WindowNode) -> Value {
+    json!({
+        "id": window.id,
+        "type": "con",
+        "name": window.title,
+        "app_id": window.app_id,
+        "shell": if window.xwayland { "xwayland" } else { "xdg_shell" },
+        "layout": "none",
+        "orientation": "none",
+        "rect": rect(&window.geometry),
+        "window_rect": rect(&window.geometry),
+        "focused": window.focused,
+        "visible": true,
+        "urgent": false,
+        "border": "none",
+        "current_border_width": 0,
+        "fullscreen_mode": u8::from(window.fullscreen),
+        "floating": if window.floating { "user_on" } else { "user_off" },
+        "marks": [],
+        "nodes": [],
+        "floating_nodes": [],
+        "focus": [],
+    })
+}
+
+fn rect(rect: &Rect) -> Value {
+    json!({
+        "x": rect.x,
+        "y": rect.y,
+        "width": rect.width,
+        "height": rect.height,
+    })
+}
+
+fn empty_rect() -> Value {
+    rect(&Rect {
+        x: 0,
+        y: 0,
+        width: 0,
+        height: 0,
+    })
+}
+
+/// Bounding box of the outputs, computed on the JSON so it can start from an empty rect
+fn union(bounds: Value, geometry: &Rect) -> Value {
+    let field = |name: &str| bounds[name].as_i64().unwrap_or(0) as i32;
+    let (x, y) = (field("x"), field("y"));
+    let (right, bottom) = (x + field("width"), y + field("height"));
+    if right == x && bottom == y {
+        return rect(geometry);
+    }
+
+    let min_x = x.min(geometry.x);
+    let min_y = y.min(geometry.y);
+    rect(&Rect {
+        x: min_x,
+        y: min_y,
+        width: right.max(geometry.x + geometry.width) - min_x,
+        height: bottom.max(geometry.y + geometry.height) - min_y,
+    })
+}
+
+fn mode(mode: &ModeInfo) -> Value {
+    json!({
+        "width": mode.width,
+        "height": mode.height,
+        "refresh": mode.refresh,
+    })
+}
+
+/// smithay transform names to the sway ones
+fn transform(transform: &str) -> &'static str {
+    match transform {
+        "_90" => "90",
+        "_180" => "180",
+        "_270" => "270",
+        "Flipped" => "flipped",
+        "Flipped90" => "flipped-90",
+        "Flipped180" => "flipped-180",
+        "Flipped270" => "flipped-270",
+        _ => "normal",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ipc::i3::tree::{root, workspace};
+    use crate::ipc::protocol::{ContainerNode, OutputInfo, Rect, TreeNode, WindowNode, WorkspaceNode};
+    use serde_json::json;
+    use speculoos::prelude::*;
+
+    fn rect(width: i32) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width,
+            height: 1080,
+        }
+    }
+
+    fn window(id: u32, floating: bool) -> TreeNode {
+        TreeNode::Window(WindowNode {
+            id,
+            app_id: Some("foot".to_string()),
+            title: Some("htop".to_string()),
+            geometry: rect(960),
+            floating,
+            fullscreen: false,
+            focused: id == 3,
+            xwayland: false,
+        })
+    }
+
+    fn workspace_node() -> WorkspaceNode {
+        WorkspaceNode {
+            num: 2,
+            output: "DP-1".to_string(),
+            focused: true,
+            fullscreen: None,
+            root: ContainerNode {
+                id: 1,
+                layout: "horizontal".to_string(),
+                geometry: rect(1920),
+                focused: false,
+                children: vec![
+                    window(3, false),
+                    TreeNode::Container(ContainerNode {
+                        id: 4,
+                        layout: "vertical".to_string(),
+                        geometry: rect(960),
+                        focused: false,
+                        children: vec![window(5, false)],
+                    }),
+                    window(6, true),
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn should_map_workspace_tree() {
+        let workspace = workspace(&workspace_node());
+
+        assert_that!(workspace["type"]).is_equal_to(json!("workspace"));
+        assert_that!(workspace["name"]).is_equal_to(json!("2"));
+        assert_that!(workspace["layout"]).is_equal_to(json!("splith"));
+        assert_that!(workspace["nodes"][0]["name"]).is_equal_to(json!("htop"));
+        assert_that!(workspace["nodes"][0]["focused"]).is_equal_to(json!(true));
+        assert_that!(workspace["nodes"][1]["layout"]).is_equal_to(json!("splitv"));
+        assert_that!(workspace["nodes"][1]["nodes"][0]["id"]).is_equal_to(json!(5));
+    }
+
+    #[test]
+    fn should_move_floating_windows_to_floating_nodes() {
+        let workspace = workspace(&workspace_node());
+
+        assert_that!(workspace["nodes"].as_array().unwrap()).has_length(2);
+        assert_that!(workspace["floating_nodes"][0]["type"]).is_equal_to(json!("floating_con"));
+        assert_that!(workspace["floating_nodes"][0]["nodes"][0]["id"]).is_equal_to(json!(6));
+    }
+
+    #[test]
+    fn should_put_workspaces_under_their_output() {
+        let output = OutputInfo {
+            name: "DP-1".to_string(),
+            make: "Dell".to_string(),
+            model: "U2720Q".to_string(),
+            serial: String::new(),
+            enabled: true,
+            geometry: Some(rect(1920)),
+            mode: None,
+            modes: vec![],
+            scale: 1.0,
+            transform: "Normal".to_string(),
+        };
+
+        let root = root(&[workspace_node()], &[output]);
+
+        assert_that!(root["rect"]["width"]).is_equal_to(json!(1920));
+        assert_that!(root["nodes"][0]["type"]).is_equal_to(json!("output"));
+        assert_that!(root["nodes"][0]["nodes"][0]["num"]).is_equal_to(json!(2));
+    }
+}
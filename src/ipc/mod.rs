@@ -0,0 +1,199 @@
+//! Control the compositor from other programs through a Unix socket,
+//! see [`protocol`] for the message format.
+
+use crate::backend::libinput::device_types;
+use crate::config::command;
+use crate::config::keybinding::Action;
+use crate::ipc::protocol::{DeviceInfo, InputsInfo, ModeInfo, OutputInfo, Reply, Request};
+use crate::ipc::server::IpcServer;
+use crate::state::output::OutputInfo as OutputBackendInfo;
+use crate::state::CallLoopData;
+use slog_scope::warn;
+use smithay::output::Mode;
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+pub mod client;
+pub mod events;
+pub mod i3;
+pub mod protocol;
+pub mod server;
+mod tree;
+
+/// Variable giving spawned programs the path of the IPC socket
+pub const SOCKET_VAR: &str = "WAZEMMES_SOCK";
+
+/// `$XDG_RUNTIME_DIR/wazemmes-$WAYLAND_DISPLAY.sock`
+pub fn socket_path(wayland_display: &OsStr) -> Option<PathBuf> {
+    let runtime_dir = env::var_os("XDG_RUNTIME_DIR")?;
+    let mut name = "wazemmes-".to_string();
+    name.push_str(&wayland_display.to_string_lossy());
+    name.push_str(".sock");
+    Some(PathBuf::from(runtime_dir).join(name))
+}
+
+/// Start the IPC server, the compositor keeps running without it if the socket cannot be bound
+pub fn init(data: &mut CallLoopData) {
+    let path = match socket_path(&data.state.socket_name) {
+        Some(path) => path,
+        None => {
+            warn!("XDG_RUNTIME_DIR is not set, IPC is disabled");
+            return;
+        }
+    };
+
+    let mut server = match IpcServer::bind(&path, &data.state._loop_handle) {
+        Ok(server) => server,
+        Err(err) => {
+            warn!("Failed to start the IPC server: {}", err);
+            return;
+        }
+    };
+
+    let i3_path = path.with_file_name(format!(
+        "wazemmes-i3-{}.sock",
+        data.state.socket_name.to_string_lossy()
+    ));
+    if let Err(err) = server.bind_i3(&i3_path, &data.state._loop_handle) {
+        warn!("Failed to start the i3 IPC server: {}", err);
+    }
+
+    data.ipc = Some(server);
+}
+
+impl CallLoopData {
+    pub fn handle_ipc_request(&mut self, request: Request) -> Reply {
+        match request {
+            // Spawn errors are reported to the client instead of being only logged
+            Request::Run {
+                action:
+                    Action::Run {
+                        command,
+                        env,
+                        shell,
+                    },
+            } => match self.run_command(&command, env, shell) {
+                Ok(pid) => Reply::Spawned { pid },
+                Err(err) => Reply::Error {
+                    message: err.to_string(),
+                },
+            },
+            Request::Run { .. } if self.state.session_lock.is_locked() => Reply::Error {
+                message: "The session is locked".to_string(),
+            },
+            Request::Run {
+                action: Action::DragMove | Action::DragResize,
+            } => Reply::Error {
+                message: "Drag actions are only available in mouse bindings".to_string(),
+            },
+            Request::Run {
+                action:
+                    Action::MoveContainerLeft
+                    | Action::MoveContainerRight
+                    | Action::MoveContainerUp
+                    | Action::MoveContainerDown,
+            } => Reply::Error {
+                message: "Moving a container is not supported".to_string(),
+            },
+            Request::Run { action } => {
+                self.run_action(action.into(), None);
+                Reply::Success
+            }
+            // Actions run until one fails, the reply of the last one is sent back
+            Request::Command { command } => match command::parse(&command) {
+                Ok(actions) => {
+                    let mut reply = Reply::Success;
+                    for action in actions {
+                        reply = self.handle_ipc_request(Request::Run { action });
+                        if let Reply::Error { .. } = reply {
+                            break;
+                        }
+                    }
+                    reply
+                }
+                Err(err) => Reply::Error {
+                    message: err.show(&command),
+                },
+            },
+            Request::GetTree => Reply::Tree {
+                workspaces: self.state.ipc_tree(),
+            },
+            Request::GetWorkspaces => Reply::Workspaces {
+                workspaces: self.state.ipc_workspaces(),
+            },
+            Request::GetOutputs => Reply::Outputs {
+                outputs: self.ipc_outputs(),
+            },
+            Request::GetInputs => Reply::Inputs(self.ipc_inputs()),
+            Request::GetConfig => match serde_json::to_value(&self.config) {
+                Ok(config) => Reply::Config { config },
+                Err(err) => Reply::Error {
+                    message: format!("Failed to serialize the config: {}", err),
+                },
+            },
+            Request::GetVersion => Reply::Version {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            // Subscriptions belong to a connection, they are recorded by the server
+            Request::Subscribe { .. } => Reply::Success,
+        }
+    }
+
+    fn ipc_outputs(&self) -> Vec<OutputInfo> {
+        self.state
+            .outputs
+            .iter()
+            .map(|output| {
+                let properties = output.physical_properties();
+                let geometry = self.state.space.output_geometry(output);
+                OutputInfo {
+                    name: output.name(),
+                    make: properties.make,
+                    model: properties.model,
+                    serial: OutputBackendInfo::for_output(output).serial.clone(),
+                    enabled: geometry.is_some(),
+                    geometry: geometry.map(Into::into),
+                    mode: output.current_mode().map(Into::into),
+                    modes: output.modes().into_iter().map(Into::into).collect(),
+                    scale: output.current_scale().fractional_scale(),
+                    transform: format!("{:?}", output.current_transform()),
+                }
+            })
+            .collect()
+    }
+
+    fn ipc_inputs(&self) -> InputsInfo {
+        let keyboard = &self.config.keyboard;
+        InputsInfo {
+            devices: self
+                .state
+                .input_devices
+                .iter()
+                .map(|device| DeviceInfo {
+                    name: device.name().to_string(),
+                    types: device_types(device)
+                        .iter()
+                        .map(|device_type| format!("{:?}", device_type).to_lowercase())
+                        .collect(),
+                })
+                .collect(),
+            binding_mode: self.state.binding_mode.clone(),
+            pending_chord: self.state.pending_chord.as_ref().map(|chord| chord.describe()),
+            keyboard_layouts: (0..keyboard.layout_count())
+                .map(|index| keyboard.layout_name(index))
+                .collect(),
+            active_layout: self.state.keyboard_layout,
+        }
+    }
+}
+
+impl From<Mode> for ModeInfo {
+    fn from(mode: Mode) -> Self {
+        ModeInfo {
+            width: mode.size.w,
+            height: mode.size.h,
+            refresh: mode.refresh,
+        }
+    }
+}
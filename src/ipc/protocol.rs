@@ -0,0 +1,413 @@
+//! Messages exchanged over the IPC socket.
+//!
+//! Every message is a little endian `u32` payload length followed by a JSON payload.
+//! Clients send a [`Request`] and receive exactly one [`Reply`] for it.
+//! Once subscribed, a client also receives [`Reply::Event`] messages between replies.
+
+use crate::config::keybinding::Action;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+/// Messages bigger than this are rejected, the client is disconnected
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+const HEADER_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Run an action, as if its key binding was pressed
+    Run { action: Action },
+    /// Run commands such as `focus parent; workspace 3`, see [`crate::config::command`]
+    Command { command: String },
+    GetTree,
+    GetWorkspaces,
+    GetOutputs,
+    GetInputs,
+    GetConfig,
+    GetVersion,
+    /// Receive the events of the given types until the connection is closed
+    Subscribe { events: Vec<EventType> },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    Success,
+    /// Process id of the program started by a `Run` action
+    Spawned { pid: u32 },
+    Error { message: String },
+    Tree { workspaces: Vec<WorkspaceNode> },
+    Workspaces { workspaces: Vec<WorkspaceInfo> },
+    Outputs { outputs: Vec<OutputInfo> },
+    Inputs(InputsInfo),
+    Config { config: serde_json::Value },
+    Version { version: String },
+    Event(Event),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Window,
+    Workspace,
+    Output,
+    Binding,
+    Mode,
+    Config,
+    Chord,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Window {
+        change: WindowChange,
+        window: WindowNode,
+    },
+    Workspace {
+        change: WorkspaceChange,
+        num: u8,
+        output: String,
+        /// Previously focused workspace, for `focus` changes
+        old: Option<u8>,
+    },
+    Output {
+        change: OutputChange,
+        name: String,
+    },
+    /// A key binding was triggered
+    Binding { action: Action },
+    Mode { mode: String },
+    ConfigReloaded,
+    /// Keys of a chord binding pressed so far, `None` once the chord completed,
+    /// was cancelled or timed out
+    Chord { pending: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowChange {
+    New,
+    Close,
+    Focus,
+    Title,
+    Floating,
+    Fullscreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkspaceChange {
+    Focus,
+    Init,
+    /// The workspace was left without any window
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputChange {
+    Added,
+    Removed,
+}
+
+impl FromStr for EventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "window" => Ok(EventType::Window),
+            "workspace" => Ok(EventType::Workspace),
+            "output" => Ok(EventType::Output),
+            "binding" => Ok(EventType::Binding),
+            "mode" => Ok(EventType::Mode),
+            "config" => Ok(EventType::Config),
+            "chord" => Ok(EventType::Chord),
+            other => Err(format!(
+                "unknown event type '{}', expected one of window, workspace, output, binding, mode, config, chord",
+                other
+            )),
+        }
+    }
+}
+
+impl Event {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::Window { .. } => EventType::Window,
+            Event::Workspace { .. } => EventType::Workspace,
+            Event::Output { .. } => EventType::Output,
+            Event::Binding { .. } => EventType::Binding,
+            Event::Mode { .. } => EventType::Mode,
+            Event::ConfigReloaded => EventType::Config,
+            Event::Chord { .. } => EventType::Chord,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WorkspaceNode {
+    pub num: u8,
+    pub output: String,
+    pub focused: bool,
+    /// Id of the fullscreen window or container, if any
+    pub fullscreen: Option<u32>,
+    pub root: ContainerNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TreeNode {
+    Container(ContainerNode),
+    Window(WindowNode),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContainerNode {
+    pub id: u32,
+    /// `horizontal` or `vertical`
+    pub layout: String,
+    pub geometry: Rect,
+    pub focused: bool,
+    pub children: Vec<TreeNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WindowNode {
+    pub id: u32,
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub geometry: Rect,
+    pub floating: bool,
+    pub fullscreen: bool,
+    pub focused: bool,
+    pub xwayland: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WorkspaceInfo {
+    pub num: u8,
+    pub output: String,
+    pub focused: bool,
+    pub windows: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OutputInfo {
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub serial: String,
+    pub enabled: bool,
+    pub geometry: Option<Rect>,
+    pub mode: Option<ModeInfo>,
+    pub modes: Vec<ModeInfo>,
+    pub scale: f64,
+    pub transform: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ModeInfo {
+    pub width: i32,
+    pub height: i32,
+    /// Refresh rate in mHz
+    pub refresh: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InputsInfo {
+    pub devices: Vec<DeviceInfo>,
+    pub binding_mode: String,
+    /// Keys of a chord binding pressed so far, for example `Alt+w`
+    pub pending_chord: Option<String>,
+    pub keyboard_layouts: Vec<String>,
+    pub active_layout: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeviceInfo {
+    pub name: String,
+    /// `touchpad`, `pointer` or `keyboard`
+    pub types: Vec<String>,
+}
+
+/// Serialize a message with its length header
+pub fn encode<T: Serialize>(message: &T) -> serde_json::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(message)?;
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend(payload);
+    Ok(bytes)
+}
+
+/// Take the next complete payload out of `buffer`, `Ok(None)` if more bytes are needed
+pub fn decode(buffer: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    if buffer.len() < HEADER_SIZE {
+        return Ok(None);
+    }
+
+    let len = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is too big", len),
+        ));
+    }
+
+    if buffer.len() < HEADER_SIZE + len {
+        return Ok(None);
+    }
+
+    let payload = buffer[HEADER_SIZE..HEADER_SIZE + len].to_vec();
+    buffer.drain(..HEADER_SIZE + len);
+    Ok(Some(payload))
+}
+
+/// Blocking write of a message, for clients
+pub fn write_message<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let bytes = encode(message)?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+/// Blocking read of a message, for clients
+pub fn read_message<T: for<'de> Deserialize<'de>>(stream: &mut impl Read) -> io::Result<T> {
+    let mut header = [0; HEADER_SIZE];
+    stream.read_exact(&mut header)?;
+
+    let len = u32::from_le_bytes(header) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes is too big", len),
+        ));
+    }
+
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload)?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::keybinding::Action;
+    use crate::ipc::protocol::{
+        decode, encode, read_message, Event, EventType, Reply, Request, WorkspaceChange,
+    };
+    use speculoos::prelude::*;
+
+    #[test]
+    fn should_decode_encoded_messages() {
+        let mut buffer = encode(&Request::GetTree).unwrap();
+        buffer.extend(encode(&Request::GetVersion).unwrap());
+
+        let first = decode(&mut buffer).unwrap().unwrap();
+        let second = decode(&mut buffer).unwrap().unwrap();
+
+        assert_that!(serde_json::from_slice::<Request>(&first).unwrap())
+            .is_equal_to(Request::GetTree);
+        assert_that!(serde_json::from_slice::<Request>(&second).unwrap())
+            .is_equal_to(Request::GetVersion);
+        assert_that!(buffer).is_empty();
+    }
+
+    #[test]
+    fn should_wait_for_complete_message() {
+        let bytes = encode(&Request::GetTree).unwrap();
+        let mut buffer = bytes[..bytes.len() - 1].to_vec();
+
+        assert_that!(decode(&mut buffer).unwrap()).is_none();
+
+        buffer.push(bytes[bytes.len() - 1]);
+        assert_that!(decode(&mut buffer).unwrap()).is_some();
+    }
+
+    #[test]
+    fn should_reject_oversized_message() {
+        let mut buffer = u32::MAX.to_le_bytes().to_vec();
+
+        assert_that!(decode(&mut buffer)).is_err();
+    }
+
+    #[test]
+    fn should_parse_run_request() {
+        let request: Request =
+            serde_json::from_str(r#"{"type": "run", "action": {"MoveToWorkspace": 3}}"#).unwrap();
+
+        assert_that!(request).is_equal_to(Request::Run {
+            action: Action::MoveToWorkspace(3),
+        });
+    }
+
+    #[test]
+    fn should_parse_subscribe_request() {
+        let request: Request =
+            serde_json::from_str(r#"{"type": "subscribe", "events": ["window", "workspace"]}"#)
+                .unwrap();
+
+        assert_that!(request).is_equal_to(Request::Subscribe {
+            events: vec![EventType::Window, EventType::Workspace],
+        });
+    }
+
+    #[test]
+    fn should_tag_events() {
+        let event = Reply::Event(Event::Workspace {
+            change: WorkspaceChange::Focus,
+            num: 2,
+            output: "DP-1".to_string(),
+            old: Some(1),
+        });
+
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_that!(json).is_equal_to(serde_json::json!({
+            "type": "event",
+            "event": "workspace",
+            "change": "focus",
+            "num": 2,
+            "output": "DP-1",
+            "old": 1,
+        }));
+        assert_that!(serde_json::from_value::<Reply>(json).unwrap()).is_equal_to(event);
+    }
+
+    #[test]
+    fn should_tag_chord_events() {
+        let json = serde_json::to_value(&Reply::Event(Event::Chord {
+            pending: Some("Alt+w".to_string()),
+        }))
+        .unwrap();
+
+        assert_that!(json).is_equal_to(serde_json::json!({
+            "type": "event",
+            "event": "chord",
+            "pending": "Alt+w",
+        }));
+        assert_that!("chord".parse::<EventType>()).is_equal_to(Ok(EventType::Chord));
+    }
+
+    #[test]
+    fn should_read_message_from_stream() {
+        let bytes = encode(&Reply::Error {
+            message: "oops".to_string(),
+        })
+        .unwrap();
+
+        let reply: Reply = read_message(&mut bytes.as_slice()).unwrap();
+
+        assert_that!(reply).is_equal_to(Reply::Error {
+            message: "oops".to_string(),
+        });
+    }
+}
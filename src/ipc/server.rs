@@ -0,0 +1,397 @@
+use crate::ipc::i3;
+use crate::ipc::protocol::{self, Event, EventType, Reply, Request, MAX_MESSAGE_SIZE};
+use crate::state::CallLoopData;
+use slog_scope::{debug, info, warn};
+use smithay::reexports::calloop::generic::Generic;
+use smithay::reexports::calloop::{
+    Interest, LoopHandle, Mode, PostAction, Readiness, RegistrationToken,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+/// The listening sockets and the connected clients
+#[derive(Debug)]
+pub struct IpcServer {
+    pub path: PathBuf,
+    /// Socket speaking the i3 IPC protocol, exported as `SWAYSOCK` and `I3SOCK`
+    pub i3_path: Option<PathBuf>,
+    clients: HashMap<u64, IpcClient>,
+    next_id: u64,
+}
+
+/// Wire format spoken by a socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// See [`protocol`]
+    Native,
+    /// See [`i3`]
+    I3,
+}
+
+#[derive(Debug)]
+struct IpcClient {
+    stream: UnixStream,
+    protocol: Protocol,
+    token: RegistrationToken,
+    subscriptions: HashSet<EventType>,
+    /// Bytes received but not yet forming a complete request
+    incoming: Vec<u8>,
+    /// Replies the socket was not ready to receive yet
+    outgoing: Vec<u8>,
+}
+
+impl IpcServer {
+    /// Bind the socket and insert it in the event loop, a stale socket left at `path` is replaced
+    pub fn bind(path: &Path, handle: &LoopHandle<'static, CallLoopData>) -> eyre::Result<Self> {
+        listen(path, Protocol::Native, handle)?;
+
+        Ok(IpcServer {
+            path: path.to_path_buf(),
+            i3_path: None,
+            clients: HashMap::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Bind the i3 compatible socket next to the native one
+    pub fn bind_i3(
+        &mut self,
+        path: &Path,
+        handle: &LoopHandle<'static, CallLoopData>,
+    ) -> eyre::Result<()> {
+        listen(path, Protocol::I3, handle)?;
+        self.i3_path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Whether an i3 client subscribed to this event type,
+    /// i3 events are only built when someone listens to them
+    pub fn has_i3_subscriber(&self, event_type: EventType) -> bool {
+        self.clients.values().any(|client| {
+            client.protocol == Protocol::I3 && client.subscriptions.contains(&event_type)
+        })
+    }
+
+    /// Send an event to its subscribers, clients failing to receive it are disconnected.
+    /// `i3_event` is the event translated for i3 clients, they are skipped without it
+    pub fn broadcast(
+        &mut self,
+        event: &Event,
+        i3_event: Option<(u32, serde_json::Value)>,
+        handle: &LoopHandle<'static, CallLoopData>,
+    ) {
+        let event_type = event.event_type();
+        let mut native = None;
+        let i3 = match i3_event {
+            Some((kind, payload)) => match i3::encode(kind, &payload) {
+                Ok(bytes) => Some(bytes),
+                Err(err) => {
+                    warn!("Failed to serialize i3 IPC event: {}", err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut failed = vec![];
+        for (id, client) in self.clients.iter_mut() {
+            if !client.subscriptions.contains(&event_type) {
+                continue;
+            }
+
+            let bytes = match client.protocol {
+                Protocol::Native => {
+                    if native.is_none() {
+                        native = match protocol::encode(&Reply::Event(event.clone())) {
+                            Ok(bytes) => Some(bytes),
+                            Err(err) => {
+                                warn!("Failed to serialize IPC event: {}", err);
+                                return;
+                            }
+                        };
+                    }
+
+                    native.as_ref()
+                }
+                Protocol::I3 => i3.as_ref(),
+            };
+
+            if let Some(bytes) = bytes {
+                if let Err(err) = client.send(bytes) {
+                    warn!("IPC client {}: {}", id, err);
+                    failed.push(*id);
+                }
+            }
+        }
+
+        for id in failed {
+            if let Some(client) = self.clients.remove(&id) {
+                handle.remove(client.token);
+            }
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+        if let Some(path) = &self.i3_path {
+            fs::remove_file(path).ok();
+        }
+    }
+}
+
+fn listen(
+    path: &Path,
+    protocol: Protocol,
+    handle: &LoopHandle<'static, CallLoopData>,
+) -> eyre::Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(eyre::eyre!("{} is already in use", path.display()));
+        }
+
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+
+    handle
+        .insert_source(
+            Generic::new(listener, Interest::READ, Mode::Level),
+            move |_, listener, data| {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => data.accept_ipc_client(stream, protocol),
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            warn!("Failed to accept IPC client: {}", err);
+                            break;
+                        }
+                    }
+                }
+
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|err| eyre::eyre!("Failed to insert the IPC socket: {}", err))?;
+
+    info!("IPC socket listening on {}", path.display());
+    Ok(())
+}
+
+impl IpcClient {
+    /// Read everything available, `Ok(false)` once the client hung up
+    fn receive(&mut self) -> io::Result<bool> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Ok(false),
+                Ok(len) => self.incoming.extend_from_slice(&buffer[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Queue a message and write as much as the socket accepts,
+    /// fails if the client stopped reading
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.outgoing.len() + bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "client is not reading its messages",
+            ));
+        }
+
+        self.outgoing.extend_from_slice(bytes);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.outgoing.drain(..len);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CallLoopData {
+    fn accept_ipc_client(&mut self, stream: UnixStream, protocol: Protocol) {
+        let server = match &mut self.ipc {
+            Some(server) => server,
+            None => return,
+        };
+
+        // The event source polls its own handle, the client keeps one for reading and writing
+        let source = match stream.set_nonblocking(true).and_then(|_| stream.try_clone()) {
+            Ok(source) => source,
+            Err(err) => {
+                warn!("Failed to set up IPC client: {}", err);
+                return;
+            }
+        };
+
+        let id = server.next_id;
+        server.next_id += 1;
+
+        let inserted = self.state._loop_handle.insert_source(
+            Generic::new(source, Interest::BOTH, Mode::Edge),
+            move |readiness, _, data| Ok(data.ipc_client_ready(id, readiness)),
+        );
+
+        let token = match inserted {
+            Ok(token) => token,
+            Err(err) => {
+                warn!("Failed to insert IPC client in the event loop: {}", err);
+                return;
+            }
+        };
+
+        debug!("IPC client {} connected ({:?})", id, protocol);
+        server.clients.insert(
+            id,
+            IpcClient {
+                stream,
+                protocol,
+                token,
+                subscriptions: HashSet::new(),
+                incoming: vec![],
+                outgoing: vec![],
+            },
+        );
+    }
+
+    fn ipc_client_ready(&mut self, id: u64, readiness: Readiness) -> PostAction {
+        match self.serve_ipc_client(id, readiness) {
+            Ok(true) => PostAction::Continue,
+            Ok(false) => {
+                debug!("IPC client {} disconnected", id);
+                self.drop_ipc_client(id)
+            }
+            Err(err) => {
+                warn!("IPC client {}: {}", id, err);
+                self.drop_ipc_client(id)
+            }
+        }
+    }
+
+    fn drop_ipc_client(&mut self, id: u64) -> PostAction {
+        if let Some(server) = &mut self.ipc {
+            server.clients.remove(&id);
+        }
+
+        PostAction::Remove
+    }
+
+    fn ipc_client(&mut self, id: u64) -> Option<&mut IpcClient> {
+        self.ipc.as_mut().and_then(|server| server.clients.get_mut(&id))
+    }
+
+    /// Answer every complete request, `Ok(false)` once the client is gone
+    fn serve_ipc_client(&mut self, id: u64, readiness: Readiness) -> io::Result<bool> {
+        let client = match self.ipc_client(id) {
+            Some(client) => client,
+            None => return Ok(false),
+        };
+
+        if readiness.writable {
+            client.flush()?;
+        }
+
+        let connected = !readiness.readable || client.receive()?;
+        let wire = client.protocol;
+        let mut messages = vec![];
+        match wire {
+            Protocol::Native => {
+                while let Some(payload) = protocol::decode(&mut client.incoming)? {
+                    messages.push((0, payload));
+                }
+            }
+            Protocol::I3 => {
+                while let Some(message) = i3::decode(&mut client.incoming)? {
+                    messages.push(message);
+                }
+            }
+        }
+
+        for (kind, payload) in messages {
+            let bytes = match wire {
+                Protocol::Native => self.serve_native_request(id, &payload)?,
+                Protocol::I3 => self.serve_i3_message(id, kind, &payload)?,
+            };
+
+            // The request might have closed the compositor or dropped the server
+            match self.ipc_client(id) {
+                Some(client) => client.send(&bytes)?,
+                None => return Ok(false),
+            }
+        }
+
+        Ok(connected)
+    }
+
+    fn serve_native_request(&mut self, id: u64, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let reply = match serde_json::from_slice::<Request>(payload) {
+            Ok(Request::Subscribe { events }) => {
+                debug!("IPC client {} subscribed to {:?}", id, events);
+                if let Some(client) = self.ipc_client(id) {
+                    client.subscriptions.extend(events);
+                }
+                Reply::Success
+            }
+            Ok(request) => {
+                debug!("IPC client {}: {:?}", id, request);
+                self.handle_ipc_request(request)
+            }
+            Err(err) => Reply::Error {
+                message: format!("Invalid request: {}", err),
+            },
+        };
+
+        Ok(protocol::encode(&reply)?)
+    }
+
+    fn serve_i3_message(&mut self, id: u64, kind: u32, payload: &[u8]) -> io::Result<Vec<u8>> {
+        if kind != i3::SUBSCRIBE {
+            let reply = self.handle_i3_message(kind, payload);
+            return Ok(i3::encode(kind, &reply)?);
+        }
+
+        let names: Vec<String> = match serde_json::from_slice(payload) {
+            Ok(names) => names,
+            Err(err) => {
+                let reply = serde_json::json!({ "success": false, "error": err.to_string() });
+                return Ok(i3::encode(kind, &reply)?);
+            }
+        };
+
+        debug!("i3 IPC client {} subscribed to {:?}", id, names);
+        if let Some(client) = self.ipc_client(id) {
+            client.subscriptions.extend(i3::event_types(&names));
+        }
+
+        let mut bytes = i3::encode(kind, &serde_json::json!({ "success": true }))?;
+        // i3 greets tick subscribers with a first tick
+        if names.iter().any(|name| name == "tick") {
+            let tick = serde_json::json!({ "first": true, "payload": "" });
+            bytes.extend(i3::encode(i3::EVENT_TICK, &tick)?);
+        }
+
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,135 @@
+//! Snapshot of the workspace tree sent to IPC clients
+
+use crate::ipc::protocol::{ContainerNode, Rect, TreeNode, WindowNode, WorkspaceInfo, WorkspaceNode};
+use crate::shell::container::{ContainerLayout, ContainerRef};
+use crate::shell::node::Node;
+use crate::shell::windows::toplevel::WindowWrap;
+use crate::state::Wazemmes;
+use smithay::desktop::Kind;
+use smithay::utils::{Logical, Rectangle};
+
+/// What has the focus in the workspace being described
+struct Focus {
+    container: Option<u32>,
+    window: Option<u32>,
+    fullscreen: Option<u32>,
+}
+
+impl Wazemmes {
+    /// Every workspace with its containers and windows, sorted by number
+    pub fn ipc_tree(&self) -> Vec<WorkspaceNode> {
+        let mut nums: Vec<u8> = self.workspaces.keys().copied().collect();
+        nums.sort_unstable();
+
+        nums.into_iter()
+            .map(|num| {
+                let workspace = self.workspaces[&num].get();
+                let focused = num == self.current_workspace;
+                let fullscreen = workspace.fullscreen_layer.as_ref().map(Node::id);
+                let focus = if focused {
+                    let (container, window) = workspace.get_focus();
+                    let container = container.get().id;
+                    Focus {
+                        container: Some(container),
+                        window: window.map(|window| window.id()),
+                        fullscreen,
+                    }
+                } else {
+                    Focus {
+                        container: None,
+                        window: None,
+                        fullscreen,
+                    }
+                };
+
+                WorkspaceNode {
+                    num,
+                    output: workspace.output.name(),
+                    focused,
+                    fullscreen,
+                    root: container_node(&workspace.root(), &focus),
+                }
+            })
+            .collect()
+    }
+
+    pub fn ipc_workspaces(&self) -> Vec<WorkspaceInfo> {
+        let mut workspaces: Vec<WorkspaceInfo> = self
+            .workspaces
+            .iter()
+            .map(|(num, workspace)| {
+                let workspace = workspace.get();
+                WorkspaceInfo {
+                    num: *num,
+                    output: workspace.output.name(),
+                    focused: *num == self.current_workspace,
+                    windows: workspace.flatten_window().len(),
+                }
+            })
+            .collect();
+
+        workspaces.sort_by_key(|workspace| workspace.num);
+        workspaces
+    }
+}
+
+fn container_node(container: &ContainerRef, focus: &Focus) -> ContainerNode {
+    let container = container.get();
+    let children = container
+        .nodes
+        .iter_spine()
+        .map(|(_, node)| match node {
+            Node::Container(child) => TreeNode::Container(container_node(child, focus)),
+            Node::Window(window) => TreeNode::Window(window_node(
+                window,
+                focus.window == Some(window.id()),
+                focus.fullscreen == Some(window.id()),
+            )),
+        })
+        .collect();
+
+    let layout = match container.layout {
+        ContainerLayout::Horizontal => "horizontal",
+        ContainerLayout::Vertical => "vertical",
+    };
+
+    ContainerNode {
+        id: container.id,
+        layout: layout.to_string(),
+        geometry: Rectangle::from_loc_and_size(container.location, container.size).into(),
+        focused: focus.container == Some(container.id) && focus.window.is_none(),
+        children,
+    }
+}
+
+pub fn window_node(window: &WindowWrap, focused: bool, fullscreen: bool) -> WindowNode {
+    let (app_id, title, xwayland) = match window.inner().toplevel() {
+        Kind::Xdg(_) => {
+            let attributes = window.xdg_surface_attributes();
+            (attributes.app_id, attributes.title, false)
+        }
+        Kind::X11(_) => (None, None, true),
+    };
+
+    WindowNode {
+        id: window.id(),
+        app_id,
+        title,
+        geometry: window.geometry().into(),
+        floating: window.is_floating(),
+        fullscreen,
+        focused,
+        xwayland,
+    }
+}
+
+impl From<Rectangle<i32, Logical>> for Rect {
+    fn from(rect: Rectangle<i32, Logical>) -> Self {
+        Rect {
+            x: rect.loc.x,
+            y: rect.loc.y,
+            width: rect.size.w,
+            height: rect.size.h,
+        }
+    }
+}
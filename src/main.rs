@@ -3,12 +3,13 @@
 
 use crate::backend::BackendState;
 use crate::config::WazemmesConfig;
+use crate::process::ChildProcesses;
 use crate::resources::pointer::PointerIcon;
 use crate::shell::workspace::WorkspaceRef;
 use crate::state::{CallLoopData, Wazemmes};
 use clap::Parser;
 use slog::Drain;
-use slog_scope::error;
+use slog_scope::{error, warn};
 use smithay::desktop;
 use smithay::desktop::PopupManager;
 use smithay::input::SeatState;
@@ -21,6 +22,7 @@ use smithay::wayland::data_device::DataDeviceState;
 use smithay::wayland::dmabuf::DmabufState;
 use smithay::wayland::output::OutputManagerState;
 use smithay::wayland::primary_selection::PrimarySelectionState;
+use smithay::wayland::shell::wlr_layer::WlrLayerShellState;
 use smithay::wayland::shell::xdg::decoration::XdgDecorationState;
 use smithay::wayland::shell::xdg::XdgShellState;
 use smithay::wayland::shm::ShmState;
@@ -32,6 +34,7 @@ use std::time::Instant;
 #[cfg(feature = "xwayland")]
 use smithay::xwayland::{XWayland, XWaylandEvent};
 use crate::handlers::screencopy::ScreenCopyManagerState;
+use crate::handlers::session_lock::SessionLockManagerState;
 
 mod backend;
 pub mod border;
@@ -40,6 +43,8 @@ mod config;
 pub mod draw;
 mod handlers;
 mod inputs;
+mod ipc;
+mod process;
 mod resources;
 mod shell;
 pub mod state;
@@ -144,6 +149,48 @@ fn main() -> eyre::Result<()> {
 
     let opt = cli::WazemmesCli::parse();
 
+    if let Some(cli::Command::CheckConfig { path }) = &opt.command {
+        let path = path
+            .clone()
+            .or_else(|| opt.config.clone())
+            .or_else(WazemmesConfig::path);
+
+        let path = match path {
+            Some(path) => path,
+            None => {
+                eprintln!("No config file found");
+                std::process::exit(1);
+            }
+        };
+
+        let diagnostics = config::check::check_file(&path);
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+
+        let errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == config::check::Severity::Error)
+            .count();
+
+        if errors > 0 {
+            eprintln!("{} error(s) found in {}", errors, path.display());
+            std::process::exit(1);
+        }
+
+        println!("{} is valid", path.display());
+        return Ok(());
+    }
+
+    if let Some(cli::Command::Msg(args)) = &opt.command {
+        if let Err(err) = ipc::client::run(args) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let mut event_loop = EventLoop::<CallLoopData>::try_new()?;
     let mut display = Display::new()?;
     let socket_name = init_wayland_listener(&mut display, &mut event_loop, slog_scope::logger());
@@ -158,7 +205,9 @@ fn main() -> eyre::Result<()> {
     let mut seat_state = SeatState::<Wazemmes>::new();
     let data_device_state = DataDeviceState::new::<Wazemmes, _>(&dh, slog_scope::logger());
     let xdg_decoration_state = XdgDecorationState::new::<Wazemmes, _>(&dh, slog_scope::logger());
+    let layer_shell_state = WlrLayerShellState::new::<Wazemmes, _>(&dh, slog_scope::logger());
     let screen_copy_manager_state = ScreenCopyManagerState::new(&dh);
+    let session_lock_manager_state = SessionLockManagerState::new(&dh);
 
     let dmabuf_state = DmabufState::new();
 
@@ -166,7 +215,16 @@ fn main() -> eyre::Result<()> {
 
     seat.add_pointer();
 
-    seat.add_keyboard(Default::default(), 200, 200)?;
+    let config = WazemmesConfig::get(opt.config.as_deref())?;
+    let keyboard = &config.keyboard;
+    if let Err(err) = seat.add_keyboard(
+        keyboard.xkb_config(0),
+        keyboard.repeat_delay,
+        keyboard.repeat_rate,
+    ) {
+        warn!("Invalid keyboard config, using the default keymap: {:?}", err);
+        seat.add_keyboard(Default::default(), keyboard.repeat_delay, keyboard.repeat_rate)?;
+    }
 
     #[cfg(feature = "xwayland")]
     let xwayland = init_xwayland_connection(&event_loop.handle(), &display.handle());
@@ -182,15 +240,21 @@ fn main() -> eyre::Result<()> {
         compositor_state,
         xdg_shell_state,
         xdg_decoration_state,
+        layer_shell_state,
         primary_selection_state,
         shm_state,
         _output_manager_state: output_manager_state,
         screen_copy_manager_state: screen_copy_manager_state,
+        session_lock_manager_state,
         seat_state,
         data_device_state,
         dmabuf_state,
         pointer_icon,
         backend: BackendState::default(),
+        outputs: vec![],
+        input_devices: vec![],
+        ipc_events: Default::default(),
+        children: ChildProcesses::default(),
         socket_name,
 
         #[cfg(feature = "xwayland")]
@@ -202,29 +266,50 @@ fn main() -> eyre::Result<()> {
         workspaces: Default::default(),
         current_workspace: 0,
         next_layout: None,
-        mod_pressed: false,
+        workspace_transition: None,
+        binding_mode: config::DEFAULT_MODE.to_string(),
+        keyboard_layout: 0,
+        pending_chord: None,
+        chord_timer: None,
+        pending_release: None,
+        intercepted_button: None,
+        scroll_accumulator: (0.0, 0.0),
+        modifiers: Default::default(),
+        focus_history: Default::default(),
+        window_switcher: None,
+        overview: None,
+        layer_focus: None,
+        session_lock: Default::default(),
     };
 
-    let config = WazemmesConfig::get()?;
     let mut data = CallLoopData {
         state,
         config,
+        config_path: opt.config.clone(),
         display,
+        ipc: None,
     };
 
+    ipc::init(&mut data);
+
     backend::init(
         &mut event_loop,
         &data.display.handle(),
         &mut data,
         opt.backend,
+        &opt.headless_outputs,
     );
 
+    config::reload::watch_config(&mut event_loop, &data)?;
+
     #[cfg(feature = "xwayland")]
     data.state.xwayland.start(event_loop.handle())?;
 
     event_loop.run(None, &mut data, |data| {
         data.state.space.refresh(&data.display.handle());
         data.state.popups.cleanup();
+        data.state.children.reap();
+        data.dispatch_ipc_events();
         data.display.flush_clients().unwrap();
     })?;
 
@@ -0,0 +1,369 @@
+use slog_scope::{debug, warn};
+use smithay::nix::libc;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::time::Instant;
+
+/// Maximum number of parent processes walked to find the program launching a window
+const MAX_ANCESTORS: usize = 16;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SpawnError {
+    #[error("Empty command")]
+    Empty,
+    #[error("Unterminated {0} quote in '{1}'")]
+    UnterminatedQuote(char, String),
+    #[error("Trailing backslash in '{0}'")]
+    TrailingBackslash(String),
+    #[error("Failed to open log file {0}: {1}")]
+    Log(PathBuf, io::Error),
+    #[error("Failed to spawn '{0}': {1}")]
+    Spawn(String, io::Error),
+}
+
+/// A program started by the compositor
+#[derive(Debug)]
+pub struct SpawnedChild {
+    pub pid: u32,
+    pub command: String,
+    pub started: Instant,
+    child: Child,
+}
+
+/// Programs started by the compositor, they are reaped once they exit
+#[derive(Debug, Default)]
+pub struct ChildProcesses {
+    children: Vec<SpawnedChild>,
+}
+
+/// How a command is started
+#[derive(Debug, Default)]
+pub struct SpawnOptions<'a> {
+    /// Run the command with `sh -c` instead of splitting it ourselves
+    pub shell: bool,
+    /// Directory receiving the stdout and stderr of the child, discarded when unset
+    pub log_dir: Option<&'a Path>,
+    /// Pipe stdout to the compositor instead, see [`ChildProcesses::take_stdout`]
+    pub capture_stdout: bool,
+}
+
+impl ChildProcesses {
+    pub fn spawn(
+        &mut self,
+        command: &str,
+        env: impl IntoIterator<Item = (String, String)>,
+        options: SpawnOptions,
+    ) -> Result<u32, SpawnError> {
+        let args = split_command(command)?;
+        if args.is_empty() {
+            return Err(SpawnError::Empty);
+        }
+
+        let mut process = if options.shell {
+            let mut process = Command::new("sh");
+            process.arg("-c").arg(command);
+            process
+        } else {
+            let mut process = Command::new(&args[0]);
+            process.args(&args[1..]);
+            process
+        };
+
+        process.envs(env);
+        process.stdin(Stdio::null());
+
+        match options.log_dir {
+            Some(dir) => {
+                let log = open_log(dir, &args[0])?;
+                let err = log
+                    .try_clone()
+                    .map_err(|err| SpawnError::Log(dir.to_path_buf(), err))?;
+                process.stdout(log);
+                process.stderr(err);
+            }
+            None => {
+                process.stdout(Stdio::null());
+                process.stderr(Stdio::null());
+            }
+        }
+
+        if options.capture_stdout {
+            process.stdout(Stdio::piped());
+        }
+
+        // Start a new session so the child does not receive signals sent to the compositor
+        unsafe {
+            process.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(())
+            });
+        }
+
+        let child = process
+            .spawn()
+            .map_err(|err| SpawnError::Spawn(command.to_string(), err))?;
+
+        let pid = child.id();
+        debug!("Spawned '{}' with pid {}", command, pid);
+        self.children.push(SpawnedChild {
+            pid,
+            command: command.to_string(),
+            started: Instant::now(),
+            child,
+        });
+
+        Ok(pid)
+    }
+
+    /// Collect the exit status of terminated children
+    pub fn reap(&mut self) {
+        self.children.retain_mut(|spawned| match spawned.child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) if status.success() => {
+                debug!("'{}' ({}) exited", spawned.command, spawned.pid);
+                false
+            }
+            Ok(Some(status)) => {
+                warn!("'{}' ({}) {}", spawned.command, spawned.pid, status);
+                false
+            }
+            Err(err) => {
+                warn!("Failed to wait for '{}' ({}): {}", spawned.command, spawned.pid, err);
+                false
+            }
+        });
+    }
+
+    /// The read end of a child stdout spawned with `capture_stdout`, it can be taken once
+    pub fn take_stdout(&mut self, pid: u32) -> Option<ChildStdout> {
+        self.children
+            .iter_mut()
+            .find(|spawned| spawned.pid == pid)
+            .and_then(|spawned| spawned.child.stdout.take())
+    }
+
+    pub fn get(&self, pid: u32) -> Option<&SpawnedChild> {
+        self.children.iter().find(|spawned| spawned.pid == pid)
+    }
+
+    /// The spawned child running `pid` or one of its ancestors,
+    /// so windows opened by a shell script are tied to the script's command
+    pub fn launcher_of(&self, pid: u32) -> Option<&SpawnedChild> {
+        let mut pid = pid;
+        for _ in 0..MAX_ANCESTORS {
+            if let Some(spawned) = self.get(pid) {
+                return Some(spawned);
+            }
+
+            match parent_pid(pid) {
+                Some(parent) if parent > 1 => pid = parent,
+                _ => return None,
+            }
+        }
+
+        None
+    }
+}
+
+fn open_log(dir: &Path, program: &str) -> Result<File, SpawnError> {
+    let dir = expand_home(dir);
+    let name = Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| program.to_string());
+    let path = dir.join(format!("{}.log", name));
+
+    fs::create_dir_all(&dir)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .map_err(|err| SpawnError::Log(path, err))
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The executable name is between parentheses and may contain spaces,
+    // the state then the parent pid follow it
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Replace a leading `~` with the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(relative), Some(home)) => home.join(relative),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Split a command into arguments the way a POSIX shell would, without expanding
+/// variables: quotes group words, backslashes escape the next character and
+/// a leading `~` is replaced with the home directory
+pub fn split_command(command: &str) -> Result<Vec<String>, SpawnError> {
+    let mut args = vec![];
+    let mut current = String::new();
+    // Distinguishes an empty quoted argument from no argument at all
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => {
+                            return Err(SpawnError::UnterminatedQuote('\'', command.to_string()))
+                        }
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => {
+                                return Err(SpawnError::UnterminatedQuote(
+                                    '"',
+                                    command.to_string(),
+                                ))
+                            }
+                        },
+                        Some(c) => current.push(c),
+                        None => {
+                            return Err(SpawnError::UnterminatedQuote('"', command.to_string()))
+                        }
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => return Err(SpawnError::TrailingBackslash(command.to_string())),
+                }
+            }
+            '~' if !in_word && matches!(chars.peek(), None | Some('/')) => {
+                in_word = true;
+                match dirs::home_dir() {
+                    Some(home) => current.push_str(&home.to_string_lossy()),
+                    None => current.push('~'),
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        args.push(current);
+    }
+
+    Ok(args)
+}
+
+/// Quote an argument so [`split_command`] gives it back unchanged
+pub fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::process::{quote_arg, split_command, SpawnError};
+    use speculoos::prelude::*;
+
+    fn split(command: &str) -> Vec<String> {
+        split_command(command).unwrap()
+    }
+
+    #[test]
+    fn should_split_on_whitespace() {
+        assert_that!(split("alacritty  -e   htop")).is_equal_to(vec![
+            "alacritty".to_string(),
+            "-e".to_string(),
+            "htop".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn should_group_quoted_words() {
+        assert_that!(split(r#"notify-send "Hello world" 'it''s' """#)).is_equal_to(vec![
+            "notify-send".to_string(),
+            "Hello world".to_string(),
+            "its".to_string(),
+            "".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn should_handle_escapes() {
+        assert_that!(split(r#"echo a\ b "say \"hi\"" 'no\escape'"#)).is_equal_to(vec![
+            "echo".to_string(),
+            "a b".to_string(),
+            r#"say "hi""#.to_string(),
+            r"no\escape".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn should_expand_leading_tilde_only() {
+        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+
+        assert_that!(split("~/bin/script a~b '~'")).is_equal_to(vec![
+            format!("{}/bin/script", home),
+            "a~b".to_string(),
+            "~".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn should_fail_on_unterminated_quote() {
+        let result = split_command("echo 'oops");
+
+        assert_that!(matches!(result, Err(SpawnError::UnterminatedQuote('\'', _)))).is_true();
+    }
+
+    #[test]
+    fn should_quote_arguments_split_back_unchanged() {
+        let args = ["notify-send", "a b", "it's", "", "~", "$HOME", "a\\b"];
+        let command: Vec<String> = args.iter().map(|arg| quote_arg(arg)).collect();
+
+        assert_that!(command[0]).is_equal_to("notify-send".to_string());
+        assert_that!(split(&command.join(" "))).is_equal_to(args.map(String::from).to_vec());
+    }
+
+    #[test]
+    fn should_return_no_argument_for_blank_command() {
+        assert_that!(split("   ")).is_empty();
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::animation::{AnimationConfig, Easing};
+use crate::shell::workspace::WorkspaceRef;
+use smithay::utils::{Logical, Point, Rectangle, Size};
+use std::time::{Duration, Instant};
+
+/// Timing of a running animation
+#[derive(Debug, Clone)]
+pub struct Animation {
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Animation {
+    pub fn new(config: &AnimationConfig) -> Self {
+        Self {
+            start: Instant::now(),
+            duration: Duration::from_millis(config.duration),
+            easing: config.easing,
+        }
+    }
+
+    /// Eased progress of the animation in `[0, 1]`
+    pub fn progress(&self, now: Instant) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        let elapsed = now.saturating_duration_since(self.start).as_secs_f64();
+        self.easing.apply(elapsed / self.duration.as_secs_f64())
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start) >= self.duration
+    }
+}
+
+/// Interpolate a window geometry between its previous and its next layout position.
+#[derive(Debug, Clone)]
+pub struct WindowAnimation {
+    pub from: Rectangle<i32, Logical>,
+    pub to: Rectangle<i32, Logical>,
+    pub activate: bool,
+    animation: Animation,
+}
+
+impl WindowAnimation {
+    pub fn new(
+        from: Rectangle<i32, Logical>,
+        to: Rectangle<i32, Logical>,
+        activate: bool,
+        config: &AnimationConfig,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            activate,
+            animation: Animation::new(config),
+        }
+    }
+
+    pub fn geometry(&self, now: Instant) -> Rectangle<i32, Logical> {
+        let progress = self.animation.progress(now);
+        let lerp = |from: i32, to: i32| from + ((to - from) as f64 * progress).round() as i32;
+
+        let loc: Point<i32, Logical> = (
+            lerp(self.from.loc.x, self.to.loc.x),
+            lerp(self.from.loc.y, self.to.loc.y),
+        )
+            .into();
+
+        let size: Size<i32, Logical> = (
+            lerp(self.from.size.w, self.to.size.w),
+            lerp(self.from.size.h, self.to.size.h),
+        )
+            .into();
+
+        Rectangle::from_loc_and_size(loc, size)
+    }
+
+    pub fn is_done(&self, now: Instant) -> bool {
+        self.animation.is_done(now)
+    }
+}
+
+/// Slide between the previously focused workspace and the current one
+#[derive(Debug)]
+pub struct WorkspaceTransition {
+    pub previous: WorkspaceRef,
+    /// 1 when the new workspace comes from the right, -1 when it comes from the left
+    pub direction: i32,
+    pub animation: Animation,
+}
+
+impl WorkspaceTransition {
+    pub fn new(previous: WorkspaceRef, direction: i32, config: &AnimationConfig) -> Self {
+        Self {
+            previous,
+            direction,
+            animation: Animation::new(config),
+        }
+    }
+
+    /// Horizontal offsets of the previous and the current workspace for the given output width
+    pub fn offsets(&self, width: i32, now: Instant) -> (i32, i32) {
+        let shift = (width as f64 * self.animation.progress(now)).round() as i32;
+        let previous = -self.direction * shift;
+        let current = self.direction * (width - shift);
+        (previous, current)
+    }
+}
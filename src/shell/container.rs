@@ -10,7 +10,7 @@ use crate::backend::xwayland::X11State;
 use smithay::output::Output;
 use smithay::wayland::shell::xdg::ToplevelSurface;
 
-use crate::config::CONFIG;
+use crate::config::animation::AnimationConfig;
 use crate::shell::node;
 use crate::shell::node::Node;
 
@@ -38,6 +38,17 @@ impl ContainerRef {
         self.inner.borrow_mut()
     }
 
+    /// Append nodes to this container, child containers are reparented
+    pub fn adopt(&self, nodes: Vec<(u32, Node)>) {
+        for (_, node) in &nodes {
+            if let Node::Container(child) = node {
+                child.get_mut().parent = Some(self.clone());
+            }
+        }
+
+        self.get_mut().nodes.extend(nodes);
+    }
+
     pub fn container_having_window(&self, id: u32) -> Option<ContainerRef> {
         let this = self.get();
 
@@ -77,6 +88,7 @@ pub struct Container {
     pub parent: Option<ContainerRef>,
     pub nodes: NodeMap,
     pub layout: ContainerLayout,
+    pub gaps: i32,
     #[cfg(feature = "xwayland")]
     pub xpopups: Vec<X11Popup>,
 }
@@ -133,6 +145,7 @@ impl Container {
                 parent: Some(parent),
                 nodes: NodeMap::default(),
                 layout,
+                gaps: self.gaps,
                 #[cfg(feature = "xwayland")]
                 xpopups: vec![],
             };
@@ -175,7 +188,7 @@ impl Container {
                     self.size
                 } else {
                     let len = len as i32;
-                    let gaps = CONFIG.gaps as i32;
+                    let gaps = self.gaps;
                     let total_gaps = gaps * (len - 1);
                     match self.layout {
                         ContainerLayout::Vertical => {
@@ -209,7 +222,7 @@ impl Container {
         if idx == 0 {
             self.location
         } else {
-            let gaps = CONFIG.gaps as i32;
+            let gaps = self.gaps;
             let pos = idx as i32;
 
             match self.layout {
@@ -250,7 +263,12 @@ impl Container {
 
     // Push a window to the tree and update the focus
     pub fn push_toplevel(&mut self, surface: ToplevelSurface) -> u32 {
-        let window = Node::Window(WindowWrap::from(surface));
+        self.push_window(WindowWrap::from(surface))
+    }
+
+    // Push an existing window after the focused one, used when moving windows between workspaces
+    pub fn push_window(&mut self, window: WindowWrap) -> u32 {
+        let window = Node::Window(window);
         match self.get_focused_window() {
             None => self.nodes.push(window),
             Some(focus) => self
@@ -267,17 +285,15 @@ impl Container {
 
     #[cfg(feature = "xwayland")]
     pub fn push_xwindow(&mut self, window: WindowWrap) -> u32 {
-        let window = Node::Window(window);
-        match self.get_focused_window() {
-            None => self.nodes.push(window),
-            Some(focus) => self
-                .nodes
-                .insert_after(focus.id(), window)
-                .expect("Should insert window"),
-        }
+        self.push_window(window)
     }
 
-    pub fn redraw(&self, space: &mut Space, x11_state: Option<&mut X11State>) {
+    pub fn redraw(
+        &self,
+        space: &mut Space,
+        x11_state: Option<&mut X11State>,
+        animation: &AnimationConfig,
+    ) {
         let x11_state = x11_state.unwrap();
         let focused_window_id = self.get_focused_window().map(|window| window.id());
 
@@ -285,11 +301,11 @@ impl Container {
             match node {
                 Node::Container(container) => {
                     let child = container.get();
-                    child.redraw(space, Some(x11_state));
+                    child.redraw(space, Some(x11_state), animation);
                 }
                 Node::Window(window) => {
                     let activate = Some(*id) == focused_window_id;
-                    window.map(space, Some(x11_state), activate)
+                    window.map_animated(space, Some(x11_state), activate, animation)
                 }
             }
         }
@@ -315,6 +331,14 @@ impl Container {
             let mut child = child.get_mut();
             if child.nodes.iter_windows().count() == 0 {
                 let children = child.nodes.drain_containers();
+
+                // The emptied child parent is this container
+                for (_, node) in &children {
+                    if let Node::Container(orphan) = node {
+                        orphan.get_mut().parent = child.parent.clone();
+                    }
+                }
+
                 orphans.extend_from_slice(children.as_slice());
             }
         }
@@ -329,7 +353,7 @@ impl Container {
     }
 
     pub fn set_fullscreen_loc_and_size(&mut self, output_geometry: Rectangle<i32, Logical>) {
-        let gaps = CONFIG.gaps as i32;
+        let gaps = self.gaps;
         self.location = (output_geometry.loc.x + gaps, output_geometry.loc.y + gaps).into();
         self.size = (
             output_geometry.size.w - 2 * gaps,
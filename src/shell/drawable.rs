@@ -16,6 +16,14 @@ pub struct Borders {
     pub bottom: Rectangle<i32, Logical>,
 }
 
+/// A plain colored rectangle drawn on top of the scene
+#[derive(Debug, Clone)]
+pub struct Quad {
+    pub geometry: Rectangle<i32, Logical>,
+    pub color: (f32, f32, f32),
+    pub z_index: u8,
+}
+
 impl Default for Borders {
     fn default() -> Self {
         Self {
@@ -28,12 +36,10 @@ impl Default for Borders {
     }
 }
 
-impl Border for WindowWrap {
-    fn make_borders(&self) -> Borders {
-        let window_size = self.size();
-        let window_loc = self.location();
-        let (x, y) = (window_loc.x, window_loc.y);
-        let (w, h) = (window_size.w, window_size.h);
+impl Borders {
+    pub fn for_window_geometry(geometry: Rectangle<i32, Logical>) -> Self {
+        let (x, y) = (geometry.loc.x, geometry.loc.y);
+        let (w, h) = (geometry.size.w, geometry.size.h);
 
         let left = {
             let topleft = (x - 2, y - 2);
@@ -69,6 +75,12 @@ impl Border for WindowWrap {
     }
 }
 
+impl Border for WindowWrap {
+    fn make_borders(&self) -> Borders {
+        Borders::for_window_geometry(self.geometry())
+    }
+}
+
 impl Border for Container {
     fn make_borders(&self) -> Borders {
         let h = self.size.h + 4;
@@ -0,0 +1,63 @@
+/// Window ids ordered from the most recently focused to the least recently focused
+#[derive(Debug, Default)]
+pub struct FocusHistory {
+    stack: Vec<u32>,
+}
+
+impl FocusHistory {
+    pub fn push(&mut self, window_id: u32) {
+        self.remove(window_id);
+        self.stack.insert(0, window_id);
+    }
+
+    pub fn remove(&mut self, window_id: u32) {
+        self.stack.retain(|id| *id != window_id);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &u32> {
+        self.stack.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::shell::focus_history::FocusHistory;
+    use speculoos::prelude::*;
+
+    fn ids(history: &FocusHistory) -> Vec<u32> {
+        history.iter().copied().collect()
+    }
+
+    #[test]
+    fn should_put_pushed_window_first() {
+        let mut history = FocusHistory::default();
+        history.push(1);
+        history.push(2);
+        history.push(3);
+
+        assert_that!(ids(&history)).is_equal_to(vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn should_move_focused_window_to_front_without_duplicate() {
+        let mut history = FocusHistory::default();
+        history.push(1);
+        history.push(2);
+        history.push(3);
+        history.push(1);
+
+        assert_that!(ids(&history)).is_equal_to(vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn should_remove_window() {
+        let mut history = FocusHistory::default();
+        history.push(1);
+        history.push(2);
+        history.push(3);
+        history.remove(2);
+        history.remove(4);
+
+        assert_that!(ids(&history)).is_equal_to(vec![3, 1]);
+    }
+}
@@ -0,0 +1,483 @@
+use crate::shell::container::ContainerRef;
+use crate::shell::node::Node;
+use crate::shell::workspace::Workspace;
+use smithay::utils::{Logical, Rectangle};
+use std::collections::HashSet;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LayoutError {
+    #[error("Node({0}) appears more than once in the spine")]
+    DuplicateSpineEntry(u32),
+    #[error("Node({0}) is in the spine but not in the node map")]
+    MissingItem(u32),
+    #[error("Node({0}) is in the node map but not in the spine")]
+    NotInSpine(u32),
+    #[error("Node({node}) is stored under id {key}")]
+    MismatchedId { key: u32, node: u32 },
+    #[error("Focus index {index} is out of bounds for a spine of {len} nodes")]
+    FocusOutOfBounds { index: usize, len: usize },
+    #[error("Root container({0}) should not have a parent")]
+    RootHasParent(u32),
+    #[error("Container({child}) has parent {found:?}, expected Container({expected})")]
+    WrongParent {
+        child: u32,
+        expected: u32,
+        found: Option<u32>,
+    },
+    #[error("Node({0}) is reachable more than once from the root container")]
+    DuplicateNode(u32),
+    #[error("Focused container({0}) is not part of the workspace tree")]
+    FocusNotInTree(u32),
+    #[error("Node({child}) at {geometry:?} overflows Container({parent}) at {bounds:?}")]
+    OutOfBounds {
+        child: u32,
+        parent: u32,
+        geometry: Rectangle<i32, Logical>,
+        bounds: Rectangle<i32, Logical>,
+    },
+    #[error("Node({0}) and Node({1}) overlap")]
+    Overlap(u32, u32),
+}
+
+impl Workspace {
+    /// Walk the whole workspace tree and check its structural invariants.
+    /// Tiled geometry is checked as well unless a fullscreen layer is shown.
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let root = self.root();
+
+        if root.get().parent.is_some() {
+            return Err(LayoutError::RootHasParent(root.get().id));
+        }
+
+        let mut visited = HashSet::new();
+        let check_geometry = self.fullscreen_layer.is_none();
+        validate_container(&root, &mut visited, check_geometry)?;
+
+        let focus = self.get_focus().0.get().id;
+        if !visited.contains(&focus) {
+            return Err(LayoutError::FocusNotInTree(focus));
+        }
+
+        Ok(())
+    }
+}
+
+/// Check a container and its children, `visited` collects every node id found in the tree.
+pub fn validate_container(
+    container: &ContainerRef,
+    visited: &mut HashSet<u32>,
+    check_geometry: bool,
+) -> Result<(), LayoutError> {
+    let this = container.get();
+
+    if !visited.insert(this.id) {
+        return Err(LayoutError::DuplicateNode(this.id));
+    }
+
+    this.nodes.validate()?;
+
+    let bounds = Rectangle::from_loc_and_size(this.location, this.size);
+    let mut tiled: Vec<(u32, Rectangle<i32, Logical>)> = vec![];
+
+    for (id, node) in this.nodes.iter_spine() {
+        let geometry = match node {
+            Node::Container(child) => {
+                let parent = child.get().parent.as_ref().map(|parent| parent.get().id);
+
+                if parent != Some(this.id) {
+                    return Err(LayoutError::WrongParent {
+                        child: *id,
+                        expected: this.id,
+                        found: parent,
+                    });
+                }
+
+                validate_container(child, visited, check_geometry)?;
+
+                let child = child.get();
+                Rectangle::from_loc_and_size(child.location, child.size)
+            }
+            Node::Window(window) => {
+                if !visited.insert(*id) {
+                    return Err(LayoutError::DuplicateNode(*id));
+                }
+
+                if window.is_floating() {
+                    continue;
+                }
+
+                window.geometry()
+            }
+        };
+
+        tiled.push((*id, geometry));
+    }
+
+    if !check_geometry {
+        return Ok(());
+    }
+
+    for (idx, (id, geometry)) in tiled.iter().enumerate() {
+        if !contains(bounds, *geometry) {
+            return Err(LayoutError::OutOfBounds {
+                child: *id,
+                parent: this.id,
+                geometry: *geometry,
+                bounds,
+            });
+        }
+
+        if let Some((other, _)) = tiled[idx + 1..]
+            .iter()
+            .find(|(_, other)| overlaps(*other, *geometry))
+        {
+            return Err(LayoutError::Overlap(*id, *other));
+        }
+    }
+
+    Ok(())
+}
+
+fn contains(bounds: Rectangle<i32, Logical>, rect: Rectangle<i32, Logical>) -> bool {
+    rect.loc.x >= bounds.loc.x
+        && rect.loc.y >= bounds.loc.y
+        && rect.loc.x + rect.size.w <= bounds.loc.x + bounds.size.w
+        && rect.loc.y + rect.size.h <= bounds.loc.y + bounds.size.h
+}
+
+// Adjacent rectangles sharing an edge do not overlap
+fn overlaps(a: Rectangle<i32, Logical>, b: Rectangle<i32, Logical>) -> bool {
+    a.loc.x < b.loc.x + b.size.w
+        && b.loc.x < a.loc.x + a.size.w
+        && a.loc.y < b.loc.y + b.size.h
+        && b.loc.y < a.loc.y + a.size.h
+}
+
+#[cfg(test)]
+mod test {
+    use crate::shell::container::{Container, ContainerLayout, ContainerRef};
+    use crate::shell::invariant::LayoutError;
+    use crate::shell::node;
+    use crate::shell::node::Node;
+    use crate::shell::nodemap::NodeMap;
+    use crate::shell::workspace::Workspace;
+    use proptest::prelude::*;
+    use smithay::output::{Output, PhysicalProperties, Subpixel};
+    use smithay::utils::{Logical, Rectangle};
+    use speculoos::prelude::*;
+
+    // Windows need a wayland client, the generated trees only hold containers
+    const MAX_CHILDREN: usize = 4;
+    const MAX_DEPTH: usize = 3;
+    const GAPS: u32 = 14;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Push(usize),
+        InsertBefore(usize, usize),
+        InsertAfter(usize, usize),
+        Remove(usize),
+        Focus(usize),
+        FocusParent,
+        Move(usize, usize),
+        Drain(usize),
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            any::<usize>().prop_map(Op::Push),
+            (any::<usize>(), any::<usize>()).prop_map(|(t, a)| Op::InsertBefore(t, a)),
+            (any::<usize>(), any::<usize>()).prop_map(|(t, a)| Op::InsertAfter(t, a)),
+            any::<usize>().prop_map(Op::Remove),
+            any::<usize>().prop_map(Op::Focus),
+            Just(Op::FocusParent),
+            (any::<usize>(), any::<usize>()).prop_map(|(n, d)| Op::Move(n, d)),
+            any::<usize>().prop_map(Op::Drain),
+        ]
+    }
+
+    fn output_geometry() -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((0, 0), (1920, 1080))
+    }
+
+    fn workspace() -> Workspace {
+        let output = Output::new(
+            "test".to_string(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "wazemmes".to_string(),
+                model: "test".to_string(),
+            },
+            None,
+        );
+
+        Workspace::new(&output, output_geometry(), GAPS)
+    }
+
+    fn child(parent: &ContainerRef) -> (u32, Node) {
+        let (output, layout, gaps) = {
+            let parent = parent.get();
+            let layout = match parent.layout {
+                ContainerLayout::Horizontal => ContainerLayout::Vertical,
+                ContainerLayout::Vertical => ContainerLayout::Horizontal,
+            };
+            (parent.output.clone(), layout, parent.gaps)
+        };
+
+        let container = ContainerRef::new(Container {
+            id: node::id::next(),
+            location: Default::default(),
+            size: Default::default(),
+            output,
+            parent: Some(parent.clone()),
+            nodes: NodeMap::default(),
+            layout,
+            gaps,
+            #[cfg(feature = "xwayland")]
+            xpopups: vec![],
+        });
+
+        let id = container.get().id;
+        (id, Node::Container(container))
+    }
+
+    // Every container of the tree in depth first order, starting with `root`
+    fn containers(root: &ContainerRef) -> Vec<ContainerRef> {
+        let mut all = vec![root.clone()];
+        let children: Vec<ContainerRef> = root
+            .get()
+            .nodes
+            .iter_spine()
+            .filter_map(|(_, node)| node.try_into().ok())
+            .collect();
+
+        for child in children {
+            all.extend(containers(&child));
+        }
+
+        all
+    }
+
+    fn depth(container: &ContainerRef) -> usize {
+        match &container.get().parent {
+            None => 0,
+            Some(parent) => depth(parent) + 1,
+        }
+    }
+
+    fn height(container: &ContainerRef) -> usize {
+        containers(container)
+            .iter()
+            .map(depth)
+            .max()
+            .unwrap_or_default()
+            - depth(container)
+    }
+
+    fn can_grow(container: &ContainerRef, extra: usize) -> bool {
+        container.get().nodes.spine.len() + extra <= MAX_CHILDREN
+    }
+
+    fn pick(root: &ContainerRef, idx: usize) -> ContainerRef {
+        let containers = containers(root);
+        containers[idx % containers.len()].clone()
+    }
+
+    // Pick any container but the root one
+    fn pick_child(root: &ContainerRef, idx: usize) -> Option<(ContainerRef, ContainerRef)> {
+        let containers = containers(root);
+        if containers.len() < 2 {
+            return None;
+        }
+
+        let container = containers[1 + idx % (containers.len() - 1)].clone();
+        let parent = container.get().parent.clone()?;
+        Some((container, parent))
+    }
+
+    fn is_in_subtree(container: &ContainerRef, id: u32) -> bool {
+        containers(container).iter().any(|c| c.get().id == id)
+    }
+
+    fn apply(ws: &mut Workspace, op: Op) {
+        let root = ws.root();
+
+        match op {
+            Op::Push(target) => {
+                let target = pick(&root, target);
+                if can_grow(&target, 1) && depth(&target) < MAX_DEPTH {
+                    let (_, node) = child(&target);
+                    target.get_mut().nodes.push(node);
+                }
+            }
+            Op::InsertBefore(target, anchor) | Op::InsertAfter(target, anchor) => {
+                let target_ref = pick(&root, target);
+                let anchor = {
+                    let target = target_ref.get();
+                    let spine = &target.nodes.spine;
+                    (!spine.is_empty()).then(|| spine[anchor % spine.len()])
+                };
+
+                if let Some(anchor) = anchor {
+                    if can_grow(&target_ref, 1) && depth(&target_ref) < MAX_DEPTH {
+                        let (id, node) = child(&target_ref);
+                        let mut target = target_ref.get_mut();
+                        let inserted = match op {
+                            Op::InsertBefore(..) => target.nodes.insert_before(anchor, node),
+                            _ => target.nodes.insert_after(anchor, node),
+                        };
+                        assert_that!(inserted).is_equal_to(Some(id));
+                    }
+                }
+            }
+            Op::Remove(target) => {
+                if let Some((container, parent)) = pick_child(&root, target) {
+                    let id = container.get().id;
+                    if is_in_subtree(&container, ws.get_focus().0.get().id) {
+                        ws.set_container_focused(&parent);
+                    }
+                    assert_that!(parent.get_mut().nodes.remove(&id)).is_some();
+                }
+            }
+            Op::Focus(target) => {
+                let target = pick(&root, target);
+                let parent = target.get().parent.clone();
+                if let Some(parent) = parent {
+                    let id = target.get().id;
+                    parent.get_mut().nodes.set_focus(id);
+                }
+                ws.set_container_focused(&target);
+            }
+            Op::FocusParent => {
+                ws.focus_parent();
+            }
+            Op::Move(target, destination) => {
+                let destination = pick(&root, destination);
+                if let Some((container, parent)) = pick_child(&root, target) {
+                    let id = container.get().id;
+                    let fits = depth(&destination) + 1 + height(&container) <= MAX_DEPTH;
+
+                    if !is_in_subtree(&container, destination.get().id)
+                        && can_grow(&destination, 1)
+                        && fits
+                    {
+                        let node = parent.get_mut().nodes.remove(&id).unwrap();
+                        destination.adopt(vec![(id, node)]);
+                    }
+                }
+            }
+            Op::Drain(target) => {
+                if let Some((container, parent)) = pick_child(&root, target) {
+                    let id = container.get().id;
+                    let len = container.get().nodes.spine.len();
+
+                    if can_grow(&parent, len.saturating_sub(1)) {
+                        if is_in_subtree(&container, ws.get_focus().0.get().id) {
+                            ws.set_container_focused(&parent);
+                        }
+
+                        let children = container.get_mut().nodes.drain_all();
+                        assert_that!(container.get().nodes.validate()).is_ok();
+                        parent.get_mut().nodes.remove(&id);
+                        parent.adopt(children);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn insert_container_keeps_focus_on_the_same_node() {
+        let ws = workspace();
+        let root = ws.root();
+        let (first, first_node) = child(&root);
+        let (second, second_node) = child(&root);
+        let (inserted, inserted_node) = child(&root);
+
+        let mut root = root.get_mut();
+        root.nodes.push(first_node);
+        root.nodes.push(second_node);
+        root.nodes.set_focus(second);
+        root.nodes.insert_before(first, inserted_node);
+
+        assert_that!(root.nodes.get_focused().map(Node::id)).is_equal_to(Some(second));
+        assert_that!(root.nodes.spine).is_equal_to(vec![inserted, first, second]);
+
+        root.nodes.remove(&first);
+        assert_that!(root.nodes.get_focused().map(Node::id)).is_equal_to(Some(second));
+        assert_that!(root.nodes.validate()).is_ok();
+    }
+
+    #[test]
+    fn removing_the_last_focused_node_clears_focus() {
+        let ws = workspace();
+        let root = ws.root();
+        let (first, first_node) = child(&root);
+        let (second, second_node) = child(&root);
+
+        let mut root = root.get_mut();
+        root.nodes.push(first_node);
+        root.nodes.push(second_node);
+        root.nodes.set_focus(second);
+        root.nodes.remove(&second);
+
+        assert_that!(root.nodes.get_focused().map(Node::id)).is_none();
+        assert_that!(root.nodes.validate()).is_ok();
+
+        root.nodes.remove(&first);
+        assert_that!(root.nodes.validate()).is_ok();
+    }
+
+    #[test]
+    fn drain_all_empties_the_spine() {
+        let ws = workspace();
+        let root = ws.root();
+        let (_, first) = child(&root);
+        let (_, second) = child(&root);
+
+        let mut root = root.get_mut();
+        root.nodes.push(first);
+        root.nodes.push(second);
+        let drained = root.nodes.drain_all();
+
+        assert_that!(drained).has_length(2);
+        assert_that!(root.nodes.spine).is_empty();
+        assert_that!(root.nodes.validate()).is_ok();
+    }
+
+    #[test]
+    fn should_detect_wrong_parent() {
+        let ws = workspace();
+        let root = ws.root();
+        let (_, intermediate) = child(&root);
+        let intermediate: ContainerRef = intermediate.try_into().unwrap();
+        let (id, orphan) = child(&intermediate);
+
+        // The orphan claims `intermediate` as a parent but lives in the root container
+        root.get_mut().nodes.push(orphan);
+
+        assert_that!(ws.validate()).is_err_containing(LayoutError::WrongParent {
+            child: id,
+            expected: root.get().id,
+            found: Some(intermediate.get().id),
+        });
+    }
+
+    proptest! {
+        #[test]
+        fn layout_tree_invariants_hold(ops in prop::collection::vec(op(), 1..64)) {
+            let mut ws = workspace();
+
+            for op in ops {
+                apply(&mut ws, op.clone());
+                ws.root().get_mut().update_layout(output_geometry());
+
+                if let Err(err) = ws.validate() {
+                    return Err(TestCaseError::fail(format!("{} after {:?}", err, op)));
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,22 @@
+use crate::config::WazemmesConfig;
+use crate::ipc::protocol::WorkspaceChange;
+use crate::shell::animation::WorkspaceTransition;
+use crate::shell::container::ContainerState;
+use crate::shell::switcher::SwitcherEntry;
+use crate::shell::windows::toplevel::WindowWrap;
 use crate::shell::workspace::WorkspaceRef;
 use crate::Wazemmes;
+use std::time::Instant;
 
+pub mod animation;
 pub mod container;
 pub mod drawable;
+pub mod focus_history;
+pub mod invariant;
 pub mod node;
 pub mod nodemap;
+pub mod overview;
+pub mod switcher;
 pub mod windows;
 pub mod workspace;
 
@@ -17,22 +29,71 @@ impl Wazemmes {
             .clone()
     }
 
-    pub fn move_to_workspace(&mut self, num: u8) {
+    /// Windows of every workspace, most recently focused first
+    pub fn windows_by_recent_focus(&self) -> Vec<SwitcherEntry> {
+        let mut entries: Vec<SwitcherEntry> = self
+            .workspaces
+            .iter()
+            .flat_map(|(num, workspace)| {
+                let windows = workspace.get().flatten_window();
+                windows.into_iter().map(move |window| SwitcherEntry {
+                    workspace: *num,
+                    window,
+                })
+            })
+            .collect();
+
+        entries.sort_by_key(|entry| {
+            let id = entry.window.id();
+            self.focus_history
+                .iter()
+                .position(|focused| *focused == id)
+                .unwrap_or(usize::MAX)
+        });
+
+        entries
+    }
+
+    pub fn move_to_workspace(&mut self, num: u8, config: &WazemmesConfig) {
         // Target workspace is already focused
         if self.current_workspace == num {
             return;
         }
 
+        // Finish any running slide before starting a new one
+        if let Some(transition) = self.workspace_transition.take() {
+            transition.previous.get_mut().unmap_all(&mut self.space);
+        }
+
         let current_workspace = self.get_current_workspace();
-        let mut current_workspace = current_workspace.get_mut();
-        current_workspace.unmap_all(&mut self.space);
+        let previous = self.current_workspace;
+        let previous_empty = current_workspace.get().flatten_window().is_empty();
+
+        if config.animation.enabled {
+            let direction = if num > self.current_workspace { 1 } else { -1 };
+            self.workspace_transition = Some(WorkspaceTransition::new(
+                current_workspace,
+                direction,
+                &config.animation,
+            ));
+        } else {
+            current_workspace.get_mut().unmap_all(&mut self.space);
+        }
+
         self.current_workspace = num;
 
         match self.workspaces.get(&num) {
             None => {
-                let output = self.space.outputs().next().unwrap();
-                let workspace = WorkspaceRef::new(output.clone(), &self.space);
+                // Without any output the workspace waits on the previous one to be restored
+                let output = self
+                    .space
+                    .outputs()
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| current_workspace.get().output.clone());
+                let workspace = WorkspaceRef::new(output, &self.space, config.gaps);
                 self.workspaces.insert(num, workspace);
+                self.ipc_workspace_event(WorkspaceChange::Init, num, None);
             }
             Some(workspace) => {
                 let mut workspace = workspace.get_mut();
@@ -40,5 +101,122 @@ impl Wazemmes {
                 workspace.needs_redraw = true;
             }
         };
+
+        self.ipc_workspace_event(WorkspaceChange::Focus, num, Some(previous));
+        if previous_empty {
+            self.ipc_workspace_event(WorkspaceChange::Empty, previous, None);
+        }
+    }
+
+    /// Move a window to the focused container of another workspace, creating it if needed
+    pub fn move_window_to_workspace(
+        &mut self,
+        window: &WindowWrap,
+        from: u8,
+        num: u8,
+        config: &WazemmesConfig,
+    ) {
+        if from == num {
+            return;
+        }
+
+        let source = match self.workspaces.get(&from) {
+            Some(workspace) => workspace.clone(),
+            None => return,
+        };
+
+        let container = match source.get().root().container_having_window(window.id()) {
+            Some(container) => container,
+            None => return,
+        };
+
+        let state = {
+            let mut container = container.get_mut();
+            container.nodes.remove(&window.id());
+            container.state()
+        };
+
+        if state == ContainerState::Empty {
+            let mut source = source.get_mut();
+            if source.get_focus().0.get().id == container.get().id {
+                source.pop_container();
+            } else if let Some(parent) = &container.get().parent {
+                parent.get_mut().nodes.remove(&container.get().id);
+            }
+        }
+
+        if from == self.current_workspace {
+            self.space.unmap_window(window.inner());
+        }
+
+        {
+            let mut source = source.get_mut();
+            source.update_layout(&self.space);
+            source.needs_redraw = from == self.current_workspace;
+            source.update_borders();
+        }
+
+        let created = !self.workspaces.contains_key(&num);
+        let target = self
+            .workspaces
+            .entry(num)
+            .or_insert_with(|| {
+                // A new workspace opens on the output the window comes from
+                let output = source.get().output.clone();
+                let output = Some(output.clone())
+                    .filter(|output| self.space.output_geometry(output).is_some())
+                    .or_else(|| self.space.outputs().next().cloned())
+                    .unwrap_or(output);
+                WorkspaceRef::new(output, &self.space, config.gaps)
+            })
+            .clone();
+
+        if created {
+            self.ipc_workspace_event(WorkspaceChange::Init, num, None);
+        }
+
+        let mut target = target.get_mut();
+        let (container, _) = target.get_focus();
+        container.get_mut().push_window(window.clone());
+        target.update_layout(&self.space);
+        target.needs_redraw = num == self.current_workspace;
+        target.update_borders();
+    }
+
+    /// Slide the previous workspace out and the current one in, returns true while the transition runs
+    pub fn update_workspace_transition(&mut self) -> bool {
+        let now = Instant::now();
+        let current = self.get_current_workspace();
+
+        let done = match &self.workspace_transition {
+            None => return false,
+            Some(transition) => {
+                let current = current.get();
+                let width = self
+                    .space
+                    .output_geometry(&current.output)
+                    .map(|geometry| geometry.size.w)
+                    .unwrap_or_default();
+
+                let (previous_offset, current_offset) = transition.offsets(width, now);
+                transition
+                    .previous
+                    .get()
+                    .map_with_offset(&mut self.space, (previous_offset, 0).into());
+                current.map_with_offset(&mut self.space, (current_offset, 0).into());
+                transition.animation.is_done(now)
+            }
+        };
+
+        if done {
+            if let Some(transition) = self.workspace_transition.take() {
+                transition.previous.get_mut().unmap_all(&mut self.space);
+            }
+
+            let mut current = current.get_mut();
+            current.needs_redraw = true;
+        }
+
+        !done
     }
 }
@@ -1,10 +1,11 @@
 use slog_scope::debug;
 use smithay::utils::IsAlive;
 use std::collections::hash_map::Iter;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 
 use crate::shell::container::ContainerRef;
+use crate::shell::invariant::LayoutError;
 use crate::shell::node::Node;
 use crate::shell::windows::toplevel::WindowWrap;
 
@@ -61,8 +62,7 @@ impl NodeMap {
         let mut drained = vec![];
 
         for id in ids {
-            self.spine.drain_filter(|id_| id == *id_);
-            let node = self.items.remove(&id).unwrap();
+            let node = self.remove(&id).unwrap();
             drained.push((id, node))
         }
 
@@ -81,28 +81,23 @@ impl NodeMap {
         let redraw = !ids.is_empty();
 
         for id in ids {
-            self.spine.drain_filter(|id_| id == *id_);
-            let _node = self.items.remove(&id).unwrap();
+            let _node = self.remove(&id).unwrap();
         }
 
         redraw
     }
 
+    /// Remove every node in spine order, nested containers are drained with their children
     pub fn drain_all(&mut self) -> Vec<(u32, Node)> {
-        let mut drained = vec![];
-        for id in &self.spine {
-            let node = self.items.remove(id).unwrap();
-            drained.push((*id, node))
-        }
-
-        for node in &mut self.items.values() {
-            if let Node::Container(c) = node {
-                let mut ref_mut = c.get_mut();
-                drained.extend(ref_mut.nodes.drain_all());
-            }
-        }
+        self.focus_idx = None;
 
-        drained
+        self.spine
+            .drain(..)
+            .map(|id| {
+                let node = self.items.remove(&id).unwrap();
+                (id, node)
+            })
+            .collect()
     }
 
     pub fn extend(&mut self, other: Vec<(u32, Node)>) {
@@ -144,41 +139,30 @@ impl NodeMap {
         id
     }
 
-    /// Insert a container or a window after the given node id in the spine
+    /// Insert a container or a window after the given node id in the spine and return its id
     pub fn insert_after(&mut self, id: u32, node: Node) -> Option<u32> {
-        let focus_index = self.spine_index(id);
-
-        if let Some(index) = focus_index {
-            let index = index + 1;
-            self.spine.insert(index, node.id());
-
-            if !node.is_container() {
-                self.set_focus_index(index);
-            }
-
-            self.items.insert(node.id(), node);
-            Some(id)
-        } else {
-            None
-        }
+        self.spine_index(id)
+            .map(|index| self.insert_at(index + 1, node))
     }
 
-    /// Insert a container or a window after the given node id in the spine
+    /// Insert a container or a window before the given node id in the spine and return its id
     pub fn insert_before(&mut self, id: u32, node: Node) -> Option<u32> {
-        let focus_index = self.spine_index(id);
-
-        if let Some(index) = focus_index {
-            self.spine.insert(index, node.id());
+        self.spine_index(id).map(|index| self.insert_at(index, node))
+    }
 
-            if !node.is_container() {
-                self.set_focus_index(index);
-            }
+    fn insert_at(&mut self, index: usize, node: Node) -> u32 {
+        let id = node.id();
+        self.spine.insert(index, id);
 
-            self.items.insert(node.id(), node);
-            Some(id)
-        } else {
-            None
+        if !node.is_container() {
+            self.set_focus_index(index);
+        } else if let Some(focus_idx) = self.focus_idx.filter(|focus| *focus >= index) {
+            // Keep the focus on the same node, it was shifted by the insertion
+            self.focus_idx = Some(focus_idx + 1);
         }
+
+        self.items.insert(id, node);
+        id
     }
 
     pub fn remove(&mut self, id: &u32) -> Option<Node> {
@@ -215,19 +199,25 @@ impl NodeMap {
         if let Some((idx, id)) = spine_part {
             self.spine.remove(idx);
 
-            if self.spine.is_empty() {
-                self.focus_idx = None
-            } else {
-                let new_focus = self.spine[..idx]
-                    .iter()
-                    .enumerate()
-                    .rfind(|(_idx, id)| matches!(self.items.get(id), Some(Node::Window(_))))
-                    .map(|(idx, _)| idx);
-
-                if let Some(new_focus) = new_focus {
-                    self.set_focus_index(new_focus)
+            self.focus_idx = match self.focus_idx {
+                // Nodes after the removed one are shifted to the left
+                Some(focus) if focus > idx => Some(focus - 1),
+                // The focused node was removed, focus the nearest window before it, or after it
+                Some(focus) if focus == idx => {
+                    let is_window = |id: &u32| matches!(self.items.get(id), Some(Node::Window(_)));
+                    self.spine[..idx]
+                        .iter()
+                        .rposition(is_window)
+                        .or_else(|| {
+                            self.spine[idx..]
+                                .iter()
+                                .position(is_window)
+                                .map(|position| position + idx)
+                        })
                 }
-            }
+                focus => focus,
+            };
+
             Some(id)
         } else {
             None
@@ -258,13 +248,49 @@ impl NodeMap {
             })
     }
 
+    /// Check that the spine, the node map and the focus index agree with each other
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        let mut seen = HashSet::with_capacity(self.spine.len());
+
+        for id in &self.spine {
+            if !seen.insert(*id) {
+                return Err(LayoutError::DuplicateSpineEntry(*id));
+            }
+
+            if !self.items.contains_key(id) {
+                return Err(LayoutError::MissingItem(*id));
+            }
+        }
+
+        for (key, node) in &self.items {
+            if !seen.contains(key) {
+                return Err(LayoutError::NotInSpine(*key));
+            }
+
+            if node.id() != *key {
+                return Err(LayoutError::MismatchedId {
+                    key: *key,
+                    node: node.id(),
+                });
+            }
+        }
+
+        match self.focus_idx {
+            Some(index) if index >= self.spine.len() => Err(LayoutError::FocusOutOfBounds {
+                index,
+                len: self.spine.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     fn set_focus_index(&mut self, idx: usize) {
         debug!("Updating focus index={}", idx);
         debug_assert!(self.spine.get(idx).is_some());
         self.focus_idx = Some(idx)
     }
 
-    fn spine_index(&mut self, id: u32) -> Option<usize> {
+    fn spine_index(&self, id: u32) -> Option<usize> {
         self.spine
             .iter()
             .enumerate()
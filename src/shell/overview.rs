@@ -0,0 +1,373 @@
+use crate::backend::drawing::OVERLAY_Z_INDEX;
+use crate::border::{BLUE, DARK_GREY, GREY};
+use crate::inputs::handlers::Direction;
+use crate::shell::drawable::Quad;
+use crate::shell::windows::toplevel::WindowWrap;
+use crate::shell::workspace::WorkspaceRef;
+use slog_scope::warn;
+use smithay::backend::renderer::gles2::{Gles2Renderer, Gles2Texture};
+use smithay::backend::renderer::{Bind, Frame, Offscreen, Renderer, Texture, Unbind};
+use smithay::desktop::draw_window;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Rectangle, Size, Transform};
+use std::collections::HashMap;
+
+const PADDING: i32 = 32;
+const HIGHLIGHT_WIDTH: i32 = 4;
+
+/// A window thumbnail being dragged to another workspace
+#[derive(Debug)]
+pub struct OverviewDrag {
+    pub workspace: u8,
+    pub window: WindowWrap,
+    /// Pointer offset from the window thumbnail origin
+    offset: Point<f64, Logical>,
+}
+
+/// Exposé like view of every workspace of an output
+#[derive(Debug)]
+pub struct Overview {
+    pub output: Output,
+    workspaces: Vec<u8>,
+    selected: usize,
+    selected_window: Option<usize>,
+    thumbnails: HashMap<u8, Gles2Texture>,
+    pub drag: Option<OverviewDrag>,
+}
+
+impl Overview {
+    /// Open the overview on the given workspaces, `current` is preselected
+    pub fn new(output: Output, mut workspaces: Vec<u8>, current: u8) -> Self {
+        workspaces.sort_unstable();
+        let selected = workspaces
+            .iter()
+            .position(|num| *num == current)
+            .unwrap_or_default();
+
+        Self {
+            output,
+            workspaces,
+            selected,
+            selected_window: None,
+            thumbnails: HashMap::new(),
+            drag: None,
+        }
+    }
+
+    pub fn selected_workspace(&self) -> u8 {
+        self.workspaces[self.selected]
+    }
+
+    pub fn selected_window(&self, workspaces: &HashMap<u8, WorkspaceRef>) -> Option<WindowWrap> {
+        let idx = self.selected_window?;
+        let workspace = workspaces.get(&self.selected_workspace())?;
+        let windows = workspace.get().flatten_window();
+        windows.get(idx).cloned()
+    }
+
+    pub fn select(&mut self, workspace: u8, window: Option<usize>) {
+        if let Some(idx) = self.workspaces.iter().position(|num| *num == workspace) {
+            self.selected = idx;
+            self.selected_window = window;
+        }
+    }
+
+    /// Move the workspace selection in the grid
+    pub fn move_selection(&mut self, direction: Direction) {
+        let count = self.workspaces.len();
+        let columns = columns(count);
+
+        let target = match direction {
+            Direction::Left => self.selected.checked_sub(1),
+            Direction::Right => Some(self.selected + 1),
+            Direction::Up => self.selected.checked_sub(columns),
+            Direction::Down => Some(self.selected + columns),
+        };
+
+        if let Some(target) = target.filter(|target| *target < count) {
+            self.selected = target;
+            self.selected_window = None;
+        }
+    }
+
+    /// Cycle through the windows of the selected workspace
+    pub fn next_window(&mut self, workspaces: &HashMap<u8, WorkspaceRef>) {
+        let count = workspaces
+            .get(&self.selected_workspace())
+            .map(|workspace| workspace.get().flatten_window().len())
+            .unwrap_or_default();
+
+        self.selected_window = match self.selected_window {
+            _ if count == 0 => None,
+            None => Some(0),
+            Some(idx) => Some((idx + 1) % count),
+        };
+    }
+
+    /// Thumbnail geometries laid out in a grid filling the output
+    pub fn thumbnails_geometry(
+        &self,
+        output_geometry: Rectangle<i32, Logical>,
+    ) -> Vec<(u8, Rectangle<i32, Logical>)> {
+        let count = self.workspaces.len() as i32;
+        let columns = columns(self.workspaces.len()) as i32;
+        let rows = (count + columns - 1) / columns;
+
+        let size = output_geometry.size;
+        let cell_w = (size.w - (columns + 1) * PADDING) / columns;
+        let cell_h = (size.h - (rows + 1) * PADDING) / rows;
+        let scale = f64::min(cell_w as f64 / size.w as f64, cell_h as f64 / size.h as f64);
+        let thumb_w = (size.w as f64 * scale) as i32;
+        let thumb_h = (size.h as f64 * scale) as i32;
+
+        self.workspaces
+            .iter()
+            .enumerate()
+            .map(|(idx, num)| {
+                let (column, row) = (idx as i32 % columns, idx as i32 / columns);
+                let x = output_geometry.loc.x
+                    + PADDING
+                    + column * (cell_w + PADDING)
+                    + (cell_w - thumb_w) / 2;
+                let y = output_geometry.loc.y
+                    + PADDING
+                    + row * (cell_h + PADDING)
+                    + (cell_h - thumb_h) / 2;
+                let geometry = Rectangle::from_loc_and_size((x, y), (thumb_w, thumb_h));
+                (*num, geometry)
+            })
+            .collect()
+    }
+
+    pub fn workspace_under(
+        &self,
+        output_geometry: Rectangle<i32, Logical>,
+        point: Point<f64, Logical>,
+    ) -> Option<(u8, Rectangle<i32, Logical>)> {
+        self.thumbnails_geometry(output_geometry)
+            .into_iter()
+            .find(|(_, geometry)| geometry.to_f64().contains(point))
+    }
+
+    /// Find the window thumbnail under the pointer, floating windows are on top
+    pub fn window_under(
+        &self,
+        workspaces: &HashMap<u8, WorkspaceRef>,
+        output_geometry: Rectangle<i32, Logical>,
+        point: Point<f64, Logical>,
+    ) -> Option<(u8, usize, WindowWrap)> {
+        let (num, thumbnail) = self.workspace_under(output_geometry, point)?;
+        let windows = workspaces.get(&num)?.get().flatten_window();
+
+        let mut candidates: Vec<(usize, WindowWrap)> = windows
+            .into_iter()
+            .enumerate()
+            .filter(|(_, window)| {
+                scale_to_thumbnail(window.geometry(), output_geometry, thumbnail)
+                    .to_f64()
+                    .contains(point)
+            })
+            .collect();
+
+        candidates.sort_by_key(|(_, window)| window.z_index());
+        candidates.pop().map(|(idx, window)| (num, idx, window))
+    }
+
+    pub fn start_drag(
+        &mut self,
+        workspace: u8,
+        window: WindowWrap,
+        output_geometry: Rectangle<i32, Logical>,
+        point: Point<f64, Logical>,
+    ) {
+        let offset = self
+            .thumbnails_geometry(output_geometry)
+            .into_iter()
+            .find(|(num, _)| *num == workspace)
+            .map(|(_, thumbnail)| {
+                let geometry = scale_to_thumbnail(window.geometry(), output_geometry, thumbnail);
+                point - geometry.loc.to_f64()
+            })
+            .unwrap_or_default();
+
+        self.drag = Some(OverviewDrag {
+            workspace,
+            window,
+            offset,
+        });
+    }
+
+    /// Render every workspace into an offscreen texture sized like its thumbnail
+    pub fn render_thumbnails(
+        &mut self,
+        renderer: &mut Gles2Renderer,
+        workspaces: &HashMap<u8, WorkspaceRef>,
+        output_geometry: Rectangle<i32, Logical>,
+    ) {
+        let output_scale = self.output.current_scale().fractional_scale();
+        let log = slog_scope::logger();
+
+        for (num, thumbnail) in self.thumbnails_geometry(output_geometry) {
+            let workspace = match workspaces.get(&num) {
+                Some(workspace) => workspace,
+                None => continue,
+            };
+
+            let size = thumbnail.size.to_physical_precise_round(output_scale);
+            let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+            let scale = output_scale * thumbnail.size.w as f64 / output_geometry.size.w as f64;
+
+            let texture = match self.thumbnails.get(&num) {
+                Some(texture) if texture.size() == buffer_size => texture.clone(),
+                _ => match Offscreen::<Gles2Texture>::create_buffer(renderer, buffer_size) {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        warn!("Failed to create workspace thumbnail: {:?}", err);
+                        continue;
+                    }
+                },
+            };
+
+            if let Err(err) = renderer.bind(texture.clone()) {
+                warn!("Failed to bind workspace thumbnail: {:?}", err);
+                continue;
+            }
+
+            let windows = workspace.get().flatten_window();
+            let result = renderer.render(size, Transform::Normal, |renderer, frame| {
+                let damage = [Rectangle::from_loc_and_size((0, 0), size)];
+                frame.clear([0.1, 0.1, 0.1, 1.0], &damage)?;
+
+                let (tiling, floating): (Vec<_>, Vec<_>) =
+                    windows.iter().partition(|window| !window.is_floating());
+
+                for window in tiling.into_iter().chain(floating) {
+                    let location = (window.loc() - output_geometry.loc)
+                        .to_f64()
+                        .to_physical(scale);
+                    draw_window(
+                        renderer,
+                        frame,
+                        window.inner(),
+                        scale,
+                        location,
+                        &damage,
+                        &log,
+                    )?;
+                }
+
+                Ok(())
+            });
+
+            if let Err(err) = result.and_then(|result| result) {
+                warn!("Failed to render workspace thumbnail: {:?}", err);
+            }
+
+            if let Err(err) = renderer.unbind() {
+                warn!("Failed to unbind workspace thumbnail: {:?}", err);
+            }
+
+            self.thumbnails.insert(num, texture);
+        }
+    }
+
+    pub fn thumbnails(
+        &self,
+        output_geometry: Rectangle<i32, Logical>,
+    ) -> Vec<(u8, Gles2Texture, Rectangle<i32, Logical>)> {
+        self.thumbnails_geometry(output_geometry)
+            .into_iter()
+            .filter_map(|(num, geometry)| {
+                self.thumbnails
+                    .get(&num)
+                    .map(|texture| (num, texture.clone(), geometry))
+            })
+            .collect()
+    }
+
+    /// Background, selection highlight and dragged window drawn around the thumbnails
+    pub fn overlay(
+        &self,
+        workspaces: &HashMap<u8, WorkspaceRef>,
+        output_geometry: Rectangle<i32, Logical>,
+        pointer: Point<f64, Logical>,
+    ) -> Vec<Quad> {
+        let thumbnails = self.thumbnails_geometry(output_geometry);
+
+        let mut quads = vec![Quad {
+            geometry: output_geometry,
+            color: DARK_GREY,
+            z_index: OVERLAY_Z_INDEX,
+        }];
+
+        if let Some((_, selected)) = thumbnails.get(self.selected) {
+            quads.push(Quad {
+                geometry: Rectangle::from_loc_and_size(
+                    (
+                        selected.loc.x - HIGHLIGHT_WIDTH,
+                        selected.loc.y - HIGHLIGHT_WIDTH,
+                    ),
+                    (
+                        selected.size.w + 2 * HIGHLIGHT_WIDTH,
+                        selected.size.h + 2 * HIGHLIGHT_WIDTH,
+                    ),
+                ),
+                color: BLUE,
+                z_index: OVERLAY_Z_INDEX + 1,
+            });
+
+            if let Some(window) = self.selected_window(workspaces) {
+                let geometry = scale_to_thumbnail(window.geometry(), output_geometry, *selected);
+                quads.push(Quad {
+                    geometry,
+                    color: BLUE,
+                    z_index: OVERLAY_Z_INDEX + 3,
+                });
+            }
+        }
+
+        if let Some(drag) = &self.drag {
+            let thumbnail = thumbnails
+                .iter()
+                .find(|(num, _)| *num == drag.workspace)
+                .map(|(_, thumbnail)| *thumbnail);
+
+            if let Some(thumbnail) = thumbnail {
+                let mut geometry =
+                    scale_to_thumbnail(drag.window.geometry(), output_geometry, thumbnail);
+                geometry.loc = (pointer - drag.offset).to_i32_round();
+                quads.push(Quad {
+                    geometry,
+                    color: GREY,
+                    z_index: OVERLAY_Z_INDEX + 3,
+                });
+            }
+        }
+
+        quads
+    }
+}
+
+fn columns(count: usize) -> usize {
+    ((count as f64).sqrt().ceil() as usize).max(1)
+}
+
+/// Map a rectangle on the output to the same relative rectangle inside a thumbnail
+fn scale_to_thumbnail(
+    geometry: Rectangle<i32, Logical>,
+    output_geometry: Rectangle<i32, Logical>,
+    thumbnail: Rectangle<i32, Logical>,
+) -> Rectangle<i32, Logical> {
+    let scale_x = thumbnail.size.w as f64 / output_geometry.size.w as f64;
+    let scale_y = thumbnail.size.h as f64 / output_geometry.size.h as f64;
+
+    let x = ((geometry.loc.x - output_geometry.loc.x) as f64 * scale_x) as i32;
+    let y = ((geometry.loc.y - output_geometry.loc.y) as f64 * scale_y) as i32;
+    let size: Size<i32, Logical> = (
+        ((geometry.size.w as f64 * scale_x) as i32).max(1),
+        ((geometry.size.h as f64 * scale_y) as i32).max(1),
+    )
+        .into();
+
+    Rectangle::from_loc_and_size((thumbnail.loc.x + x, thumbnail.loc.y + y), size)
+}
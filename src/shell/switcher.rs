@@ -0,0 +1,127 @@
+use crate::backend::drawing::OVERLAY_Z_INDEX;
+use crate::border::{BLUE, DARK_GREY, GREY};
+use crate::shell::drawable::Quad;
+use crate::shell::windows::toplevel::WindowWrap;
+use smithay::input::keyboard::ModifiersState;
+use smithay::utils::{Logical, Rectangle};
+
+const CARD_HEIGHT: i32 = 120;
+const CARD_SPACING: i32 = 16;
+const HIGHLIGHT_WIDTH: i32 = 4;
+
+#[derive(Debug, Clone)]
+pub struct SwitcherEntry {
+    pub workspace: u8,
+    pub window: WindowWrap,
+}
+
+/// Alt-Tab like window switcher, active while the modifiers of the triggering binding are held
+#[derive(Debug)]
+pub struct WindowSwitcher {
+    entries: Vec<SwitcherEntry>,
+    selected: usize,
+    modifiers: ModifiersState,
+}
+
+impl WindowSwitcher {
+    /// Create a switcher over windows in MRU order, the previously focused window is preselected
+    pub fn new(entries: Vec<SwitcherEntry>, modifiers: ModifiersState) -> Option<Self> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        let selected = if entries.len() > 1 { 1 } else { 0 };
+
+        Some(Self {
+            entries,
+            selected,
+            modifiers,
+        })
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.entries.len();
+    }
+
+    pub fn selected(&self) -> &SwitcherEntry {
+        &self.entries[self.selected]
+    }
+
+    /// The selection is committed once every modifier held when the switcher opened is released
+    pub fn should_commit(&self, modifiers: &ModifiersState) -> bool {
+        let held = |m: &ModifiersState| [m.ctrl, m.alt, m.shift, m.logo];
+
+        !held(&self.modifiers)
+            .iter()
+            .zip(held(modifiers))
+            .any(|(was_held, is_held)| *was_held && is_held)
+    }
+
+    /// Cards drawn in the middle of the output, each one showing where its window sits on the output.
+    pub fn overlay(&self, output_geometry: Rectangle<i32, Logical>) -> Vec<Quad> {
+        let count = self.entries.len() as i32;
+        let output_size = output_geometry.size;
+
+        let mut card_h = CARD_HEIGHT;
+        let mut card_w = card_h * output_size.w / output_size.h.max(1);
+        let max_width = output_size.w - 2 * CARD_SPACING;
+        let needed = count * card_w + (count + 1) * CARD_SPACING;
+
+        if needed > max_width {
+            card_w = ((max_width - (count + 1) * CARD_SPACING) / count).max(1);
+            card_h = card_w * output_size.h / output_size.w.max(1);
+        }
+
+        let panel_w = count * card_w + (count + 1) * CARD_SPACING;
+        let panel_h = card_h + 2 * CARD_SPACING;
+        let panel_x = output_geometry.loc.x + (output_size.w - panel_w) / 2;
+        let panel_y = output_geometry.loc.y + (output_size.h - panel_h) / 2;
+
+        let mut quads = vec![Quad {
+            geometry: Rectangle::from_loc_and_size((panel_x, panel_y), (panel_w, panel_h)),
+            color: DARK_GREY,
+            z_index: OVERLAY_Z_INDEX,
+        }];
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let x = panel_x + CARD_SPACING + idx as i32 * (card_w + CARD_SPACING);
+            let y = panel_y + CARD_SPACING;
+
+            if idx == self.selected {
+                quads.push(Quad {
+                    geometry: Rectangle::from_loc_and_size(
+                        (x - HIGHLIGHT_WIDTH, y - HIGHLIGHT_WIDTH),
+                        (card_w + 2 * HIGHLIGHT_WIDTH, card_h + 2 * HIGHLIGHT_WIDTH),
+                    ),
+                    color: BLUE,
+                    z_index: OVERLAY_Z_INDEX + 1,
+                });
+            }
+
+            quads.push(Quad {
+                geometry: Rectangle::from_loc_and_size((x, y), (card_w, card_h)),
+                color: GREY,
+                z_index: OVERLAY_Z_INDEX + 2,
+            });
+
+            let window = entry.window.geometry();
+            let scale_x = card_w as f64 / output_size.w as f64;
+            let scale_y = card_h as f64 / output_size.h as f64;
+            let window_x = ((window.loc.x - output_geometry.loc.x) as f64 * scale_x) as i32;
+            let window_y = ((window.loc.y - output_geometry.loc.y) as f64 * scale_y) as i32;
+            let window_w = ((window.size.w as f64 * scale_x) as i32).max(1);
+            let window_h = ((window.size.h as f64 * scale_y) as i32).max(1);
+
+            quads.push(Quad {
+                geometry: Rectangle::from_loc_and_size(
+                    (x + window_x, y + window_y),
+                    (window_w, window_h),
+                ),
+                color: BLUE,
+                z_index: OVERLAY_Z_INDEX + 3,
+            });
+        }
+
+        quads
+    }
+}
@@ -1,7 +1,10 @@
 use crate::backend::drawing::{FLOATING_Z_INDEX, TILING_Z_INDEX};
 use crate::backend::xwayland::X11State;
+use crate::config::animation::AnimationConfig;
+use crate::shell::animation::WindowAnimation;
 use crate::shell::drawable::{Border, Borders};
 use crate::shell::node;
+use slog_scope::debug;
 use smithay::desktop::{Kind, Space, Window};
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::reexports::wayland_server::Resource;
@@ -11,6 +14,7 @@ use smithay::wayland::shell::xdg::{ToplevelSurface, XdgToplevelSurfaceRoleAttrib
 use std::cell::RefCell;
 use std::fmt::Debug;
 use std::sync::Mutex;
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct WindowState {
@@ -21,6 +25,9 @@ pub struct WindowState {
     size: RefCell<Size<i32, Logical>>,
     loc: RefCell<Point<i32, Logical>>,
     borders: RefCell<Borders>,
+    // Geometry the window was last mapped with
+    rendered: RefCell<Option<Rectangle<i32, Logical>>>,
+    animation: RefCell<Option<WindowAnimation>>,
 }
 
 impl WindowState {
@@ -33,6 +40,8 @@ impl WindowState {
             size: RefCell::new(Default::default()),
             loc: RefCell::new(Default::default()),
             borders: RefCell::new(Borders::default()),
+            rendered: RefCell::new(None),
+            animation: RefCell::new(None),
         }
     }
 
@@ -68,6 +77,10 @@ impl WindowState {
     pub fn borders(&self) -> Borders {
         self.borders.borrow().clone()
     }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.borrow().is_some()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -155,7 +168,7 @@ impl WindowWrap {
         }
     }
 
-    pub fn map(&self, space: &mut Space, x11_state: Option<&mut X11State>, activate: bool) {
+    fn configure(&self, x11_state: Option<&mut X11State>) {
         match self.inner.toplevel() {
             Kind::Xdg(toplevel) => {
                 toplevel.with_pending_state(|state| {
@@ -173,8 +186,76 @@ impl WindowWrap {
                     .expect("X11 Error");
             }
         }
+    }
 
+    pub fn map(&self, space: &mut Space, x11_state: Option<&mut X11State>, activate: bool) {
+        self.get_state().animation.replace(None);
+        self.configure(x11_state);
         space.map_window(&self.inner, self.loc(), self.z_index(), activate);
+        self.get_state().rendered.replace(Some(self.geometry()));
+    }
+
+    /// Map the window, animating from the geometry it was last rendered with.
+    /// The client is only reconfigured once the animation ends, see [`WindowWrap::animate`].
+    pub fn map_animated(
+        &self,
+        space: &mut Space,
+        x11_state: Option<&mut X11State>,
+        activate: bool,
+        config: &AnimationConfig,
+    ) {
+        let target = self.geometry();
+        let rendered = *self.get_state().rendered.borrow();
+
+        let from = match rendered {
+            _ if !config.enabled => None,
+            // Newly opened windows slide in from below their target location
+            None => Some(Rectangle::from_loc_and_size(
+                (target.loc.x, target.loc.y + target.size.h / 2),
+                target.size,
+            )),
+            Some(rendered) if rendered != target => Some(rendered),
+            Some(_) => None,
+        };
+
+        match from {
+            Some(from) => {
+                debug!("Animating window({}) from {:?} to {:?}", self.id(), from, target);
+                let animation = WindowAnimation::new(from, target, activate, config);
+                space.map_window(&self.inner, from.loc, self.z_index(), activate);
+                self.get_state().rendered.replace(Some(from));
+                self.get_state().animation.replace(Some(animation));
+            }
+            None => self.map(space, x11_state, activate),
+        }
+    }
+
+    /// Step the running animation if any, returns true while the animation is not finished.
+    pub fn animate(&self, space: &mut Space, x11_state: Option<&mut X11State>, now: Instant) -> bool {
+        let animation = self.get_state().animation.borrow().clone();
+
+        match animation {
+            None => false,
+            Some(animation) if animation.is_done(now) => {
+                self.map(space, x11_state, animation.activate);
+                self.get_state().borders.replace(self.make_borders());
+                false
+            }
+            Some(animation) => {
+                let geometry = animation.geometry(now);
+                space.map_window(&self.inner, geometry.loc, self.z_index(), false);
+                self.get_state().rendered.replace(Some(geometry));
+                self.get_state()
+                    .borders
+                    .replace(Borders::for_window_geometry(geometry));
+                true
+            }
+        }
+    }
+
+    /// Map the window at its layout location translated by the given offset without reconfiguring it
+    pub fn map_with_offset(&self, space: &mut Space, offset: Point<i32, Logical>) {
+        space.map_window(&self.inner, self.loc() + offset, self.z_index(), false);
     }
 
     pub fn update_loc<P>(&self, location: P) -> bool
@@ -278,6 +359,10 @@ impl WindowWrap {
     pub fn loc(&self) -> Point<i32, Logical> {
         *self.get_state().loc.borrow()
     }
+
+    pub fn geometry(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size(self.loc(), self.size())
+    }
 }
 
 impl From<ToplevelSurface> for WindowWrap {
@@ -1,5 +1,5 @@
 use crate::backend::xwayland::X11State;
-use crate::config::CONFIG;
+use crate::config::animation::AnimationConfig;
 use crate::shell::container::{Container, ContainerLayout, ContainerRef};
 use crate::shell::drawable::{Border, Borders};
 use crate::shell::node;
@@ -7,11 +7,12 @@ use crate::shell::node::Node;
 use crate::shell::nodemap::NodeMap;
 use crate::shell::windows::toplevel::WindowWrap;
 use slog_scope::debug;
-use smithay::desktop::Space;
-use smithay::utils::{Logical, Physical, Rectangle};
+use smithay::desktop::{layer_map_for_output, Space};
+use smithay::utils::{Logical, Physical, Point, Rectangle};
 use smithay::output::Output;
 use std::cell::{Ref, RefCell, RefMut};
 use std::rc::Rc;
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct WorkspaceRef {
@@ -19,10 +20,11 @@ pub struct WorkspaceRef {
 }
 
 impl WorkspaceRef {
-    pub fn new(output: Output, space: &Space) -> Self {
-        let geometry = space.output_geometry(&output).unwrap();
+    pub fn new(output: Output, space: &Space, gaps: u32) -> Self {
+        // Without any output left the workspace is laid out once it is given one
+        let geometry = tiling_area(space, &output).unwrap_or_default();
         Self {
-            inner: Rc::new(RefCell::new(Workspace::new(&output, geometry))),
+            inner: Rc::new(RefCell::new(Workspace::new(&output, geometry, gaps))),
         }
     }
 
@@ -35,9 +37,20 @@ impl WorkspaceRef {
     }
 }
 
+/// The output geometry minus the exclusive zones of its layer surfaces
+pub fn tiling_area(space: &Space, output: &Output) -> Option<Rectangle<i32, Logical>> {
+    let geometry = space.output_geometry(output)?;
+    let mut area = layer_map_for_output(output).non_exclusive_zone();
+    area.loc += geometry.loc;
+    Some(area)
+}
+
 #[derive(Debug)]
 pub struct Workspace {
     pub output: Output,
+    /// Name of the output this workspace was moved away from when it was disconnected,
+    /// the workspace goes back to it once it is connected again
+    pub home_output: Option<String>,
     pub fullscreen_layer: Option<Node>,
     root: ContainerRef,
     focus: ContainerRef,
@@ -46,8 +59,8 @@ pub struct Workspace {
 }
 
 impl Workspace {
-    pub fn new(output: &Output, geometry: Rectangle<i32, Logical>) -> Workspace {
-        let gaps = CONFIG.gaps as i32;
+    pub fn new(output: &Output, geometry: Rectangle<i32, Logical>, gaps: u32) -> Workspace {
+        let gaps = gaps as i32;
 
         let root = Container {
             id: node::id::get(),
@@ -57,6 +70,7 @@ impl Workspace {
             parent: None,
             nodes: NodeMap::default(),
             layout: ContainerLayout::Horizontal,
+            gaps,
             #[cfg(feature = "xwayland")]
             xpopups: vec![],
         };
@@ -66,6 +80,7 @@ impl Workspace {
 
         Self {
             output: output.clone(),
+            home_output: None,
             root,
             focus,
             fullscreen_layer: None,
@@ -75,14 +90,35 @@ impl Workspace {
     }
 
     pub fn update_layout(&mut self, space: &Space) {
-        let geometry = space.output_geometry(&self.output).unwrap();
+        // The output is gone, `restore_workspaces` lays the workspace out on the next one
+        let geometry = match space.output_geometry(&self.output) {
+            Some(geometry) => geometry,
+            None => return,
+        };
+
+        // Layer surfaces might have reserved or released an exclusive zone
+        self.reset_gaps(space);
         let root = &self.root;
         let mut root = root.get_mut();
         self.needs_redraw = root.update_layout(geometry);
+        drop(root);
+
+        #[cfg(debug_assertions)]
+        if let Err(err) = self.validate() {
+            slog_scope::error!("Layout invariant violated: {}", err);
+        }
     }
 
-    pub fn redraw(&mut self, space: &mut Space, x11_state: Option<&mut X11State>) {
-        let geometry = space.output_geometry(&self.output).expect("Geometry");
+    pub fn redraw(
+        &mut self,
+        space: &mut Space,
+        x11_state: Option<&mut X11State>,
+        animation: &AnimationConfig,
+    ) {
+        let geometry = match space.output_geometry(&self.output) {
+            Some(geometry) => geometry,
+            None => return,
+        };
         self.unmap_all(space);
 
         if let Some(layer) = &self.fullscreen_layer {
@@ -90,7 +126,7 @@ impl Workspace {
                 Node::Container(container) => {
                     debug!("Redraw: FullScreen Container");
                     let container = container.get();
-                    container.redraw(space, x11_state);
+                    container.redraw(space, x11_state, animation);
                 }
                 Node::Window(window) => {
                     debug!("Redraw: FullScreen Window");
@@ -101,12 +137,33 @@ impl Workspace {
         } else {
             debug!("Redraw: Root Container");
             let root = self.root.get();
-            root.redraw(space, x11_state);
+            root.redraw(space, x11_state, animation);
         }
 
         self.needs_redraw = false;
     }
 
+    /// Step window animations, returns true if some windows are still animating
+    pub fn animate(&self, space: &mut Space, mut x11_state: Option<&mut X11State>) -> bool {
+        let now = Instant::now();
+        let mut animating = false;
+
+        for window in self.flatten_window() {
+            if window.get_state().is_animating() {
+                animating |= window.animate(space, x11_state.as_deref_mut(), now);
+            }
+        }
+
+        animating
+    }
+
+    /// Map every window translated by the given offset, used to slide workspaces
+    pub fn map_with_offset(&self, space: &mut Space, offset: Point<i32, Logical>) {
+        for window in self.flatten_window() {
+            window.map_with_offset(space, offset);
+        }
+    }
+
     pub fn root(&self) -> ContainerRef {
         self.root.clone()
     }
@@ -144,6 +201,24 @@ impl Workspace {
         }
     }
 
+    /// Focus the parent of the focused container, `false` if the root is already focused
+    pub fn focus_parent(&mut self) -> bool {
+        let (id, parent) = {
+            let focus = self.focus.get();
+            (focus.id, focus.parent.clone())
+        };
+
+        match parent {
+            Some(parent) => {
+                // The parent now reports the child container as focused, not one of its windows
+                parent.get_mut().nodes.set_focus(id);
+                self.focus = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn set_container_focused(&mut self, container: &ContainerRef) {
         self.focus = container.clone();
     }
@@ -184,11 +259,48 @@ impl Workspace {
         }
     }
 
+    /// Change the gaps of every container, the layout needs to be updated afterward
+    pub fn set_gaps(&mut self, space: &Space, gaps: u32) {
+        let root = self.root();
+        let mut containers = vec![root];
+
+        while let Some(container) = containers.pop() {
+            let mut container = container.get_mut();
+            container.gaps = gaps as i32;
+            containers.extend(container.nodes.iter_containers().cloned());
+        }
+
+        if space.output_geometry(&self.output).is_some() {
+            self.reset_gaps(space);
+        }
+    }
+
+    /// Move the workspace and its containers to another output
+    pub fn set_output(&mut self, output: &Output, space: &Space) {
+        let root = self.root();
+        let mut containers = vec![root];
+
+        while let Some(container) = containers.pop() {
+            let mut container = container.get_mut();
+            container.output = output.clone();
+            containers.extend(container.nodes.iter_containers().cloned());
+        }
+
+        self.output = output.clone();
+
+        if space.output_geometry(output).is_some() {
+            self.reset_gaps(space);
+            self.update_layout(space);
+            self.update_borders();
+        }
+    }
+
     pub fn reset_gaps(&self, space: &Space) {
-        let gaps = CONFIG.gaps as i32;
-        let geometry = space
-            .output_geometry(&self.output)
-            .expect("Output should have a geometry");
+        let gaps = self.root.get().gaps;
+        let geometry = match tiling_area(space, &self.output) {
+            Some(geometry) => geometry,
+            None => return,
+        };
         let mut container = self.root.get_mut();
         container.location = (geometry.loc.x + gaps, geometry.loc.y + gaps).into();
         container.size = (geometry.size.w - 2 * gaps, geometry.size.h - 2 * gaps).into();
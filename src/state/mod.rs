@@ -1,19 +1,32 @@
+use crate::config::keybinding::Action;
+use crate::inputs::chord::PendingChord;
+use crate::ipc::events::IpcEvents;
+use crate::ipc::server::IpcServer;
+use crate::process::{ChildProcesses, SpawnedChild};
+use crate::shell::animation::WorkspaceTransition;
 use crate::shell::container::ContainerLayout;
+use crate::shell::focus_history::FocusHistory;
+use crate::shell::overview::Overview;
+use crate::shell::switcher::WindowSwitcher;
 use crate::shell::workspace::WorkspaceRef;
 
-use smithay::desktop::{PopupManager, WindowSurfaceType};
+use smithay::desktop::utils::under_from_surface_tree;
+use smithay::desktop::{layer_map_for_output, LayerSurface, PopupManager, WindowSurfaceType};
 
-use smithay::reexports::calloop::{LoopHandle, LoopSignal};
+use smithay::reexports::calloop::{LoopHandle, LoopSignal, RegistrationToken};
+use smithay::reexports::input;
 use smithay::reexports::wayland_server::backend::{ClientData, ClientId, DisconnectReason};
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::reexports::wayland_server::{Display, DisplayHandle};
+use smithay::reexports::wayland_server::{Display, DisplayHandle, Resource};
 use smithay::utils::{Logical, Point};
 use smithay::wayland::compositor::CompositorState;
 use smithay::wayland::data_device::DataDeviceState;
 use smithay::output::Output;
 
+use smithay::input::keyboard::ModifiersState;
 use smithay::input::pointer::PointerHandle;
 use smithay::input::{Seat, SeatState};
+use smithay::wayland::shell::wlr_layer::{Layer, WlrLayerShellState};
 use smithay::wayland::shell::xdg::XdgShellState;
 use smithay::wayland::shm::ShmState;
 
@@ -25,6 +38,7 @@ use smithay::wayland::shell::xdg::decoration::XdgDecorationState;
 
 use std::collections::HashMap;
 use std::ffi::OsString;
+use std::path::PathBuf;
 
 use crate::config::WazemmesConfig;
 use smithay::wayland::primary_selection::PrimarySelectionState;
@@ -36,6 +50,7 @@ use crate::backend::xwayland::X11State;
 #[cfg(feature = "xwayland")]
 use smithay::xwayland::XWayland;
 use crate::handlers::screencopy::ScreenCopyManagerState;
+use crate::handlers::session_lock::{SessionLock, SessionLockManagerState};
 
 pub mod output;
 pub mod seat;
@@ -51,15 +66,25 @@ pub struct Wazemmes {
     pub compositor_state: CompositorState,
     pub xdg_shell_state: XdgShellState,
     pub xdg_decoration_state: XdgDecorationState,
+    pub layer_shell_state: WlrLayerShellState,
     pub primary_selection_state: PrimarySelectionState,
     pub shm_state: ShmState,
     pub _output_manager_state: OutputManagerState,
     pub screen_copy_manager_state: ScreenCopyManagerState,
+    pub session_lock_manager_state: SessionLockManagerState,
     pub seat_state: SeatState<Self>,
     pub data_device_state: DataDeviceState,
     pub dmabuf_state: DmabufState,
     pub pointer_icon: PointerIcon,
     pub backend: BackendState,
+    /// Every output reported by the backend, disabled ones are not mapped in `space`
+    pub outputs: Vec<Output>,
+    /// Programs spawned by keybindings and autostart
+    pub children: ChildProcesses,
+    /// Connected libinput devices, configured again when the config is reloaded
+    pub input_devices: Vec<input::Device>,
+    /// Events waiting to be sent to IPC subscribers
+    pub ipc_events: IpcEvents,
     pub socket_name: OsString,
 
     #[cfg(feature = "xwayland")]
@@ -68,10 +93,32 @@ pub struct Wazemmes {
     pub x11_state: Option<X11State>,
 
     // Shell
-    pub mod_pressed: bool,
+    /// Name of the active binding mode, see `WazemmesConfig::modes`
+    pub binding_mode: String,
+    /// Index of the active layout in `KeyboardConfig::layout`
+    pub keyboard_layout: usize,
+    /// Keys of a chord binding pressed so far
+    pub pending_chord: Option<PendingChord>,
+    /// Timer clearing `pending_chord` once `chord_timeout` elapsed
+    pub chord_timer: Option<RegistrationToken>,
+    /// Keycode of a pressed `on_release` binding and the action to run when it is released
+    pub pending_release: Option<(u32, Action)>,
+    /// Button which press triggered a mouse binding, its release is not sent to clients
+    pub intercepted_button: Option<u32>,
+    /// Scroll amount not yet consumed by mouse bindings
+    pub scroll_accumulator: (f64, f64),
+    pub modifiers: ModifiersState,
+    pub focus_history: FocusHistory,
+    pub window_switcher: Option<WindowSwitcher>,
+    pub overview: Option<Overview>,
+    /// Layer surface the keyboard was given to, focus goes back to the window once it lets go
+    pub layer_focus: Option<WlSurface>,
+    /// Only lock surfaces are shown and get input while the session is locked
+    pub session_lock: SessionLock,
     pub workspaces: HashMap<u8, WorkspaceRef>,
     pub current_workspace: u8,
     pub next_layout: Option<ContainerLayout>,
+    pub workspace_transition: Option<WorkspaceTransition>,
 }
 
 impl Wazemmes {
@@ -79,11 +126,64 @@ impl Wazemmes {
         &self,
         pointer: &PointerHandle<Self>,
     ) -> Option<(WlSurface, Point<i32, Logical>)> {
-        let pos = pointer.current_location();
+        self.surface_under(pointer.current_location())
+    }
+
+    /// The surface under `position` and its location, layer surfaces included
+    pub fn surface_under(
+        &self,
+        position: Point<f64, Logical>,
+    ) -> Option<(WlSurface, Point<i32, Logical>)> {
+        if self.session_lock.is_locked() {
+            let output = self.space.output_under(position).next()?;
+            let surface = self.session_lock.surface_for(output)?;
+            let location = self.space.output_geometry(output)?.loc;
+            return under_from_surface_tree(surface, position, location, WindowSurfaceType::all());
+        }
+
+        if let Some((layer, location)) = self.layer_under(position) {
+            return layer
+                .surface_under(position - location.to_f64(), WindowSurfaceType::all())
+                .map(|(surface, loc)| (surface, loc + location));
+        }
+
         self.space
-            .surface_under(pos, WindowSurfaceType::all())
+            .surface_under(position, WindowSurfaceType::all())
             .map(|(_, surface, location)| (surface, location))
     }
+
+    /// The layer surface under `position` and its location, the top and overlay
+    /// layers are above windows while the bottom and background ones are below them
+    pub fn layer_under(
+        &self,
+        position: Point<f64, Logical>,
+    ) -> Option<(LayerSurface, Point<i32, Logical>)> {
+        let output = self.space.output_under(position).next()?;
+        let output_location = self.space.output_geometry(output)?.loc;
+        let map = layer_map_for_output(output);
+        let layer_at = |layer| {
+            let surface = map.layer_under(layer, position - output_location.to_f64())?;
+            let geometry = map.layer_geometry(surface)?;
+            Some((surface.clone(), geometry.loc + output_location))
+        };
+
+        layer_at(Layer::Overlay)
+            .or_else(|| layer_at(Layer::Top))
+            .or_else(|| {
+                if self.space.window_under(position).is_some() {
+                    return None;
+                }
+
+                layer_at(Layer::Bottom).or_else(|| layer_at(Layer::Background))
+            })
+    }
+
+    /// The spawned command which started the client owning `surface`
+    pub fn window_launcher(&self, surface: &WlSurface) -> Option<&SpawnedChild> {
+        let client = self.display.get_client(surface.id()).ok()?;
+        let credentials = client.get_credentials(&self.display).ok()?;
+        self.children.launcher_of(credentials.pid as u32)
+    }
 }
 
 pub struct ClientState;
@@ -102,5 +202,9 @@ pub trait Backend {
 pub struct CallLoopData {
     pub state: Wazemmes,
     pub config: WazemmesConfig,
+    /// Config file given on the command line, it replaces the XDG lookup
+    pub config_path: Option<PathBuf>,
     pub display: Display<Wazemmes>,
+    /// Unset when the IPC socket could not be bound
+    pub ipc: Option<IpcServer>,
 }
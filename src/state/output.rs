@@ -1,8 +1,13 @@
 use smithay::output::Output;
+use smithay::reexports::wayland_server::backend::GlobalId;
+use smithay::utils::Transform;
+use std::cell::Cell;
 
 #[derive(Default, Debug)]
 pub struct OutputState {
     fps: fps_ticker::Fps,
+    /// Set when a frame was drawn without the space, which damage history is then wrong
+    damage_reset: Cell<bool>,
 }
 
 impl OutputState {
@@ -14,4 +19,36 @@ impl OutputState {
     pub fn fps_tick(&self) {
         self.fps.tick();
     }
+
+    pub fn reset_damage(&self) {
+        self.damage_reset.set(true);
+    }
+
+    /// Buffer age to give to the space, 0 forces a full redraw after `reset_damage`
+    pub fn buffer_age(&self, age: usize) -> usize {
+        if self.damage_reset.replace(false) {
+            0
+        } else {
+            age
+        }
+    }
+}
+
+/// What the backend reported for an output, kept to match it against the config
+/// and to restore its defaults when the config changes
+#[derive(Debug, Clone)]
+pub struct OutputInfo {
+    pub serial: String,
+    pub default_transform: Transform,
+    /// The `wl_output` global, removed when the output is disconnected
+    pub global: GlobalId,
+}
+
+impl OutputInfo {
+    pub fn for_output(output: &Output) -> &Self {
+        output
+            .user_data()
+            .get::<Self>()
+            .expect("OutputInfo is inserted when the output is created")
+    }
 }